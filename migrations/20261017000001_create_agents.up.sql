@@ -0,0 +1,17 @@
+CREATE TABLE agents (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    agent_type TEXT NOT NULL,
+    endpoint TEXT,
+    api_key TEXT NOT NULL,
+    permissions TEXT[] NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL,
+    config JSONB NOT NULL,
+    usage_stats JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_used TIMESTAMPTZ
+);
+
+CREATE INDEX agents_user_id_idx ON agents (user_id);
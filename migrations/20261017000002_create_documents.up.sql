@@ -0,0 +1,14 @@
+CREATE TABLE documents (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    doc_type TEXT NOT NULL,
+    source TEXT NOT NULL,
+    size TEXT NOT NULL,
+    tags TEXT[] NOT NULL DEFAULT '{}',
+    status TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX documents_user_id_idx ON documents (user_id);
@@ -0,0 +1,8 @@
+DROP INDEX IF EXISTS urls_user_id_idx;
+ALTER TABLE urls DROP COLUMN IF EXISTS workspace_id, DROP COLUMN IF EXISTS user_id;
+
+DROP INDEX IF EXISTS repositories_user_id_idx;
+ALTER TABLE repositories DROP COLUMN IF EXISTS workspace_id, DROP COLUMN IF EXISTS user_id;
+
+ALTER TABLE documents DROP COLUMN IF EXISTS workspace_id;
+ALTER TABLE agents DROP COLUMN IF EXISTS workspace_id;
@@ -0,0 +1,32 @@
+ALTER TABLE agents ADD COLUMN workspace_id TEXT;
+ALTER TABLE documents ADD COLUMN workspace_id TEXT;
+
+ALTER TABLE repositories
+    ADD COLUMN user_id TEXT,
+    ADD COLUMN workspace_id TEXT;
+
+ALTER TABLE urls
+    ADD COLUMN user_id TEXT,
+    ADD COLUMN workspace_id TEXT;
+
+-- Repositories and URLs created before this migration have no recorded
+-- owner. Assign them to the user named by the `api_backend.legacy_owner`
+-- setting, or refuse to migrate rather than leave rows nobody can see.
+DO $$
+DECLARE
+    legacy_owner TEXT := NULLIF(current_setting('api_backend.legacy_owner', true), '');
+BEGIN
+    IF legacy_owner IS NOT NULL THEN
+        UPDATE repositories SET user_id = legacy_owner WHERE user_id IS NULL;
+        UPDATE urls SET user_id = legacy_owner WHERE user_id IS NULL;
+    ELSIF EXISTS (SELECT 1 FROM repositories) OR EXISTS (SELECT 1 FROM urls) THEN
+        RAISE EXCEPTION 'existing repositories or urls have no owner; set api_backend.legacy_owner to the user who owns them and run the migration again';
+    END IF;
+END
+$$;
+
+ALTER TABLE repositories ALTER COLUMN user_id SET NOT NULL;
+CREATE INDEX repositories_user_id_idx ON repositories (user_id);
+
+ALTER TABLE urls ALTER COLUMN user_id SET NOT NULL;
+CREATE INDEX urls_user_id_idx ON urls (user_id);
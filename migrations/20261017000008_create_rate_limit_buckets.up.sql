@@ -0,0 +1,7 @@
+-- Gateways before this migration created the table on startup
+CREATE TABLE IF NOT EXISTS rate_limit_buckets (
+    key TEXT PRIMARY KEY,
+    tat BIGINT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS rate_limit_buckets_tat_idx ON rate_limit_buckets (tat);
@@ -0,0 +1,2 @@
+-- Sources whose webhook secret signed the delivery; sync events are published only for these
+ALTER TABLE webhook_deliveries ADD COLUMN verified_sources JSONB NOT NULL DEFAULT '[]';
@@ -1,67 +1,87 @@
 //! Auth Middleware client for authentication/authorization
 
-use reqwest::Client;
+use std::sync::Arc;
 
 use crate::error::AppError;
-use crate::models::{User, ApiKeyInfo, TokenPair};
-use super::base::{create_http_client, handle_service_response};
+use crate::middleware::CircuitBreakerRegistry;
+use crate::models::{User, ApiKeyInfo, TokenPair, WorkspaceMembership};
+use super::base::{handle_service_response, ServiceClient};
 
 /// Client for auth-middleware service
 #[derive(Clone)]
 pub struct AuthClient {
-    client: Client,
+    client: ServiceClient,
     base_url: String,
 }
 
 impl AuthClient {
     /// Create a new auth client
-    pub fn new(base_url: &str) -> Result<Self, AppError> {
+    pub fn new(base_url: &str, breaker: Arc<CircuitBreakerRegistry>) -> Result<Self, AppError> {
         Ok(Self {
-            client: create_http_client(5)?, // 5 second timeout
+            client: ServiceClient::new("auth-middleware", 5, breaker)?, // 5 second timeout
             base_url: base_url.trim_end_matches('/').to_string(),
         })
     }
     
     /// Verify a JWT token
     pub async fn verify_token(&self, token: &str) -> Result<User, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .get(format!("{}/api/auth/verify", self.base_url))
-            .header("Authorization", format!("Bearer {}", token))
-            .send()
-            .await?;
+            .header("Authorization", format!("Bearer {}", token));
         
-        handle_service_response(response, "auth-middleware").await
+        self.client.call(request).await
     }
     
     /// Validate an API key
     pub async fn validate_api_key(&self, api_key: &str) -> Result<ApiKeyInfo, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/auth/api-keys/validate", self.base_url))
-            .json(&serde_json::json!({ "apiKey": api_key }))
-            .send()
-            .await?;
+            .json(&serde_json::json!({ "apiKey": api_key }));
         
-        handle_service_response(response, "auth-middleware").await
+        self.client.call(request).await
+    }
+    
+    /// Workspaces a user belongs to
+    pub async fn workspace_memberships(&self, user_id: &str) -> Result<Vec<WorkspaceMembership>, AppError> {
+        let request = self.client
+            .http()
+            .get(format!("{}/api/users/{}/workspaces", self.base_url, user_id));
+        
+        self.client.call(request).await
     }
     
     /// Refresh an access token
     pub async fn refresh_token(&self, refresh_token: &str) -> Result<TokenPair, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/auth/refresh", self.base_url))
-            .json(&serde_json::json!({ "refreshToken": refresh_token }))
-            .send()
-            .await?;
+            .json(&serde_json::json!({ "refreshToken": refresh_token }));
+        
+        self.client.call(request).await
+    }
+    
+    /// End a session, revoking its refresh token
+    pub async fn logout(&self, access_token: &str, refresh_token: Option<&str>) -> Result<(), AppError> {
+        let request = self.client
+            .http()
+            .post(format!("{}/api/auth/logout", self.base_url))
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&serde_json::json!({ "refreshToken": refresh_token }));
         
-        handle_service_response(response, "auth-middleware").await
+        // Success responses may have no body
+        let response = self.client.send(request).await?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        handle_service_response::<serde::de::IgnoredAny>(response, self.client.service())
+            .await
+            .map(|_| ())
     }
     
     /// Health check
     pub async fn health_check(&self) -> bool {
-        self.client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
+        self.client.health_check(&self.base_url).await
     }
 }
@@ -1,9 +1,16 @@
 //! Base client utilities for service communication
 
-use reqwest::Client;
-use std::time::Duration;
+use reqwest::header::HeaderValue;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use crate::error::AppError;
+use crate::metrics::METRICS;
+use crate::request_context::RequestContext;
+use crate::telemetry;
+use crate::middleware::CircuitBreakerRegistry;
 
 /// Create a configured HTTP client
 pub fn create_http_client(timeout_secs: u64) -> Result<Client, AppError> {
@@ -14,6 +21,110 @@ pub fn create_http_client(timeout_secs: u64) -> Result<Client, AppError> {
         .map_err(|e| AppError::Internal(format!("Failed to create HTTP client: {}", e)))
 }
 
+/// HTTP client for one downstream service, guarded by that service's circuit breaker
+///
+/// Every call is checked against the breaker first and fails fast with
+/// `ServiceUnavailable` while it is open. Timeouts, connection errors and
+/// 5xx responses count as failures; 4xx responses mean the service is
+/// healthy and count as successes.
+#[derive(Clone)]
+pub struct ServiceClient {
+    http: Client,
+    service: &'static str,
+    breaker: Arc<CircuitBreakerRegistry>,
+}
+
+impl ServiceClient {
+    pub fn new(
+        service: &'static str,
+        timeout_secs: u64,
+        breaker: Arc<CircuitBreakerRegistry>,
+    ) -> Result<Self, AppError> {
+        breaker.register(service);
+        Ok(Self {
+            http: create_http_client(timeout_secs)?,
+            service,
+            breaker,
+        })
+    }
+
+    /// Underlying HTTP client, for building requests
+    pub fn http(&self) -> &Client {
+        &self.http
+    }
+
+    /// Service name used for breaker state and error messages
+    pub fn service(&self) -> &'static str {
+        self.service
+    }
+
+    /// Send a request through the circuit breaker
+    ///
+    /// The current request's correlation ID, user context and trace context
+    /// are added as headers unless the request already sets them.
+    pub async fn send(&self, request: RequestBuilder) -> Result<Response, AppError> {
+        let (client, request) = request.build_split();
+        let mut request = request?;
+        if let Some(ctx) = RequestContext::current() {
+            for (name, value) in ctx.headers() {
+                if let Ok(value) = HeaderValue::from_str(value) {
+                    request.headers_mut().entry(name).or_insert(value);
+                }
+            }
+        }
+        telemetry::inject_headers(request.headers_mut());
+
+        let start = Instant::now();
+        let Some(permit) = self.breaker.try_acquire(self.service) else {
+            METRICS.observe_downstream(self.service, "rejected", start.elapsed());
+            return Err(AppError::ServiceUnavailable(format!(
+                "{} is unavailable (circuit open)",
+                self.service
+            )));
+        };
+
+        let result = client.execute(request).await;
+        let failed = match &result {
+            Ok(response) => response.status().is_server_error(),
+            Err(e) => is_breaker_failure(e),
+        };
+
+        if failed {
+            permit.failure();
+        } else {
+            permit.success();
+        }
+        METRICS.observe_downstream(
+            self.service,
+            if failed { "failure" } else { "success" },
+            start.elapsed(),
+        );
+
+        result.map_err(Into::into)
+    }
+
+    /// Send a request through the circuit breaker and decode the response
+    pub async fn call<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, AppError> {
+        let response = self.send(request).await?;
+        handle_service_response(response, self.service).await
+    }
+
+    /// Health check that bypasses the breaker so probes always reach the service
+    pub async fn health_check(&self, base_url: &str) -> bool {
+        self.http
+            .get(format!("{}/health", base_url))
+            .send()
+            .await
+            .map(|r| r.status().is_success())
+            .unwrap_or(false)
+    }
+}
+
+/// Whether a transport error implicates the downstream service
+fn is_breaker_failure(err: &reqwest::Error) -> bool {
+    err.is_timeout() || err.is_connect() || err.is_request()
+}
+
 /// Handle service call errors consistently
 pub async fn handle_service_response<T: serde::de::DeserializeOwned>(
     response: reqwest::Response,
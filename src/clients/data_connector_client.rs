@@ -1,24 +1,25 @@
 //! Data Connector client for source management
 
-use reqwest::Client;
+use std::sync::Arc;
 use serde::Serialize;
 
 use crate::error::AppError;
+use crate::middleware::CircuitBreakerRegistry;
 use crate::models::{Source, SourceCreateRequest, SyncJob, JobStatusResponse, SourcesListResponse};
-use super::base::{create_http_client, handle_service_response};
+use super::base::ServiceClient;
 
 /// Client for data-connector service
 #[derive(Clone)]
 pub struct DataConnectorClient {
-    client: Client,
+    client: ServiceClient,
     base_url: String,
 }
 
 impl DataConnectorClient {
     /// Create a new data connector client
-    pub fn new(base_url: &str) -> Result<Self, AppError> {
+    pub fn new(base_url: &str, breaker: Arc<CircuitBreakerRegistry>) -> Result<Self, AppError> {
         Ok(Self {
-            client: create_http_client(30)?, // 30 second timeout for sync ops
+            client: ServiceClient::new("data-connector", 30, breaker)?, // 30 second timeout for sync ops
             base_url: base_url.trim_end_matches('/').to_string(),
         })
     }
@@ -42,24 +43,38 @@ impl DataConnectorClient {
             url = format!("{}?{}", url, params.join("&"));
         }
         
-        let response = self.client
+        let request = self.client
+            .http()
             .get(&url)
-            .header("X-User-Id", user_id)
-            .send()
-            .await?;
+            .header("X-User-Id", user_id);
+        
+        self.client.call(request).await
+    }
+    
+    /// Find the sources, across all users, that index a repository
+    ///
+    /// `url` is the repository's canonical `https://host/owner/repo` form.
+    pub async fn find_sources_by_repository(
+        &self,
+        source_type: &str,
+        url: &str,
+    ) -> Result<SourcesListResponse, AppError> {
+        let request = self.client
+            .http()
+            .get(format!("{}/sources/by-repository", self.base_url))
+            .query(&[("type", source_type), ("url", url)]);
         
-        handle_service_response(response, "data-connector").await
+        self.client.call(request).await
     }
     
     /// Get a specific source
     pub async fn get_source(&self, user_id: &str, source_id: &str) -> Result<Source, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .get(format!("{}/sources/{}", self.base_url, source_id))
-            .header("X-User-Id", user_id)
-            .send()
-            .await?;
+            .header("X-User-Id", user_id);
         
-        handle_service_response(response, "data-connector").await
+        self.client.call(request).await
     }
     
     /// Create a new source
@@ -68,23 +83,22 @@ impl DataConnectorClient {
         user_id: &str,
         request: &SourceCreateRequest,
     ) -> Result<Source, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/sources", self.base_url))
             .header("X-User-Id", user_id)
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "data-connector").await
+        self.client.call(request).await
     }
     
     /// Delete a source
     pub async fn delete_source(&self, user_id: &str, source_id: &str) -> Result<(), AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .delete(format!("{}/sources/{}", self.base_url, source_id))
-            .header("X-User-Id", user_id)
-            .send()
-            .await?;
+            .header("X-User-Id", user_id);
+        let response = self.client.send(request).await?;
         
         if response.status().is_success() {
             Ok(())
@@ -103,23 +117,21 @@ impl DataConnectorClient {
             source_id: String,
         }
         
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/ingest", self.base_url))
-            .json(&IngestRequest { source_id: source_id.to_string() })
-            .send()
-            .await?;
+            .json(&IngestRequest { source_id: source_id.to_string() });
         
-        handle_service_response(response, "data-connector").await
+        self.client.call(request).await
     }
     
     /// Get job status
     pub async fn get_job_status(&self, job_id: &str) -> Result<JobStatusResponse, AppError> {
-        let response = self.client
-            .get(format!("{}/jobs/{}", self.base_url, job_id))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/jobs/{}", self.base_url, job_id));
         
-        handle_service_response(response, "data-connector").await
+        self.client.call(request).await
     }
     
     /// Forward webhook payload
@@ -130,6 +142,7 @@ impl DataConnectorClient {
         headers: Vec<(String, String)>,
     ) -> Result<serde_json::Value, AppError> {
         let mut request = self.client
+            .http()
             .post(format!("{}/webhooks/{}", self.base_url, provider))
             .json(&payload);
         
@@ -137,17 +150,11 @@ impl DataConnectorClient {
             request = request.header(&key, &value);
         }
         
-        let response = request.send().await?;
-        handle_service_response(response, "data-connector").await
+        self.client.call(request).await
     }
     
     /// Health check
     pub async fn health_check(&self) -> bool {
-        self.client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
+        self.client.health_check(&self.base_url).await
     }
 }
@@ -1,4 +1,10 @@
-use tonic::transport::Channel;
+use tonic::service::interceptor::InterceptedService;
+use tonic::transport::{Channel, Endpoint};
+
+use tonic::metadata::{MetadataKey, MetadataValue};
+
+use crate::request_context::RequestContext;
+use crate::telemetry;
 
 pub mod auth {
     tonic::include_proto!("confuse.auth.v1");
@@ -28,16 +34,51 @@ pub mod client {
     tonic::include_proto!("confuse.client.v1");
 }
 
+/// Interceptor adding the current request's correlation ID, user context
+/// and W3C trace context to call metadata
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ContextInterceptor;
+
+impl tonic::service::Interceptor for ContextInterceptor {
+    fn call(&mut self, mut request: tonic::Request<()>) -> Result<tonic::Request<()>, tonic::Status> {
+        let ctx = RequestContext::current().unwrap_or_default();
+        let context_headers = ctx.headers().into_iter().map(|(k, v)| (k.to_string(), v.to_string()));
+
+        for (key, value) in context_headers.chain(telemetry::current_trace_headers()) {
+            if let (Ok(key), Ok(value)) = (
+                MetadataKey::from_bytes(key.to_ascii_lowercase().as_bytes()),
+                MetadataValue::try_from(value.as_str()),
+            ) {
+                request.metadata_mut().insert(key, value);
+            }
+        }
+        Ok(request)
+    }
+}
+
+/// Channel that attaches request context to every call
+pub type TracedChannel = InterceptedService<Channel, ContextInterceptor>;
+
+async fn traced_channel(url: String) -> Result<TracedChannel, tonic::transport::Error> {
+    let channel = Endpoint::new(url)?.connect().await?;
+    Ok(InterceptedService::new(channel, ContextInterceptor))
+}
+
+fn lazy_traced_channel(url: String) -> Result<TracedChannel, tonic::transport::Error> {
+    let channel = Endpoint::new(url)?.connect_lazy();
+    Ok(InterceptedService::new(channel, ContextInterceptor))
+}
+
 // Client wrappers
 #[derive(Clone)]
 pub struct GrpcClients {
-    pub auth: auth::auth_client::AuthClient<Channel>,
-    pub graph: graph::relation_graph_client::RelationGraphClient<Channel>,
-    pub processor: processor::unified_processor_client::UnifiedProcessorClient<Channel>,
-    pub embeddings: embeddings::embeddings_client::EmbeddingsClient<Channel>,
-    pub mcp: mcp::mcp_client::McpClient<Channel>,
-    pub data_connector: connector::data_connector_client::DataConnectorClient<Channel>,
-    pub client_connector: client::client_connector_client::ClientConnectorClient<Channel>,
+    pub auth: auth::auth_client::AuthClient<TracedChannel>,
+    pub graph: graph::relation_graph_client::RelationGraphClient<TracedChannel>,
+    pub processor: processor::unified_processor_client::UnifiedProcessorClient<TracedChannel>,
+    pub embeddings: embeddings::embeddings_client::EmbeddingsClient<TracedChannel>,
+    pub mcp: mcp::mcp_client::McpClient<TracedChannel>,
+    pub data_connector: connector::data_connector_client::DataConnectorClient<TracedChannel>,
+    pub client_connector: client::client_connector_client::ClientConnectorClient<TracedChannel>,
 }
 
 impl GrpcClients {
@@ -50,22 +91,55 @@ impl GrpcClients {
         connector_url: String,
         client_url: String,
     ) -> Result<Self, tonic::transport::Error> {
-        let auth = auth::auth_client::AuthClient::connect(auth_url).await?;
-        let graph = graph::relation_graph_client::RelationGraphClient::connect(graph_url).await?;
-        let processor = processor::unified_processor_client::UnifiedProcessorClient::connect(processor_url).await?;
-        let embeddings = embeddings::embeddings_client::EmbeddingsClient::connect(embeddings_url).await?;
-        let mcp = mcp::mcp_client::McpClient::connect(mcp_url).await?;
-        let data_connector = connector::data_connector_client::DataConnectorClient::connect(connector_url).await?;
-        let client_connector = client::client_connector_client::ClientConnectorClient::connect(client_url).await?;
-
-        Ok(Self {
-            auth,
-            graph,
-            processor,
-            embeddings,
-            mcp,
-            data_connector,
-            client_connector,
-        })
+        Ok(Self::from_channels(
+            traced_channel(auth_url).await?,
+            traced_channel(graph_url).await?,
+            traced_channel(processor_url).await?,
+            traced_channel(embeddings_url).await?,
+            traced_channel(mcp_url).await?,
+            traced_channel(connector_url).await?,
+            traced_channel(client_url).await?,
+        ))
+    }
+
+    /// Clients whose channels connect on their first call instead of up front
+    pub fn connect_lazy(
+        auth_url: String,
+        graph_url: String,
+        processor_url: String,
+        embeddings_url: String,
+        mcp_url: String,
+        connector_url: String,
+        client_url: String,
+    ) -> Result<Self, tonic::transport::Error> {
+        Ok(Self::from_channels(
+            lazy_traced_channel(auth_url)?,
+            lazy_traced_channel(graph_url)?,
+            lazy_traced_channel(processor_url)?,
+            lazy_traced_channel(embeddings_url)?,
+            lazy_traced_channel(mcp_url)?,
+            lazy_traced_channel(connector_url)?,
+            lazy_traced_channel(client_url)?,
+        ))
+    }
+
+    fn from_channels(
+        auth: TracedChannel,
+        graph: TracedChannel,
+        processor: TracedChannel,
+        embeddings: TracedChannel,
+        mcp: TracedChannel,
+        data_connector: TracedChannel,
+        client_connector: TracedChannel,
+    ) -> Self {
+        Self {
+            auth: auth::auth_client::AuthClient::new(auth),
+            graph: graph::relation_graph_client::RelationGraphClient::new(graph),
+            processor: processor::unified_processor_client::UnifiedProcessorClient::new(processor),
+            embeddings: embeddings::embeddings_client::EmbeddingsClient::new(embeddings),
+            mcp: mcp::mcp_client::McpClient::new(mcp),
+            data_connector: connector::data_connector_client::DataConnectorClient::new(data_connector),
+            client_connector: client::client_connector_client::ClientConnectorClient::new(client_connector),
+        }
     }
 }
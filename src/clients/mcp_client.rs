@@ -1,35 +1,35 @@
 //! MCP Server client for tool operations
 
-use reqwest::Client;
+use std::sync::Arc;
 
 use crate::error::AppError;
+use crate::middleware::CircuitBreakerRegistry;
 use crate::models::{McpCapabilities, McpToolResult};
-use super::base::{create_http_client, handle_service_response};
+use super::base::ServiceClient;
 
 /// Client for mcp-server service
 #[derive(Clone)]
 pub struct McpClient {
-    client: Client,
+    client: ServiceClient,
     base_url: String,
 }
 
 impl McpClient {
     /// Create a new MCP client
-    pub fn new(base_url: &str) -> Result<Self, AppError> {
+    pub fn new(base_url: &str, breaker: Arc<CircuitBreakerRegistry>) -> Result<Self, AppError> {
         Ok(Self {
-            client: create_http_client(30)?, // 30 second timeout for tool calls
+            client: ServiceClient::new("mcp-server", 30, breaker)?, // 30 second timeout for tool calls
             base_url: base_url.trim_end_matches('/').to_string(),
         })
     }
     
     /// List available tools
     pub async fn list_tools(&self) -> Result<McpCapabilities, AppError> {
-        let response = self.client
-            .get(format!("{}/tools", self.base_url))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/tools", self.base_url));
         
-        handle_service_response(response, "mcp-server").await
+        self.client.call(request).await
     }
     
     /// Call a tool
@@ -38,25 +38,19 @@ impl McpClient {
         name: &str,
         arguments: serde_json::Value,
     ) -> Result<McpToolResult, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/tools/call", self.base_url))
             .json(&serde_json::json!({
                 "name": name,
                 "arguments": arguments
-            }))
-            .send()
-            .await?;
+            }));
         
-        handle_service_response(response, "mcp-server").await
+        self.client.call(request).await
     }
     
     /// Health check
     pub async fn health_check(&self) -> bool {
-        self.client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
+        self.client.health_check(&self.base_url).await
     }
 }
@@ -3,11 +3,12 @@
 //! This client communicates with the relation-graph service (Graphiti-powered),
 //! providing temporal knowledge graph capabilities.
 
-use reqwest::Client;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 use crate::error::AppError;
-use super::base::{create_http_client, handle_service_response};
+use crate::middleware::CircuitBreakerRegistry;
+use super::base::ServiceClient;
 
 // ==============================================================================
 // Request/Response Types
@@ -175,196 +176,175 @@ pub struct EpisodeAddedData {
 /// Client for relation-graph service (Graphiti-powered)
 #[derive(Clone)]
 pub struct RelationGraphClient {
-    client: Client,
+    client: ServiceClient,
     base_url: String,
 }
 
 impl RelationGraphClient {
     /// Create a new relation graph client
-    pub fn new(base_url: &str) -> Result<Self, AppError> {
+    pub fn new(base_url: &str, breaker: Arc<CircuitBreakerRegistry>) -> Result<Self, AppError> {
         Ok(Self {
-            client: create_http_client(30)?, // 30 second timeout
+            client: ServiceClient::new("relation-graph", 30, breaker)?, // 30 second timeout
             base_url: base_url.trim_end_matches('/').to_string(),
         })
     }
     
     /// Build relationships for a source (legacy compatibility)
     pub async fn build_relationships(&self, request: &BuildRelationshipsRequest) -> Result<GraphServiceResponse<BuildResponseData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/build", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Temporal search over knowledge graph
     pub async fn temporal_search(&self, request: &TemporalSearchRequest) -> Result<GraphServiceResponse<TemporalSearchData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/temporal-search", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Simple search (GET)
     pub async fn search_simple(&self, query: &str, limit: u32) -> Result<GraphServiceResponse<TemporalSearchData>, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/search?query={}&limit={}", self.base_url, query, limit))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/search?query={}&limit={}", self.base_url, query, limit));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get entity evolution
     pub async fn get_entity_evolution(&self, entity_name: &str) -> Result<GraphServiceResponse<EntityEvolutionData>, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/entity-evolution/{}", self.base_url, entity_name))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/entity-evolution/{}", self.base_url, entity_name));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get entity evolution with time range
     pub async fn get_entity_evolution_detailed(&self, request: &EntityEvolutionRequest) -> Result<GraphServiceResponse<EntityEvolutionData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/entity-evolution", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Add an episode to the knowledge graph
     pub async fn add_episode(&self, request: &AddEpisodeRequest) -> Result<GraphServiceResponse<EpisodeAddedData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/episodes", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get relationships for a source (legacy compatibility)
     pub async fn get_relationships(&self, source_id: &str) -> Result<GraphServiceResponse<TemporalSearchData>, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/relationships/{}", self.base_url, source_id))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/relationships/{}", self.base_url, source_id));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get context for a chunk (legacy compatibility)
     pub async fn get_context_legacy(&self, chunk_id: &str) -> Result<GraphServiceResponse<TemporalSearchData>, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/context/{}", self.base_url, chunk_id))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/context/{}", self.base_url, chunk_id));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get related chunks (legacy compatibility)
     pub async fn get_related(&self, chunk_id: &str) -> Result<GraphServiceResponse<TemporalSearchData>, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/related/{}", self.base_url, chunk_id))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/related/{}", self.base_url, chunk_id));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get graph statistics
     pub async fn get_stats(&self) -> Result<serde_json::Value, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/stats", self.base_url))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/stats", self.base_url));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get service status
     pub async fn get_status(&self) -> Result<serde_json::Value, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/status", self.base_url))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/status", self.base_url));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Health check
     pub async fn health_check(&self) -> bool {
-        self.client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
+        self.client.health_check(&self.base_url).await
     }
     
     /// Unified search (hybrid vector + graph)
     pub async fn search(&self, request: &crate::models::SearchRequest) -> Result<crate::models::SearchResponse, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/search", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Vector-only search
     pub async fn search_vector(&self, request: &crate::models::SearchRequest) -> Result<crate::models::SearchResponse, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/search/vector", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Graph-only search
     pub async fn search_graph(&self, request: &crate::models::SearchRequest) -> Result<crate::models::SearchResponse, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/search/graph", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get entity by ID
     pub async fn get_entity(&self, entity_id: &str, hops: u32) -> Result<crate::models::Entity, AppError> {
         let url = format!("{}/api/v1/entities/{}?hops={}", self.base_url, entity_id, hops);
         
-        let response = self.client
-            .get(url)
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(url);
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
     
     /// Get context for a chunk (for MCP)
     pub async fn get_context(&self, chunk_id: &str) -> Result<serde_json::Value, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/context/{}", self.base_url, chunk_id))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/context/{}", self.base_url, chunk_id));
         
-        handle_service_response(response, "relation-graph").await
+        self.client.call(request).await
     }
 }
 
@@ -5,11 +5,12 @@
 //! - doc-parser (Port 3019)
 //! - embeddings (Port 3001)
 
-use reqwest::Client;
+use std::sync::Arc;
 use serde::{Deserialize, Serialize};
 
 use crate::error::AppError;
-use super::base::{create_http_client, handle_service_response};
+use crate::middleware::CircuitBreakerRegistry;
+use super::base::ServiceClient;
 
 // ==============================================================================
 // Request/Response Types
@@ -204,113 +205,100 @@ pub struct SearchData {
 /// Client for unified-processor service
 #[derive(Clone)]
 pub struct UnifiedProcessorClient {
-    client: Client,
+    client: ServiceClient,
     base_url: String,
 }
 
 impl UnifiedProcessorClient {
     /// Create a new unified processor client
-    pub fn new(base_url: &str) -> Result<Self, AppError> {
+    pub fn new(base_url: &str, breaker: Arc<CircuitBreakerRegistry>) -> Result<Self, AppError> {
         Ok(Self {
-            client: create_http_client(60)?, // 60 second timeout for processing
+            client: ServiceClient::new("unified-processor", 60, breaker)?, // 60 second timeout for processing
             base_url: base_url.trim_end_matches('/').to_string(),
         })
     }
     
     /// Process files through the unified pipeline
     pub async fn process(&self, request: &ProcessRequest) -> Result<ServiceResponse<ProcessedData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/process", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Chunk code content
     pub async fn chunk(&self, request: &ChunkRequest) -> Result<serde_json::Value, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/chunk", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Generate single text embedding
     pub async fn embed(&self, request: &EmbedRequest) -> Result<ServiceResponse<EmbeddingData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/embed", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Generate batch embeddings
     pub async fn embed_batch(&self, request: &BatchEmbedRequest) -> Result<ServiceResponse<BatchEmbeddingData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/embed/batch", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Semantic search
     pub async fn search(&self, request: &SearchRequest) -> Result<ServiceResponse<SearchData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/search", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Hybrid search (vector + keyword)
     pub async fn search_hybrid(&self, request: &HybridSearchRequest) -> Result<ServiceResponse<SearchData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/search/hybrid", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Parse document (legacy doc-parser compatibility)
     pub async fn parse_document(&self, request: &ProcessRequest) -> Result<ServiceResponse<ProcessedData>, AppError> {
-        let response = self.client
+        let request = self.client
+            .http()
             .post(format!("{}/api/v1/parse", self.base_url))
-            .json(request)
-            .send()
-            .await?;
+            .json(request);
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Get service status
     pub async fn get_status(&self) -> Result<serde_json::Value, AppError> {
-        let response = self.client
-            .get(format!("{}/api/v1/status", self.base_url))
-            .send()
-            .await?;
+        let request = self.client
+            .http()
+            .get(format!("{}/api/v1/status", self.base_url));
         
-        handle_service_response(response, "unified-processor").await
+        self.client.call(request).await
     }
     
     /// Health check
     pub async fn health_check(&self) -> bool {
-        self.client
-            .get(format!("{}/health", self.base_url))
-            .send()
-            .await
-            .map(|r| r.status().is_success())
-            .unwrap_or(false)
+        self.client.health_check(&self.base_url).await
     }
 }
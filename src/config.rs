@@ -2,7 +2,20 @@
 //!
 //! Loads configuration from environment variables
 
+use std::collections::HashMap;
 use std::env;
+use std::time::Duration;
+
+use crate::middleware::CircuitBreakerConfig;
+
+/// Downstream services that can have their own circuit breaker settings
+pub const CIRCUIT_BREAKER_SERVICES: &[&str] = &[
+    "auth-middleware",
+    "data-connector",
+    "relation-graph",
+    "mcp-server",
+    "unified-processor",
+];
 
 /// Application configuration
 #[derive(Debug, Clone)]
@@ -12,9 +25,21 @@ pub struct Config {
     
     // Database
     pub database_url: String,
+    pub database_pool_size: u32,
+    /// Apply pending schema migrations before serving
+    pub run_migrations_on_start: bool,
+    /// AES-256 key (64 hex characters) sealing agent API keys in the database
+    pub agent_key_encryption_key: String,
     
     // JWT
     pub jwt_secret: String,
+    /// Required `iss` claim, if set
+    pub jwt_issuer: Option<String>,
+    /// Required `aud` claim, if set
+    pub jwt_audience: Option<String>,
+    /// JWKS document for RS*/ES* tokens; those tokens are rejected when unset
+    pub jwks_url: Option<String>,
+    pub jwks_cache_secs: u64,
     
     // Service URLs
     pub auth_middleware_url: String,
@@ -25,6 +50,28 @@ pub struct Config {
     pub unified_processor_url: String,
     pub enhanced_graph_url: String,  // Added for new graph service
     
+    // Webhooks
+    /// Fallback secret for GitHub deliveries whose sources have no `webhook_secret`
+    pub github_webhook_secret: Option<String>,
+    /// Fallback token for GitLab deliveries whose sources have no `webhook_secret`
+    pub gitlab_webhook_token: Option<String>,
+    /// Fallback secret for Bitbucket deliveries whose sources have no `webhook_secret`
+    pub bitbucket_webhook_secret: Option<String>,
+    /// Forwarding attempts before an inbox delivery is marked failed
+    pub webhook_max_attempts: u32,
+    /// Delay before the first retry; doubles with each attempt
+    pub webhook_retry_base_secs: u64,
+    /// Attempts before an outbound notification is marked failed
+    pub outbound_webhook_max_attempts: u32,
+    /// Delay before the first outbound retry; doubles with each attempt
+    pub outbound_webhook_retry_base_secs: u64,
+    /// Failed attempts in a row after which a subscription is disabled
+    pub outbound_webhook_disable_after: u32,
+    /// Timeout for one outbound webhook request
+    pub outbound_webhook_timeout_secs: u64,
+    /// Allow subscriptions to plain-HTTP, loopback and private-network URLs
+    pub outbound_webhook_allow_private_targets: bool,
+    
     // CORS
     pub cors_origins: Vec<String>,
     
@@ -33,6 +80,53 @@ pub struct Config {
     pub rate_limit_search: u32,
     pub rate_limit_sources: u32,
     pub rate_limit_sync: u32,
+    pub rate_limit_window_secs: u64,
+    /// Counter store for rate limiting: "memory" or "postgres"
+    pub rate_limit_backend: String,
+    /// Optional JSON file with rate limit policies (hot-reloaded)
+    pub rate_limit_policy_file: Option<String>,
+    pub rate_limit_policy_reload_secs: u64,
+    /// Disable rate limiting entirely (development only)
+    pub skip_rate_limiting: bool,
+    
+    // Zero Trust
+    pub zero_trust_max_request_age_secs: u64,
+    pub zero_trust_enforce_timestamps: bool,
+    pub zero_trust_require_correlation_id: bool,
+    pub zero_trust_enforce_service_identity: bool,
+    /// Log Zero Trust violations without rejecting requests
+    pub zero_trust_audit_only: bool,
+    /// Request signing key per internal service, from `SERVICE_IDENTITY_KEYS`
+    pub service_identity_keys: HashMap<String, String>,
+    
+    // Circuit breaker
+    pub circuit_breaker: CircuitBreakerConfig,
+    /// Per-service overrides from `CIRCUIT_BREAKER_<SERVICE>_*`
+    pub circuit_breaker_overrides: HashMap<String, CircuitBreakerConfig>,
+}
+
+/// Database settings, loadable on their own for the `migrate` subcommand
+#[derive(Debug, Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_size: u32,
+}
+
+impl DatabaseConfig {
+    /// Load only `DATABASE_URL` and `DATABASE_POOL_SIZE`
+    pub fn from_env() -> Result<Self, ConfigError> {
+        dotenvy::dotenv().ok();
+        
+        Ok(Self {
+            url: env::var("DATABASE_URL")
+                .map_err(|_| ConfigError::MissingEnv("DATABASE_URL".to_string()))?,
+            
+            pool_size: env::var("DATABASE_POOL_SIZE")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+        })
+    }
 }
 
 impl Config {
@@ -40,20 +134,52 @@ impl Config {
     pub fn from_env() -> Result<Self, ConfigError> {
         dotenvy::dotenv().ok();
         
+        let database = DatabaseConfig::from_env()?;
+        
+        let auth_middleware_url = env::var("AUTH_MIDDLEWARE_GRPC_ADDR")
+            .map_err(|_| ConfigError::MissingEnv("AUTH_MIDDLEWARE_GRPC_ADDR".to_string()))?;
+        
+        let circuit_breaker = circuit_breaker_from_env("CIRCUIT_BREAKER", &CircuitBreakerConfig::default())?;
+        let mut circuit_breaker_overrides = HashMap::new();
+        for service in CIRCUIT_BREAKER_SERVICES {
+            let prefix = format!("CIRCUIT_BREAKER_{}", service.to_uppercase().replace('-', "_"));
+            let service_config = circuit_breaker_from_env(&prefix, &circuit_breaker)?;
+            if service_config != circuit_breaker {
+                circuit_breaker_overrides.insert(service.to_string(), service_config);
+            }
+        }
+        
         Ok(Self {
             port: env::var("PORT")
                 .unwrap_or_else(|_| "8000".to_string())
                 .parse()
                 .unwrap_or(8000),
             
-            database_url: env::var("DATABASE_URL")
-                .map_err(|_| ConfigError::MissingEnv("DATABASE_URL".to_string()))?,
+            database_url: database.url,
+            database_pool_size: database.pool_size,
+            
+            run_migrations_on_start: env::var("RUN_MIGRATIONS_ON_START")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            
+            agent_key_encryption_key: env::var("AGENT_KEY_ENCRYPTION_KEY")
+                .map_err(|_| ConfigError::MissingEnv("AGENT_KEY_ENCRYPTION_KEY".to_string()))?,
             
             jwt_secret: env::var("JWT_SECRET")
                 .map_err(|_| ConfigError::MissingEnv("JWT_SECRET".to_string()))?,
             
-            auth_middleware_url: env::var("AUTH_MIDDLEWARE_GRPC_ADDR")
-                .map_err(|_| ConfigError::MissingEnv("AUTH_MIDDLEWARE_GRPC_ADDR".to_string()))?,
+            jwt_issuer: env::var("JWT_ISSUER").ok(),
+            
+            jwt_audience: env::var("JWT_AUDIENCE").ok(),
+            
+            jwks_url: env::var("JWKS_URL").ok().filter(|url| !url.trim().is_empty()),
+            
+            jwks_cache_secs: env::var("JWKS_CACHE_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            
+            auth_middleware_url,
             
             data_connector_url: env::var("DATA_CONNECTOR_GRPC_ADDR")
                 .map_err(|_| ConfigError::MissingEnv("DATA_CONNECTOR_GRPC_ADDR".to_string()))?,
@@ -73,6 +199,46 @@ impl Config {
             enhanced_graph_url: env::var("ENHANCED_GRAPH_URL")
                 .map_err(|_| ConfigError::MissingEnv("ENHANCED_GRAPH_URL".to_string()))?,
             
+            github_webhook_secret: env::var("GITHUB_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty()),
+            
+            gitlab_webhook_token: env::var("GITLAB_WEBHOOK_TOKEN").ok().filter(|s| !s.is_empty()),
+            
+            bitbucket_webhook_secret: env::var("BITBUCKET_WEBHOOK_SECRET").ok().filter(|s| !s.is_empty()),
+            
+            webhook_max_attempts: env::var("WEBHOOK_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "8".to_string())
+                .parse()
+                .unwrap_or(8),
+            
+            webhook_retry_base_secs: env::var("WEBHOOK_RETRY_BASE_SECS")
+                .unwrap_or_else(|_| "5".to_string())
+                .parse()
+                .unwrap_or(5),
+            
+            outbound_webhook_max_attempts: env::var("OUTBOUND_WEBHOOK_MAX_ATTEMPTS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            
+            outbound_webhook_retry_base_secs: env::var("OUTBOUND_WEBHOOK_RETRY_BASE_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            
+            outbound_webhook_disable_after: env::var("OUTBOUND_WEBHOOK_DISABLE_AFTER")
+                .unwrap_or_else(|_| "20".to_string())
+                .parse()
+                .unwrap_or(20),
+            
+            outbound_webhook_timeout_secs: env::var("OUTBOUND_WEBHOOK_TIMEOUT_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            
+            outbound_webhook_allow_private_targets: env::var("OUTBOUND_WEBHOOK_ALLOW_PRIVATE_TARGETS")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            
             cors_origins: env::var("CORS_ORIGINS")
                 .unwrap_or_else(|_| "http://localhost:3000".to_string())
                 .split(',')
@@ -98,10 +264,107 @@ impl Config {
                 .unwrap_or_else(|_| "10".to_string())
                 .parse()
                 .unwrap_or(10),
+            
+            rate_limit_window_secs: env::var("RATE_LIMIT_WINDOW_SECS")
+                .unwrap_or_else(|_| "60".to_string())
+                .parse()
+                .unwrap_or(60),
+            
+            rate_limit_backend: env::var("RATE_LIMIT_BACKEND")
+                .unwrap_or_else(|_| "memory".to_string())
+                .to_lowercase(),
+            
+            rate_limit_policy_file: env::var("RATE_LIMIT_POLICY_FILE").ok(),
+            
+            rate_limit_policy_reload_secs: env::var("RATE_LIMIT_POLICY_RELOAD_SECS")
+                .unwrap_or_else(|_| "10".to_string())
+                .parse()
+                .unwrap_or(10),
+            
+            skip_rate_limiting: env::var("SKIP_RATE_LIMITING")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            
+            zero_trust_max_request_age_secs: env::var("ZERO_TRUST_MAX_REQUEST_AGE_SECS")
+                .unwrap_or_else(|_| "300".to_string())
+                .parse()
+                .unwrap_or(300),
+            
+            zero_trust_enforce_timestamps: env::var("ZERO_TRUST_ENFORCE_TIMESTAMPS")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            
+            zero_trust_require_correlation_id: env::var("ZERO_TRUST_REQUIRE_CORRELATION_ID")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            
+            zero_trust_enforce_service_identity: env::var("ZERO_TRUST_ENFORCE_SERVICE_IDENTITY")
+                .map(|v| v != "false")
+                .unwrap_or(true),
+            
+            zero_trust_audit_only: env::var("ZERO_TRUST_AUDIT_ONLY")
+                .map(|v| v == "true")
+                .unwrap_or(false),
+            
+            service_identity_keys: service_keys_from_env()?,
+            
+            circuit_breaker,
+            circuit_breaker_overrides,
         })
     }
 }
 
+/// Read `{prefix}_FAILURE_THRESHOLD`, `{prefix}_TIMEOUT_SECS`,
+/// `{prefix}_SUCCESS_THRESHOLD`, `{prefix}_FAILURE_RATE`, `{prefix}_WINDOW_SIZE`
+/// and `{prefix}_MIN_CALLS`, falling back to `base` for unset values
+fn circuit_breaker_from_env(
+    prefix: &str,
+    base: &CircuitBreakerConfig,
+) -> Result<CircuitBreakerConfig, ConfigError> {
+    fn parse<T: std::str::FromStr>(name: String, default: T) -> Result<T, ConfigError> {
+        match env::var(&name) {
+            Ok(value) => value.parse().map_err(|_| ConfigError::InvalidValue(name)),
+            Err(_) => Ok(default),
+        }
+    }
+    
+    Ok(CircuitBreakerConfig {
+        failure_threshold: parse(format!("{}_FAILURE_THRESHOLD", prefix), base.failure_threshold)?,
+        open_duration: Duration::from_secs(parse(
+            format!("{}_TIMEOUT_SECS", prefix),
+            base.open_duration.as_secs(),
+        )?),
+        half_open_successes: parse(format!("{}_SUCCESS_THRESHOLD", prefix), base.half_open_successes)?,
+        failure_rate_threshold: match env::var(format!("{}_FAILURE_RATE", prefix)) {
+            Ok(value) => match value.parse::<u32>() {
+                Ok(rate) if rate <= 100 => Some(rate),
+                _ => return Err(ConfigError::InvalidValue(format!("{}_FAILURE_RATE", prefix))),
+            },
+            Err(_) => base.failure_rate_threshold,
+        },
+        window_size: parse(format!("{}_WINDOW_SIZE", prefix), base.window_size)?,
+        min_calls: parse(format!("{}_MIN_CALLS", prefix), base.min_calls)?,
+    })
+}
+
+/// Parse `SERVICE_IDENTITY_KEYS`: comma-separated `service=key` pairs
+fn service_keys_from_env() -> Result<HashMap<String, String>, ConfigError> {
+    let Ok(value) = env::var("SERVICE_IDENTITY_KEYS") else {
+        return Ok(HashMap::new());
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((name, key)) if !name.trim().is_empty() && !key.is_empty() => {
+                Ok((name.trim().to_string(), key.to_string()))
+            }
+            _ => Err(ConfigError::InvalidValue("SERVICE_IDENTITY_KEYS".to_string())),
+        })
+        .collect()
+}
+
 /// Configuration errors
 #[derive(Debug, thiserror::Error)]
 pub enum ConfigError {
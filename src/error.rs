@@ -8,6 +8,7 @@ use axum::{
     Json,
 };
 use serde::Serialize;
+use schemars::JsonSchema;
 
 /// Application error types
 #[derive(Debug, thiserror::Error)]
@@ -38,12 +39,12 @@ pub enum AppError {
 }
 
 /// Error response format matching API reference
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ErrorResponse {
     pub error: ErrorDetail,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ErrorDetail {
     pub code: String,
     pub message: String,
@@ -83,6 +84,12 @@ impl From<sqlx::Error> for AppError {
     }
 }
 
+impl From<sqlx::migrate::MigrateError> for AppError {
+    fn from(err: sqlx::migrate::MigrateError) -> Self {
+        AppError::Database(err.to_string())
+    }
+}
+
 
 
 impl From<reqwest::Error> for AppError {
@@ -3,8 +3,20 @@
 //! Re-exports from confuse-common events module for convenience.
 
 pub use confuse_common::events::{
+    EventHeaders,
     EventProducer,
     SourceSyncRequestedEvent,
     config::KafkaConfig,
     topics::Topics,
 };
+
+/// Stamp an event with the current request's correlation ID (unless it
+/// already has one) and W3C trace context (`traceparent` / `tracestate`)
+/// so consumers can join their logs and continue the trace
+pub fn attach_request_context(headers: &mut EventHeaders) {
+    if headers.correlation_id.is_none() {
+        headers.correlation_id = crate::request_context::RequestContext::current()
+            .and_then(|ctx| ctx.correlation_id);
+    }
+    headers.extra.extend(crate::telemetry::current_trace_headers());
+}
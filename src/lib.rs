@@ -11,6 +11,14 @@ pub mod routes;
 pub mod clients;
 pub mod models;
 pub mod kafka;
+pub mod metrics;
+pub mod openapi;
+pub mod telemetry;
+pub mod request_context;
+pub mod store;
+pub mod webhook_dispatch;
+pub mod webhook_inbox;
+pub mod webhook_sync;
 
 pub use config::Config;
 pub use error::{AppError, Result};
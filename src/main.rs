@@ -11,13 +11,27 @@ use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 use api_backend::{Config, AppError};
+use api_backend::config::DatabaseConfig;
 use api_backend::clients::{AuthClient, DataConnectorClient, RelationGraphClient, McpClient, UnifiedProcessorClient};
 use api_backend::middleware::auth::AuthLayer;
-use api_backend::middleware::circuit_breaker::{CircuitBreakerRegistry, CircuitBreakerConfig};
+use api_backend::middleware::auth_cache::AuthCache;
+use api_backend::middleware::jwt::JwtVerifier;
+use api_backend::middleware::circuit_breaker::CircuitBreakerRegistry;
 use api_backend::middleware::cache::{ResponseCache, CacheConfig};
+use api_backend::middleware::rate_limit::{
+    RateLimitConfig, RateLimitStore, InMemoryRateLimitStore, PostgresRateLimitStore,
+};
+use api_backend::middleware::rate_limit_policy::PolicyRegistry;
+use api_backend::middleware::metrics::metrics_middleware;
+use api_backend::middleware::request_span::request_span_middleware;
 use api_backend::middleware::security_headers::security_headers_middleware;
-use api_backend::middleware::zero_trust::zero_trust_middleware;
+use api_backend::middleware::zero_trust::{zero_trust_middleware, ZeroTrustLayer};
 use api_backend::routes::v1::{v1_router, AppState};
+use api_backend::store::{PostgresStore, SecretCipher, Stores};
+use api_backend::store::migrations::{self, MigrationState};
+use api_backend::telemetry;
+use api_backend::webhook_dispatch::WebhookDispatcher;
+use api_backend::webhook_inbox::WebhookWorker;
 use confuse_common::events::{config::KafkaConfig, producer::EventProducer};
 
 #[tokio::main]
@@ -42,10 +56,24 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 .with_writer(non_blocking)
                 .json()
         )
+        // OTLP span export (enabled by OTEL_EXPORTER_OTLP_ENDPOINT)
+        .with(telemetry::otlp_layer().unwrap_or_else(|e| {
+            eprintln!("⚠️  OpenTelemetry exporter disabled: {}", e);
+            None
+        }))
         .init();
     
     tracing::info!("Starting ConFuse API Backend...");
     
+    // `api-backend migrate up|status|down` manages the schema and exits,
+    // needing only the database settings
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("migrate") {
+        let database = DatabaseConfig::from_env()?;
+        let db = connect_database(&database.url, database.pool_size).await?;
+        return migrate_command(&db, args.get(1).map(String::as_str)).await;
+    }
+    
     // Load configuration
     let config = Config::from_env().map_err(|e| {
         tracing::error!("Failed to load config: {}", e);
@@ -55,13 +83,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let config = Arc::new(config);
     tracing::info!("Configuration loaded, port: {}", config.port);
     
+    // Connect to Postgres
+    let db = connect_database(&config.database_url, config.database_pool_size).await?;
+    
+    if config.run_migrations_on_start {
+        let applied = migrations::up(&db).await?;
+        tracing::info!(applied = applied.len(), "Schema migrations up to date");
+    } else {
+        let pending = migrations::status(&db)
+            .await?
+            .into_iter()
+            .filter(|m| m.state != MigrationState::Applied)
+            .count();
+        if pending > 0 {
+            tracing::warn!(
+                "⚠️  {} schema migration(s) not applied; run `api-backend migrate up` or set RUN_MIGRATIONS_ON_START=true",
+                pending
+            );
+        }
+    }
+    let agent_key_cipher = SecretCipher::from_hex(&config.agent_key_encryption_key)?;
+    let postgres_store = PostgresStore::new(db.clone(), agent_key_cipher);
+    match postgres_store.seal_plaintext_agent_keys().await {
+        Ok(0) => {}
+        Ok(sealed) => tracing::info!(sealed = sealed, "Encrypted plaintext agent API keys"),
+        Err(e) => tracing::warn!("Could not encrypt plaintext agent API keys: {}", e),
+    }
+    match postgres_store.seal_plaintext_subscription_secrets().await {
+        Ok(0) => {}
+        Ok(sealed) => tracing::info!(sealed = sealed, "Encrypted plaintext webhook subscription secrets"),
+        Err(e) => tracing::warn!("Could not encrypt plaintext webhook subscription secrets: {}", e),
+    }
+    let stores = Stores::postgres(postgres_store);
+    
+    // Initialize circuit breaker registry (shared by all service clients)
+    let circuit_breaker = Arc::new(
+        CircuitBreakerRegistry::new(config.circuit_breaker.clone())
+            .with_overrides(config.circuit_breaker_overrides.clone()),
+    );
+    tracing::info!(
+        overrides = config.circuit_breaker_overrides.len(),
+        "Circuit breaker registry initialized"
+    );
+    
     // Initialize service clients
-    let auth_client = AuthClient::new(&config.auth_middleware_url)?;
-    let data_connector_client = DataConnectorClient::new(&config.data_connector_url)?;
-    let relation_graph_client = RelationGraphClient::new(&config.relation_graph_url)?;
-    let mcp_client = McpClient::new(&config.mcp_server_url)?;
-    let unified_processor_client = UnifiedProcessorClient::new(&config.unified_processor_url)?;
-    let enhanced_graph_client = api_backend::clients::EnhancedGraphClient::new(&config.enhanced_graph_url)?;
+    let auth_client = AuthClient::new(&config.auth_middleware_url, circuit_breaker.clone())?;
+    let data_connector_client = Arc::new(DataConnectorClient::new(&config.data_connector_url, circuit_breaker.clone())?);
+    let relation_graph_client = RelationGraphClient::new(&config.relation_graph_url, circuit_breaker.clone())?;
+    let mcp_client = McpClient::new(&config.mcp_server_url, circuit_breaker.clone())?;
+    let unified_processor_client = UnifiedProcessorClient::new(&config.unified_processor_url, circuit_breaker.clone())?;
+    let enhanced_graph_client = api_backend::clients::EnhancedGraphClient::new(&config.enhanced_graph_url, circuit_breaker.clone())?;
     
     tracing::info!("Service clients initialized (including unified-processor and enhanced-graph)");
     
@@ -94,6 +165,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         None
     };
     
+    // Forward webhook deliveries recorded in the inbox
+    WebhookWorker::new(
+        &config,
+        stores.webhooks.clone(),
+        data_connector_client.clone(),
+        event_producer.clone(),
+    )
+    .spawn();
+    tracing::info!(max_attempts = config.webhook_max_attempts, "Webhook inbox worker started");
+    
+    // Send notifications to outbound webhook subscriptions
+    let webhook_dispatcher = WebhookDispatcher::new(&config, stores.subscriptions.clone())?;
+    webhook_dispatcher.clone().spawn();
+    tracing::info!("Outbound webhook dispatcher started");
+    
     // Check if auth bypass is enabled via feature toggle (development only)
     // In production, this would check the feature-toggle service
     let auth_bypass_enabled = std::env::var("AUTH_BYPASS_ENABLED")
@@ -104,17 +190,41 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         tracing::warn!("⚠️  AUTH BYPASS ENABLED - Development mode only!");
     }
     
+    // Initialize response cache (also holds verified credentials)
+    let cache_config = CacheConfig::default();
+    let response_cache = Arc::new(ResponseCache::new(cache_config.clone()));
+    response_cache.spawn_cleanup();
+    tracing::info!("Response cache initialized");
+    
     // Create auth layer
-    let auth_layer = AuthLayer::new(auth_client.clone(), auth_bypass_enabled);
+    // JWTs are verified locally; only opaque tokens go to auth-middleware
+    let jwt_verifier = JwtVerifier::new(&config)?;
+    match &config.jwks_url {
+        Some(jwks_url) => tracing::info!(jwks_url = %jwks_url, "Local JWT verification enabled"),
+        None => tracing::info!("Local JWT verification enabled for HS256 only; set JWKS_URL to accept RS*/ES* tokens"),
+    }
+    let auth_cache = AuthCache::new(response_cache.clone(), cache_config.auth_ttl);
+    let auth_layer = AuthLayer::new(auth_client.clone(), jwt_verifier, auth_cache, auth_bypass_enabled);
     
-    // Initialize circuit breaker registry
-    let circuit_breaker = Arc::new(CircuitBreakerRegistry::new(CircuitBreakerConfig::default()));
-    tracing::info!("Circuit breaker registry initialized");
+    // Initialize rate limiting (shared Postgres counters when running several replicas)
+    if config.skip_rate_limiting {
+        tracing::warn!("⚠️  Rate limiting disabled via SKIP_RATE_LIMITING=true");
+    }
+    
+    let rate_limit_store: Arc<dyn RateLimitStore> = match config.rate_limit_backend.as_str() {
+        "postgres" => {
+            tracing::info!("Rate limiting using shared Postgres store");
+            Arc::new(PostgresRateLimitStore::new(db.clone()))
+        }
+        _ => {
+            tracing::info!("Rate limiting using in-memory store");
+            Arc::new(InMemoryRateLimitStore::new())
+        }
+    };
+    let rate_limit_policies = PolicyRegistry::load(&config)?;
+    let rate_limit = RateLimitConfig::new(rate_limit_store, rate_limit_policies, config.skip_rate_limiting);
+    rate_limit.spawn_sweeper();
     
-    // Initialize response cache
-    let response_cache = Arc::new(ResponseCache::new(CacheConfig::default()));
-    tracing::info!("Response cache initialized");
-
     // Initialize gRPC clients (REQUIRED — fail fast if connections cannot be established)
     tracing::info!("Initializing gRPC clients...");
     let grpc_clients = api_backend::clients::GrpcClients::connect(
@@ -134,17 +244,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Create application state
     let state = AppState {
         config: config.clone(),
+        db,
+        stores,
         auth_client: Arc::new(auth_client),
-        data_connector_client: Arc::new(data_connector_client),
+        data_connector_client,
         relation_graph_client: Arc::new(relation_graph_client),
         mcp_client: Arc::new(mcp_client),
         unified_processor_client: Arc::new(unified_processor_client),
         enhanced_graph_client: Arc::new(enhanced_graph_client),
         auth_layer,
+        rate_limit,
         event_producer,
         circuit_breaker,
         response_cache,
         grpc_clients,
+        webhook_dispatcher,
     };
     
     // Build CORS layer
@@ -153,9 +267,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .allow_methods(Any)
         .allow_headers(Any);
     
+    // Zero Trust request validation
+    let zero_trust = ZeroTrustLayer::from_config(&config);
+    if zero_trust.audit_only {
+        tracing::warn!("⚠️  Zero Trust in audit-only mode: violations are logged, not rejected");
+    }
+    
     // Build router
     let app = v1_router(state)
-        .layer(axum::middleware::from_fn(zero_trust_middleware))
+        .layer(axum::middleware::from_fn(metrics_middleware))
+        .layer(axum::middleware::from_fn(request_span_middleware))
+        .layer(axum::middleware::from_fn_with_state(zero_trust, zero_trust_middleware))
         .layer(axum::middleware::from_fn(security_headers_middleware))
         .layer(TraceLayer::new_for_http())
         .layer(cors);
@@ -178,5 +300,49 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let listener = tokio::net::TcpListener::bind(addr).await?;
     axum::serve(listener, app).await?;
     
+    telemetry::shutdown();
+    Ok(())
+}
+
+/// Open the Postgres pool
+async fn connect_database(url: &str, pool_size: u32) -> Result<sqlx::PgPool, sqlx::Error> {
+    let db = sqlx::postgres::PgPoolOptions::new()
+        .max_connections(pool_size)
+        .connect(url)
+        .await
+        .map_err(|e| {
+            tracing::error!("❌ Failed to connect to database: {}", e);
+            e
+        })?;
+    tracing::info!("✅ Database connected");
+    Ok(db)
+}
+
+/// Run a `migrate` subcommand against the database
+async fn migrate_command(db: &sqlx::PgPool, action: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    match action {
+        Some("up") => {
+            let applied = migrations::up(db).await?;
+            if applied.is_empty() {
+                println!("No pending migrations");
+            }
+            for m in applied {
+                println!("Applied {} {}", m.version, m.description);
+            }
+        }
+        Some("status") => {
+            for m in migrations::status(db).await? {
+                println!("{:<16} {:<9} {}", m.version, m.state.as_str(), m.description);
+            }
+        }
+        Some("down") => match migrations::down(db).await? {
+            Some(m) => println!("Reverted {} {}", m.version, m.description),
+            None => println!("No applied migrations to revert"),
+        },
+        _ => {
+            eprintln!("Usage: api-backend migrate <up|status|down>");
+            std::process::exit(2);
+        }
+    }
     Ok(())
 }
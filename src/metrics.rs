@@ -0,0 +1,247 @@
+//! Prometheus metrics for API Backend
+//!
+//! Counters and histograms are recorded as events happen. Values owned by
+//! other components (response cache statistics, circuit breaker state) are
+//! copied in when `/metrics` is scraped; circuit breaker transitions are
+//! counted by the breaker itself as they happen.
+
+use once_cell::sync::Lazy;
+use prometheus::{
+    Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge, IntGaugeVec, Opts,
+    Registry, TextEncoder,
+};
+use std::time::Duration;
+
+use crate::middleware::{BreakerSnapshot, CacheStats, CircuitState};
+
+/// Process-wide metrics
+pub static METRICS: Lazy<Metrics> = Lazy::new(Metrics::new);
+
+/// All metrics exported by the gateway
+pub struct Metrics {
+    registry: Registry,
+    http_requests: IntCounterVec,
+    http_duration: HistogramVec,
+    downstream_duration: HistogramVec,
+    rate_limit_rejections: IntCounterVec,
+    kafka_publishes: IntCounterVec,
+    cache_hits: IntCounter,
+    cache_misses: IntCounter,
+    cache_evictions: IntCounter,
+    cache_expirations: IntCounter,
+    cache_entries: IntGauge,
+    cache_bytes: IntGaugeVec,
+    breaker_state: IntGaugeVec,
+    breaker_transitions: IntCounterVec,
+    zero_trust_violations: IntCounterVec,
+    webhook_deliveries: IntCounterVec,
+    outbound_webhooks: IntCounterVec,
+}
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new();
+
+        let up = IntGauge::new("up", "Service up status").unwrap();
+        up.set(1);
+
+        let metrics = Self {
+            http_requests: IntCounterVec::new(
+                Opts::new("http_requests_total", "HTTP requests handled"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            http_duration: HistogramVec::new(
+                HistogramOpts::new("http_request_duration_seconds", "HTTP request latency"),
+                &["method", "route", "status"],
+            )
+            .unwrap(),
+            downstream_duration: HistogramVec::new(
+                HistogramOpts::new(
+                    "downstream_request_duration_seconds",
+                    "Latency of calls to downstream services",
+                ),
+                &["service", "outcome"],
+            )
+            .unwrap(),
+            rate_limit_rejections: IntCounterVec::new(
+                Opts::new("rate_limit_rejections_total", "Requests rejected by rate limiting"),
+                &["policy"],
+            )
+            .unwrap(),
+            kafka_publishes: IntCounterVec::new(
+                Opts::new("kafka_publish_total", "Kafka events published"),
+                &["topic", "result"],
+            )
+            .unwrap(),
+            cache_hits: IntCounter::new("response_cache_hits_total", "Response cache hits").unwrap(),
+            cache_misses: IntCounter::new("response_cache_misses_total", "Response cache misses").unwrap(),
+            cache_evictions: IntCounter::new(
+                "response_cache_evictions_total",
+                "Response cache entries evicted to make room",
+            )
+            .unwrap(),
+            cache_expirations: IntCounter::new(
+                "response_cache_expirations_total",
+                "Response cache entries removed after their TTL",
+            )
+            .unwrap(),
+            cache_entries: IntGauge::new("response_cache_entries", "Entries in the response cache").unwrap(),
+            cache_bytes: IntGaugeVec::new(
+                Opts::new("response_cache_bytes", "Bytes held by the response cache"),
+                &["namespace"],
+            )
+            .unwrap(),
+            breaker_state: IntGaugeVec::new(
+                Opts::new(
+                    "circuit_breaker_state",
+                    "Circuit breaker state (0 = closed, 1 = open, 2 = half-open)",
+                ),
+                &["service"],
+            )
+            .unwrap(),
+            breaker_transitions: IntCounterVec::new(
+                Opts::new("circuit_breaker_transitions_total", "Circuit breaker state transitions"),
+                &["service", "from", "to"],
+            )
+            .unwrap(),
+            zero_trust_violations: IntCounterVec::new(
+                Opts::new("zero_trust_violations_total", "Requests failing a Zero Trust check"),
+                &["check", "action"],
+            )
+            .unwrap(),
+            webhook_deliveries: IntCounterVec::new(
+                Opts::new("webhook_deliveries_total", "Webhook deliveries received and forwarded"),
+                &["provider", "result"],
+            )
+            .unwrap(),
+            outbound_webhooks: IntCounterVec::new(
+                Opts::new("outbound_webhook_attempts_total", "Attempts to notify webhook subscriptions"),
+                &["result"],
+            )
+            .unwrap(),
+            registry,
+        };
+
+        let collectors: Vec<Box<dyn prometheus::core::Collector>> = vec![
+            Box::new(up),
+            Box::new(metrics.http_requests.clone()),
+            Box::new(metrics.http_duration.clone()),
+            Box::new(metrics.downstream_duration.clone()),
+            Box::new(metrics.rate_limit_rejections.clone()),
+            Box::new(metrics.kafka_publishes.clone()),
+            Box::new(metrics.cache_hits.clone()),
+            Box::new(metrics.cache_misses.clone()),
+            Box::new(metrics.cache_evictions.clone()),
+            Box::new(metrics.cache_expirations.clone()),
+            Box::new(metrics.cache_entries.clone()),
+            Box::new(metrics.cache_bytes.clone()),
+            Box::new(metrics.breaker_state.clone()),
+            Box::new(metrics.breaker_transitions.clone()),
+            Box::new(metrics.zero_trust_violations.clone()),
+            Box::new(metrics.webhook_deliveries.clone()),
+            Box::new(metrics.outbound_webhooks.clone()),
+        ];
+        for collector in collectors {
+            metrics.registry.register(collector).unwrap();
+        }
+
+        metrics
+    }
+
+    /// Record a handled HTTP request, labelled by route template
+    pub fn observe_request(&self, method: &str, route: &str, status: u16, elapsed: Duration) {
+        let status = status.to_string();
+        let labels = [method, route, status.as_str()];
+        self.http_requests.with_label_values(&labels).inc();
+        self.http_duration
+            .with_label_values(&labels)
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record a call to a downstream service
+    ///
+    /// `outcome` is `success`, `failure` (timeout, connection error or 5xx)
+    /// or `rejected` (circuit open).
+    pub fn observe_downstream(&self, service: &str, outcome: &str, elapsed: Duration) {
+        self.downstream_duration
+            .with_label_values(&[service, outcome])
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record a request rejected by a rate limit policy
+    pub fn record_rate_limited(&self, policy: &str) {
+        self.rate_limit_rejections.with_label_values(&[policy]).inc();
+    }
+
+    /// Record the result of publishing an event to Kafka
+    pub fn record_kafka_publish(&self, topic: &str, ok: bool) {
+        let result = if ok { "success" } else { "failure" };
+        self.kafka_publishes.with_label_values(&[topic, result]).inc();
+    }
+
+    /// Record a circuit breaker moving between states
+    pub fn record_breaker_transition(&self, service: &str, from: CircuitState, to: CircuitState) {
+        self.breaker_transitions
+            .with_label_values(&[service, from.as_str(), to.as_str()])
+            .inc();
+    }
+
+    /// Record a failed Zero Trust check
+    ///
+    /// `action` is `rejected`, or `audited` in audit-only mode.
+    pub fn record_zero_trust_violation(&self, check: &str, action: &str) {
+        self.zero_trust_violations.with_label_values(&[check, action]).inc();
+    }
+
+    /// Record a webhook delivery event
+    ///
+    /// `result` is `queued` or `duplicate` when received, then `delivered`,
+    /// `retry` or `failed` for each forwarding attempt.
+    pub fn record_webhook_delivery(&self, provider: &str, result: &str) {
+        self.webhook_deliveries.with_label_values(&[provider, result]).inc();
+    }
+
+    /// Record an attempt to notify a webhook subscription
+    ///
+    /// `result` is `delivered`, `retry` or `failed`; `disabled` counts
+    /// subscriptions disabled after repeated failures.
+    pub fn record_outbound_webhook(&self, result: &str) {
+        self.outbound_webhooks.with_label_values(&[result]).inc();
+    }
+
+    /// Render all metrics in Prometheus text exposition format
+    pub fn render(&self, cache: &CacheStats, breakers: &[BreakerSnapshot]) -> String {
+        sync_counter(&self.cache_hits, cache.hits);
+        sync_counter(&self.cache_misses, cache.misses);
+        sync_counter(&self.cache_evictions, cache.evictions);
+        sync_counter(&self.cache_expirations, cache.expirations);
+        self.cache_entries.set(cache.entries as i64);
+        for ns in &cache.namespaces {
+            self.cache_bytes.with_label_values(&[ns.namespace]).set(ns.bytes as i64);
+        }
+
+        for breaker in breakers {
+            let state = match breaker.state {
+                CircuitState::Closed => 0,
+                CircuitState::Open => 1,
+                CircuitState::HalfOpen => 2,
+            };
+            self.breaker_state.with_label_values(&[&breaker.service]).set(state);
+        }
+
+        let mut buffer = Vec::new();
+        if let Err(e) = TextEncoder::new().encode(&self.registry.gather(), &mut buffer) {
+            tracing::error!("Failed to encode metrics: {}", e);
+        }
+        String::from_utf8(buffer).unwrap_or_default()
+    }
+}
+
+/// Bring a counter up to a total maintained elsewhere
+fn sync_counter(counter: &IntCounter, total: u64) {
+    let current = counter.get();
+    if total > current {
+        counter.inc_by(total - current);
+    }
+}
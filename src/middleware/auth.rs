@@ -1,6 +1,7 @@
 //! Authentication middleware
 //!
-//! Handles JWT Bearer token and API key authentication
+//! Handles JWT Bearer token and API key authentication, and resolves the
+//! workspace a request is scoped to
 
 use axum::{
     extract::{Request, State},
@@ -12,26 +13,140 @@ use std::sync::Arc;
 
 use crate::clients::AuthClient;
 use crate::error::AppError;
-use crate::models::User;
+use crate::models::{ApiKeyInfo, User, WorkspaceMembership};
+use crate::request_context::RequestContext;
+use super::auth_cache::{AuthCache, Credential};
+use super::jwt::JwtVerifier;
+use super::zero_trust::record_nonce;
 
 /// Extension type for authenticated user
 #[derive(Clone)]
 pub struct AuthenticatedUser(pub User);
 
+/// Extension type for the API key a request was authenticated with
+#[derive(Clone)]
+pub struct AuthenticatedApiKey(pub ApiKeyInfo);
+
 /// Authentication layer configuration
 #[derive(Clone)]
 pub struct AuthLayer {
     pub auth_client: Arc<AuthClient>,
+    pub jwt_verifier: Arc<JwtVerifier>,
+    pub cache: Arc<AuthCache>,
     pub auth_bypass_enabled: bool,
 }
 
 impl AuthLayer {
-    pub fn new(auth_client: AuthClient, auth_bypass_enabled: bool) -> Self {
+    pub fn new(
+        auth_client: AuthClient,
+        jwt_verifier: JwtVerifier,
+        cache: AuthCache,
+        auth_bypass_enabled: bool,
+    ) -> Self {
         Self {
             auth_client: Arc::new(auth_client),
+            jwt_verifier: Arc::new(jwt_verifier),
+            cache: Arc::new(cache),
             auth_bypass_enabled,
         }
     }
+
+    /// Verify a bearer token locally, or via auth-middleware if it is opaque
+    pub async fn verify_token(&self, token: &str) -> Result<User, AppError> {
+        let credential = Credential::BearerToken(token);
+        if let Some(cached) = self.cache.get(credential) {
+            return Ok(cached.user);
+        }
+
+        let (user, expires_at) = match self.jwt_verifier.verify(token).await? {
+            Some(verified) => (verified.user, Some(verified.expires_at)),
+            None => (self.auth_client.verify_token(token).await?, None),
+        };
+        self.cache.insert(credential, &user, None, expires_at);
+        Ok(user)
+    }
+
+    /// Validate an API key with auth-middleware, returning the user it acts as
+    pub async fn validate_api_key(&self, key: &str) -> Result<(User, ApiKeyInfo), AppError> {
+        let credential = Credential::ApiKey(key);
+        if let Some(cached) = self.cache.get(credential) {
+            if let Some(info) = cached.api_key {
+                return Ok((cached.user, info));
+            }
+        }
+
+        let info = self.auth_client.validate_api_key(key).await?;
+        let user = api_key_user(&info);
+        let expires_at = info
+            .expires_at
+            .as_deref()
+            .and_then(|at| chrono::DateTime::parse_from_rfc3339(at).ok())
+            .map(|at| at.timestamp().max(0) as u64);
+        // A key whose expiry can't be read is still cached, for at most auth_ttl
+        self.cache.insert(credential, &user, Some(&info), expires_at);
+        Ok((user, info))
+    }
+
+    /// Workspace memberships of a user, from the cache or auth-middleware
+    pub async fn memberships(&self, user_id: &str) -> Result<Vec<WorkspaceMembership>, AppError> {
+        if let Some(memberships) = self.cache.memberships(user_id) {
+            return Ok(memberships);
+        }
+        let memberships = self.auth_client.workspace_memberships(user_id).await?;
+        self.cache.insert_memberships(user_id, &memberships);
+        Ok(memberships)
+    }
+
+    /// Workspace a request is scoped to
+    ///
+    /// The requested workspace (`X-Workspace-Id`, else the token's
+    /// `workspace_id`) must be one the user belongs to, or the request is
+    /// rejected with 403. With none requested, the user's default workspace
+    /// is used, or their only one; otherwise the request is unscoped.
+    pub async fn resolve_workspace(
+        &self,
+        user: &User,
+        requested: Option<&str>,
+    ) -> Result<Option<String>, AppError> {
+        let memberships = self.memberships(&user.id).await?;
+
+        if let Some(workspace_id) = requested.or(user.workspace_id.as_deref()) {
+            if memberships.iter().any(|m| m.workspace_id == workspace_id) {
+                return Ok(Some(workspace_id.to_string()));
+            }
+            tracing::warn!(
+                user_id = %user.id,
+                workspace_id = %workspace_id,
+                "Rejected request for a workspace the user is not a member of"
+            );
+            return Err(AppError::Forbidden(format!(
+                "Not a member of workspace '{}'",
+                workspace_id
+            )));
+        }
+
+        let default = memberships
+            .iter()
+            .find(|m| m.is_default)
+            .or(match memberships.as_slice() {
+                [only] => Some(only),
+                _ => None,
+            });
+        Ok(default.map(|m| m.workspace_id.clone()))
+    }
+}
+
+/// User an API key acts as; it has no roles, only the key's scopes
+fn api_key_user(info: &ApiKeyInfo) -> User {
+    User {
+        id: info.user_id.clone(),
+        email: format!("api-key-{}@confuse.dev", info.id),
+        name: Some(info.name.clone()),
+        picture: None,
+        roles: vec![],
+        scopes: info.scopes.clone(),
+        workspace_id: None,
+    }
 }
 
 /// Demo user for auth bypass in development
@@ -42,6 +157,7 @@ fn demo_user() -> User {
         name: Some("Demo User".to_string()),
         picture: None,
         roles: vec!["user".to_string()],
+        scopes: vec![],
         workspace_id: Some("demo-workspace-001".to_string()),
     }
 }
@@ -55,8 +171,13 @@ pub async fn auth_middleware(
     // Check for auth bypass (development only)
     if auth_layer.auth_bypass_enabled {
         tracing::debug!("Auth bypass enabled, using demo user");
-        request.extensions_mut().insert(AuthenticatedUser(demo_user()));
-        return Ok(next.run(request).await);
+        let user = demo_user();
+        if let Some(rejection) = record_nonce(&mut request, &user.id) {
+            return Ok(rejection);
+        }
+        let context = RequestContext::with_user(&user);
+        request.extensions_mut().insert(AuthenticatedUser(user));
+        return Ok(context.scope(next.run(request)).await);
     }
     
     // Try to extract authorization
@@ -70,45 +191,50 @@ pub async fn auth_middleware(
         .get("X-API-Key")
         .and_then(|v| v.to_str().ok());
     
-    // Extract workspace ID from headers (optional - for multi-tenant context)
+    // Requested workspace (optional - validated against memberships below)
     let workspace_id = request
         .headers()
         .get("X-Workspace-Id")
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
     
+    let mut api_key_info = None;
+    
     let mut user = if let Some(auth_value) = auth_header {
         // Bearer token authentication
         if let Some(token) = auth_value.strip_prefix("Bearer ") {
-            auth_layer.auth_client.verify_token(token).await?
+            auth_layer.verify_token(token).await?
         } else {
             return Err(AppError::Unauthorized("Invalid authorization header format".to_string()));
         }
     } else if let Some(key) = api_key {
         // API key authentication
-        let api_key_info = auth_layer.auth_client.validate_api_key(key).await?;
-        // Convert API key info to user
-        User {
-            id: api_key_info.user_id,
-            email: format!("api-key-{}@confuse.dev", api_key_info.id),
-            name: Some(api_key_info.name),
-            picture: None,
-            roles: api_key_info.scopes,
-            workspace_id: None,
-        }
+        let (user, info) = auth_layer.validate_api_key(key).await?;
+        api_key_info = Some(info);
+        user
     } else {
         return Err(AppError::Unauthorized("No authentication provided".to_string()));
     };
     
-    // Set workspace_id if provided in headers
-    if workspace_id.is_some() {
-        user.workspace_id = workspace_id;
+    // Scope the request to a workspace the user belongs to
+    user.workspace_id = auth_layer
+        .resolve_workspace(&user, workspace_id.as_deref())
+        .await?;
+    
+    // Now that the caller is known, use up the request's nonce (Zero Trust)
+    if let Some(rejection) = record_nonce(&mut request, &user.id) {
+        return Ok(rejection);
     }
     
-    // Attach user to request extensions
+    // Attach user (and API key, if used) to request extensions, and make the
+    // user context available to service clients for the rest of the request
+    let context = RequestContext::with_user(&user);
     request.extensions_mut().insert(AuthenticatedUser(user));
+    if let Some(info) = api_key_info {
+        request.extensions_mut().insert(AuthenticatedApiKey(info));
+    }
     
-    Ok(next.run(request).await)
+    Ok(context.scope(next.run(request)).await)
 }
 
 /// Optional authentication - doesn't fail if no auth provided
@@ -119,8 +245,10 @@ pub async fn optional_auth_middleware(
 ) -> Response {
     // Check for auth bypass
     if auth_layer.auth_bypass_enabled {
-        request.extensions_mut().insert(AuthenticatedUser(demo_user()));
-        return next.run(request).await;
+        let user = demo_user();
+        let context = RequestContext::with_user(&user);
+        request.extensions_mut().insert(AuthenticatedUser(user));
+        return context.scope(next.run(request)).await;
     }
     
     // Try to extract and validate authorization
@@ -131,8 +259,10 @@ pub async fn optional_auth_middleware(
     
     if let Some(auth_value) = auth_header {
         if let Some(token) = auth_value.strip_prefix("Bearer ") {
-            if let Ok(user) = auth_layer.auth_client.verify_token(token).await {
+            if let Ok(user) = auth_layer.verify_token(token).await {
+                let context = RequestContext::with_user(&user);
                 request.extensions_mut().insert(AuthenticatedUser(user));
+                return context.scope(next.run(request)).await;
             }
         }
     }
@@ -0,0 +1,188 @@
+//! Cache of successful credential verifications
+//!
+//! Verified users are kept in the response cache's `auth` namespace, keyed
+//! by a SHA-256 of the bearer token or API key so raw credentials are never
+//! held. An entry lives for at most `CacheConfig.auth_ttl` and never past
+//! the credential's own expiry.
+//!
+//! Users' workspace memberships are cached alongside, for the same TTL.
+//!
+//! Revoking a user or API key (`POST /v1/admin/auth-cache/invalidate`)
+//! records the revocation time; cached verifications made before it are
+//! discarded on their next lookup, so the credential is re-verified.
+//! Revoking a user also drops their cached memberships.
+
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::models::{ApiKeyInfo, User, WorkspaceMembership};
+use super::cache::{CacheNamespace, ResponseCache};
+
+/// A credential presented by a caller
+#[derive(Clone, Copy)]
+pub enum Credential<'a> {
+    BearerToken(&'a str),
+    ApiKey(&'a str),
+}
+
+impl Credential<'_> {
+    fn cache_key(&self) -> String {
+        let (kind, secret) = match self {
+            Credential::BearerToken(token) => ("token", token),
+            Credential::ApiKey(key) => ("api_key", key),
+        };
+        format!("auth:{}:{}", kind, hex::encode(Sha256::digest(secret.as_bytes())))
+    }
+}
+
+/// A cached verification outcome
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedAuth {
+    pub user: User,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<ApiKeyInfo>,
+    /// When the credential was verified, milliseconds since the epoch
+    verified_at: u64,
+}
+
+/// Verification cache shared by the auth middlewares
+pub struct AuthCache {
+    cache: Arc<ResponseCache>,
+    ttl: Duration,
+    /// Revocation time (ms) per `user:<id>` / `api_key:<id>`
+    revoked: DashMap<String, u64>,
+}
+
+impl AuthCache {
+    pub fn new(cache: Arc<ResponseCache>, ttl: Duration) -> Self {
+        Self {
+            cache,
+            ttl,
+            revoked: DashMap::new(),
+        }
+    }
+
+    fn now_millis() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+
+    /// Cached verification for a credential, unless it has been revoked since
+    pub fn get(&self, credential: Credential<'_>) -> Option<CachedAuth> {
+        let key = credential.cache_key();
+        let (data, _, _) = self.cache.get(&key)?;
+        let Ok(cached) = serde_json::from_slice::<CachedAuth>(&data) else {
+            self.cache.invalidate_prefix(&key);
+            return None;
+        };
+
+        let mut subjects = vec![format!("user:{}", cached.user.id)];
+        if let Some(info) = &cached.api_key {
+            subjects.push(format!("api_key:{}", info.id));
+        }
+        let revoked = subjects.iter().any(|subject| {
+            self.revoked
+                .get(subject)
+                .map(|at| cached.verified_at <= *at)
+                .unwrap_or(false)
+        });
+        if revoked {
+            self.cache.invalidate_prefix(&key);
+            return None;
+        }
+
+        Some(cached)
+    }
+
+    /// Remember a successful verification
+    ///
+    /// `expires_at` is the credential's own expiry in seconds since the
+    /// epoch; credentials that expire within a second are not cached.
+    pub fn insert(
+        &self,
+        credential: Credential<'_>,
+        user: &User,
+        api_key: Option<&ApiKeyInfo>,
+        expires_at: Option<u64>,
+    ) {
+        let now = Self::now_millis();
+        let mut ttl = self.ttl.as_secs();
+        if let Some(expires_at) = expires_at {
+            // The cache rounds expiry to whole seconds; stay one second short
+            ttl = ttl.min(expires_at.saturating_sub(now / 1000 + 1));
+        }
+        if ttl == 0 {
+            return;
+        }
+
+        let cached = CachedAuth {
+            user: user.clone(),
+            api_key: api_key.cloned(),
+            verified_at: now,
+        };
+        let Ok(data) = serde_json::to_vec(&cached) else {
+            return;
+        };
+        self.cache.set(
+            CacheNamespace::Auth,
+            &credential.cache_key(),
+            data,
+            200,
+            "application/json",
+            Duration::from_secs(ttl),
+        );
+    }
+
+    /// Cached workspace memberships of a user
+    pub fn memberships(&self, user_id: &str) -> Option<Vec<WorkspaceMembership>> {
+        let (data, _, _) = self.cache.get(&memberships_key(user_id))?;
+        serde_json::from_slice(&data).ok()
+    }
+
+    pub fn insert_memberships(&self, user_id: &str, memberships: &[WorkspaceMembership]) {
+        let Ok(data) = serde_json::to_vec(memberships) else {
+            return;
+        };
+        self.cache.set(
+            CacheNamespace::Auth,
+            &memberships_key(user_id),
+            data,
+            200,
+            "application/json",
+            self.ttl,
+        );
+    }
+
+    /// Discard the cached verification of one credential
+    pub fn remove(&self, credential: Credential<'_>) {
+        self.cache.invalidate_prefix(&credential.cache_key());
+    }
+
+    /// Discard cached verifications for every credential of a user
+    pub fn revoke_user(&self, user_id: &str) {
+        self.cache.invalidate_prefix(&memberships_key(user_id));
+        self.revoke(format!("user:{}", user_id));
+    }
+
+    /// Discard the cached verification of an API key
+    pub fn revoke_api_key(&self, key_id: &str) {
+        self.revoke(format!("api_key:{}", key_id));
+    }
+
+    fn revoke(&self, subject: String) {
+        let now = Self::now_millis();
+        // Entries older than the TTL have expired anyway
+        let horizon = now.saturating_sub(self.ttl.as_millis() as u64 + 1000);
+        self.revoked.retain(|_, at| *at >= horizon);
+        self.revoked.insert(subject, now);
+    }
+}
+
+fn memberships_key(user_id: &str) -> String {
+    format!("auth:workspaces:{}", user_id)
+}
@@ -1,12 +1,52 @@
 //! Request caching middleware for API Backend
 //!
-//! In-memory LRU-style cache for auth/data responses with TTL.
+//! In-memory LRU cache for auth/data responses with TTL, bounded by a
+//! total byte budget and per-namespace quotas (auth, search, default).
 //! Designed for easy migration to Redis when available.
 
-use dashmap::DashMap;
-use std::sync::Arc;
+use axum::{
+    body::{to_bytes, Body, HttpBody},
+    extract::{OriginalUri, Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::task::JoinHandle;
+
+use crate::error::AppError;
+use super::auth::AuthenticatedUser;
+
+/// How often expired entries are swept out
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Largest request body hashed into a cache key (search queries are small)
+const MAX_CACHEABLE_REQUEST_BYTES: usize = 1024 * 1024;
+
+/// Largest response body stored in the cache
+const MAX_CACHEABLE_RESPONSE_BYTES: usize = 4 * 1024 * 1024;
+
+/// POST endpoints that are read-only searches and safe to cache
+const CACHEABLE_POST_PATHS: &[&str] = &[
+    "/v1/search",
+    "/v1/search/vector",
+    "/v1/search/graph",
+    "/v1/search/semantic",
+    "/v1/mcp/search",
+];
+
+/// Path prefixes whose cached responses depend on a scope's sources
+pub const SOURCE_DEPENDENT_PATHS: &[&str] = &[
+    "/v1/sources",
+    "/v1/search",
+    "/v1/mcp",
+    "/v1/entities",
+];
 
 #[derive(Debug, Clone)]
 struct CacheEntry {
@@ -14,6 +54,62 @@ struct CacheEntry {
     status: u16,
     content_type: String,
     expires_at: u64,
+    /// Approximate memory footprint, charged against the byte budgets
+    size: usize,
+    /// Recency stamp; higher is more recently used
+    last_used: u64,
+}
+
+/// Fixed per-entry overhead added to payload sizes when accounting bytes
+const ENTRY_OVERHEAD_BYTES: usize = 96;
+
+/// Cache namespace, each with its own byte quota and LRU order
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheNamespace {
+    /// Auth verification results: small, hot, long-lived
+    Auth,
+    /// Search responses: large, short-lived
+    Search,
+    /// Everything else
+    Default,
+}
+
+impl CacheNamespace {
+    pub const ALL: [CacheNamespace; 3] = [CacheNamespace::Auth, CacheNamespace::Search, CacheNamespace::Default];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheNamespace::Auth => "auth",
+            CacheNamespace::Search => "search",
+            CacheNamespace::Default => "default",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            CacheNamespace::Auth => 0,
+            CacheNamespace::Search => 1,
+            CacheNamespace::Default => 2,
+        }
+    }
+}
+
+/// Byte quotas per namespace
+#[derive(Debug, Clone)]
+pub struct NamespaceQuotas {
+    pub auth_bytes: usize,
+    pub search_bytes: usize,
+    pub default_bytes: usize,
+}
+
+impl NamespaceQuotas {
+    fn for_namespace(&self, namespace: CacheNamespace) -> usize {
+        match namespace {
+            CacheNamespace::Auth => self.auth_bytes,
+            CacheNamespace::Search => self.search_bytes,
+            CacheNamespace::Default => self.default_bytes,
+        }
+    }
 }
 
 /// Cache configuration
@@ -27,6 +123,10 @@ pub struct CacheConfig {
     pub search_ttl: Duration,
     /// Maximum entries before eviction
     pub max_entries: usize,
+    /// Total byte budget across all namespaces
+    pub max_bytes: usize,
+    /// Per-namespace byte quotas (may add up to more than `max_bytes`)
+    pub namespace_quotas: NamespaceQuotas,
     /// Whether caching is enabled
     pub enabled: bool,
 }
@@ -38,15 +138,133 @@ impl Default for CacheConfig {
             auth_ttl: Duration::from_secs(300),    // 5 min for auth verifications
             search_ttl: Duration::from_secs(30),    // 30s for search results
             max_entries: 10_000,
+            max_bytes: 64 * 1024 * 1024,            // 64 MiB
+            namespace_quotas: NamespaceQuotas {
+                auth_bytes: 8 * 1024 * 1024,        // auth entries can't be crowded out by search
+                search_bytes: 32 * 1024 * 1024,
+                default_bytes: 24 * 1024 * 1024,
+            },
             enabled: true,
         }
     }
 }
 
-/// In-memory response cache
+/// Per-namespace LRU bookkeeping
+#[derive(Default)]
+struct Namespace {
+    entries: HashMap<String, CacheEntry>,
+    /// Recency stamp -> key, oldest first
+    order: BTreeMap<u64, String>,
+    bytes: usize,
+    evictions: u64,
+}
+
+impl Namespace {
+    fn remove(&mut self, key: &str) -> Option<CacheEntry> {
+        let entry = self.entries.remove(key)?;
+        self.order.remove(&entry.last_used);
+        self.bytes -= entry.size;
+        Some(entry)
+    }
+
+    /// Recency stamp of the least recently used entry
+    fn oldest(&self) -> Option<u64> {
+        self.order.keys().next().copied()
+    }
+
+    fn evict_oldest(&mut self) -> bool {
+        let Some((_, key)) = self.order.pop_first() else {
+            return false;
+        };
+        if let Some(entry) = self.entries.remove(&key) {
+            self.bytes -= entry.size;
+        }
+        self.evictions += 1;
+        true
+    }
+}
+
+struct CacheInner {
+    namespaces: [Namespace; 3],
+    /// Namespace of each key, for lookups that don't know it
+    index: HashMap<String, CacheNamespace>,
+    clock: u64,
+    expirations: u64,
+}
+
+impl CacheInner {
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn total_bytes(&self) -> usize {
+        self.namespaces.iter().map(|ns| ns.bytes).sum()
+    }
+
+    fn remove(&mut self, key: &str) -> Option<CacheEntry> {
+        let namespace = self.index.remove(key)?;
+        self.namespaces[namespace.index()].remove(key)
+    }
+
+    /// Evict the globally least recently used entry
+    fn evict_global_oldest(&mut self) -> bool {
+        let victim = self
+            .namespaces
+            .iter()
+            .enumerate()
+            .filter_map(|(i, ns)| ns.oldest().map(|stamp| (stamp, i)))
+            .min();
+
+        match victim {
+            Some((stamp, i)) => {
+                if let Some(key) = self.namespaces[i].order.get(&stamp).cloned() {
+                    self.index.remove(&key);
+                }
+                self.namespaces[i].evict_oldest()
+            }
+            None => false,
+        }
+    }
+
+    fn evict_namespace_oldest(&mut self, namespace: CacheNamespace) -> bool {
+        let ns = &mut self.namespaces[namespace.index()];
+        if let Some(key) = ns.oldest().and_then(|stamp| ns.order.get(&stamp).cloned()) {
+            self.index.remove(&key);
+        }
+        ns.evict_oldest()
+    }
+}
+
+/// Per-namespace cache statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct NamespaceStats {
+    pub namespace: &'static str,
+    pub entries: usize,
+    pub bytes: usize,
+    pub quota_bytes: usize,
+    pub evictions: u64,
+}
+
+/// Cache statistics
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub entries: usize,
+    pub bytes: usize,
+    pub max_bytes: usize,
+    /// Entries removed to make room (LRU evictions)
+    pub evictions: u64,
+    /// Entries removed because their TTL elapsed
+    pub expirations: u64,
+    pub namespaces: Vec<NamespaceStats>,
+}
+
+/// In-memory response cache with LRU eviction under a byte budget
 #[derive(Clone)]
 pub struct ResponseCache {
-    entries: Arc<DashMap<String, CacheEntry>>,
+    inner: Arc<Mutex<CacheInner>>,
     config: CacheConfig,
     hits: Arc<AtomicU64>,
     misses: Arc<AtomicU64>,
@@ -54,25 +272,41 @@ pub struct ResponseCache {
 
 impl ResponseCache {
     pub fn new(config: CacheConfig) -> Self {
-        let cache = Self {
-            entries: Arc::new(DashMap::new()),
+        Self {
+            inner: Arc::new(Mutex::new(CacheInner {
+                namespaces: Default::default(),
+                index: HashMap::new(),
+                clock: 0,
+                expirations: 0,
+            })),
             config,
             hits: Arc::new(AtomicU64::new(0)),
             misses: Arc::new(AtomicU64::new(0)),
-        };
+        }
+    }
 
-        // Periodic cleanup every 60s
-        let entries = cache.entries.clone();
+    /// Start the task that drops expired entries every minute
+    pub fn spawn_cleanup(self: &Arc<Self>) -> JoinHandle<()> {
+        let inner = self.inner.clone();
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(60));
+            let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
             loop {
                 interval.tick().await;
                 let now = Self::now_epoch();
-                entries.retain(|_, entry| entry.expires_at > now);
+                let mut inner = inner.lock().unwrap();
+                let expired: Vec<String> = inner
+                    .namespaces
+                    .iter()
+                    .flat_map(|ns| ns.entries.iter())
+                    .filter(|(_, entry)| entry.expires_at <= now)
+                    .map(|(key, _)| key.clone())
+                    .collect();
+                for key in &expired {
+                    inner.remove(key);
+                }
+                inner.expirations += expired.len() as u64;
             }
-        });
-
-        cache
+        })
     }
 
     fn now_epoch() -> u64 {
@@ -82,21 +316,55 @@ impl ResponseCache {
             .as_secs()
     }
 
-    /// Build a cache key from method + path + query + user_id
-    pub fn build_key(method: &str, path: &str, query: Option<&str>, user_id: Option<&str>) -> String {
-        let q = query.unwrap_or("");
-        let u = user_id.unwrap_or("anon");
-        format!("cache:{}:{}:{}:{}", method, path, q, u)
+    /// Cache scope for a caller: the workspace when one is active, else the user
+    pub fn scope(user_id: Option<&str>, workspace_id: Option<&str>) -> String {
+        match (workspace_id, user_id) {
+            (Some(ws), _) => format!("ws:{}", ws),
+            (None, Some(user)) => format!("user:{}", user),
+            (None, None) => "anon".to_string(),
+        }
     }
 
-    /// Get TTL based on path
-    pub fn ttl_for_path(&self, path: &str) -> Duration {
+    /// Build a cache key from scope + path + method + user + query + body hash.
+    ///
+    /// Scope and path come first so that `invalidate_prefix` can drop every
+    /// cached response under a path for a whole workspace.
+    pub fn build_key(
+        method: &str,
+        path: &str,
+        query: Option<&str>,
+        user_id: Option<&str>,
+        workspace_id: Option<&str>,
+        body_hash: Option<&str>,
+    ) -> String {
+        format!(
+            "cache:{}:{}:{}:{}:{}:{}",
+            Self::scope(user_id, workspace_id),
+            path,
+            method,
+            user_id.unwrap_or("anon"),
+            query.unwrap_or(""),
+            body_hash.unwrap_or(""),
+        )
+    }
+
+    /// Namespace a path's responses are accounted under
+    pub fn namespace_for_path(path: &str) -> CacheNamespace {
         if path.contains("/verify") || path.contains("/auth") {
-            self.config.auth_ttl
+            CacheNamespace::Auth
         } else if path.contains("/search") {
-            self.config.search_ttl
+            CacheNamespace::Search
         } else {
-            self.config.default_ttl
+            CacheNamespace::Default
+        }
+    }
+
+    /// Get TTL based on path
+    pub fn ttl_for_path(&self, path: &str) -> Duration {
+        match Self::namespace_for_path(path) {
+            CacheNamespace::Auth => self.config.auth_ttl,
+            CacheNamespace::Search => self.config.search_ttl,
+            CacheNamespace::Default => self.config.default_ttl,
         }
     }
 
@@ -106,61 +374,397 @@ impl ResponseCache {
             return None;
         }
 
-        let entry = self.entries.get(key)?;
-        if entry.expires_at < Self::now_epoch() {
-            drop(entry);
-            self.entries.remove(key);
+        let mut inner = self.inner.lock().unwrap();
+        let Some(namespace) = inner.index.get(key).copied() else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+
+        let expired = inner.namespaces[namespace.index()]
+            .entries
+            .get(key)
+            .map(|entry| entry.expires_at < Self::now_epoch())
+            .unwrap_or(true);
+        if expired {
+            inner.remove(key);
+            inner.expirations += 1;
             self.misses.fetch_add(1, Ordering::Relaxed);
             return None;
         }
+
+        // Mark as most recently used
+        let stamp = inner.tick();
+        let ns = &mut inner.namespaces[namespace.index()];
+        let entry = ns.entries.get_mut(key)?;
+        ns.order.remove(&entry.last_used);
+        entry.last_used = stamp;
+        ns.order.insert(stamp, key.to_string());
+
         self.hits.fetch_add(1, Ordering::Relaxed);
         Some((entry.data.clone(), entry.status, entry.content_type.clone()))
     }
 
     /// Store a response in cache
-    pub fn set(&self, key: &str, data: Vec<u8>, status: u16, content_type: &str, ttl: Duration) {
+    pub fn set(
+        &self,
+        namespace: CacheNamespace,
+        key: &str,
+        data: Vec<u8>,
+        status: u16,
+        content_type: &str,
+        ttl: Duration,
+    ) {
         if !self.config.enabled {
             return;
         }
 
-        // Evict if at capacity
-        if self.entries.len() >= self.config.max_entries {
-            // Simple eviction: remove 10% oldest
-            let to_remove = self.config.max_entries / 10;
-            let mut keys_to_remove = Vec::with_capacity(to_remove);
-            let now = Self::now_epoch();
-            for entry in self.entries.iter() {
-                if entry.expires_at < now || keys_to_remove.len() < to_remove {
-                    keys_to_remove.push(entry.key().clone());
-                }
-                if keys_to_remove.len() >= to_remove {
-                    break;
-                }
+        let size = data.len() + key.len() + content_type.len() + ENTRY_OVERHEAD_BYTES;
+        let quota = self.config.namespace_quotas.for_namespace(namespace);
+        if size > quota || size > self.config.max_bytes {
+            // Would evict the whole namespace to fit; not worth caching
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove(key);
+
+        // Make room within the namespace quota first, so one namespace
+        // cannot push out another's entries
+        while inner.namespaces[namespace.index()].bytes + size > quota {
+            if !inner.evict_namespace_oldest(namespace) {
+                break;
             }
-            for k in keys_to_remove {
-                self.entries.remove(&k);
+        }
+
+        // Then within the global byte and entry budgets, evicting the
+        // least recently used entry across all namespaces
+        while inner.total_bytes() + size > self.config.max_bytes
+            || inner.index.len() >= self.config.max_entries
+        {
+            if !inner.evict_global_oldest() {
+                break;
             }
         }
 
-        self.entries.insert(key.to_string(), CacheEntry {
+        let stamp = inner.tick();
+        let ns = &mut inner.namespaces[namespace.index()];
+        ns.order.insert(stamp, key.to_string());
+        ns.bytes += size;
+        ns.entries.insert(key.to_string(), CacheEntry {
             data,
             status,
             content_type: content_type.to_string(),
             expires_at: Self::now_epoch() + ttl.as_secs(),
+            size,
+            last_used: stamp,
         });
+        inner.index.insert(key.to_string(), namespace);
     }
 
     /// Invalidate cache entries matching a prefix
     pub fn invalidate_prefix(&self, prefix: &str) {
-        self.entries.retain(|k, _| !k.starts_with(prefix));
+        let mut inner = self.inner.lock().unwrap();
+        let keys: Vec<String> = inner
+            .index
+            .keys()
+            .filter(|k| k.starts_with(prefix))
+            .cloned()
+            .collect();
+        for key in &keys {
+            inner.remove(key);
+        }
+    }
+
+    /// Invalidate everything derived from a caller's sources, in both the
+    /// workspace scope and the caller's personal scope
+    pub fn invalidate_sources(&self, user_id: &str, workspace_id: Option<&str>) {
+        let mut scopes = vec![Self::scope(Some(user_id), None)];
+        if workspace_id.is_some() {
+            scopes.push(Self::scope(Some(user_id), workspace_id));
+        }
+
+        for scope in &scopes {
+            for path in SOURCE_DEPENDENT_PATHS {
+                self.invalidate_prefix(&format!("cache:{}:{}", scope, path));
+            }
+        }
     }
 
     /// Get cache statistics
-    pub fn stats(&self) -> (u64, u64, usize) {
-        (
-            self.hits.load(Ordering::Relaxed),
-            self.misses.load(Ordering::Relaxed),
-            self.entries.len(),
-        )
+    pub fn stats(&self) -> CacheStats {
+        let inner = self.inner.lock().unwrap();
+        let namespaces: Vec<NamespaceStats> = CacheNamespace::ALL
+            .iter()
+            .map(|namespace| {
+                let ns = &inner.namespaces[namespace.index()];
+                NamespaceStats {
+                    namespace: namespace.as_str(),
+                    entries: ns.entries.len(),
+                    bytes: ns.bytes,
+                    quota_bytes: self.config.namespace_quotas.for_namespace(*namespace),
+                    evictions: ns.evictions,
+                }
+            })
+            .collect();
+
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            entries: inner.index.len(),
+            bytes: inner.total_bytes(),
+            max_bytes: self.config.max_bytes,
+            evictions: namespaces.iter().map(|ns| ns.evictions).sum(),
+            expirations: inner.expirations,
+            namespaces,
+        }
+    }
+}
+
+/// Whether a request may be served from / stored in the cache
+fn is_cacheable(method: &Method, path: &str) -> bool {
+    if *method == Method::GET {
+        // Status endpoints are polled for progress and must stay fresh
+        !path.ends_with("/status")
+    } else if *method == Method::POST {
+        CACHEABLE_POST_PATHS.contains(&path)
+    } else {
+        false
+    }
+}
+
+/// Response caching middleware
+///
+/// Caches successful GET responses and read-only search POSTs per user and
+/// workspace. Clients can send `Cache-Control: no-cache` to skip the lookup
+/// (the fresh response is still stored) or `no-store` to bypass the cache.
+pub async fn response_cache_middleware(
+    State(cache): State<Arc<ResponseCache>>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+
+    if !cache.config.enabled || !is_cacheable(request.method(), &path) {
+        return Ok(next.run(request).await);
+    }
+
+    let cache_control = request
+        .headers()
+        .get(header::CACHE_CONTROL)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if cache_control.contains("no-store") {
+        return Ok(next.run(request).await);
+    }
+    let skip_lookup = cache_control.contains("no-cache");
+
+    let user = request.extensions().get::<AuthenticatedUser>().map(|u| u.0.clone());
+    let user_id = user.as_ref().map(|u| u.id.as_str());
+    let workspace_id = user.as_ref().and_then(|u| u.workspace_id.as_deref());
+    let method = request.method().clone();
+    let query = request.uri().query().map(|q| q.to_string());
+
+    // POST bodies are part of the key; buffer and hash them, then rebuild the request
+    let (request, body_hash) = if method == Method::POST {
+        let (parts, body) = request.into_parts();
+        let bytes = to_bytes(body, MAX_CACHEABLE_REQUEST_BYTES)
+            .await
+            .map_err(|e| AppError::ValidationError(format!("Failed to read request body: {}", e)))?;
+        let hash = hex::encode(Sha256::digest(&bytes));
+        (Request::from_parts(parts, Body::from(bytes)), Some(hash))
+    } else {
+        (request, None)
+    };
+
+    let key = ResponseCache::build_key(
+        method.as_str(),
+        &path,
+        query.as_deref(),
+        user_id,
+        workspace_id,
+        body_hash.as_deref(),
+    );
+
+    if !skip_lookup {
+        if let Some((data, status, content_type)) = cache.get(&key) {
+            let status = StatusCode::from_u16(status).unwrap_or(StatusCode::OK);
+            let mut response = (status, Body::from(data)).into_response();
+            if let Ok(val) = HeaderValue::from_str(&content_type) {
+                response.headers_mut().insert(header::CONTENT_TYPE, val);
+            }
+            response.headers_mut().insert("X-Cache", HeaderValue::from_static("HIT"));
+            return Ok(response);
+        }
+    }
+
+    let response = next.run(request).await;
+    if response.status() != StatusCode::OK {
+        return Ok(response);
+    }
+
+    // Leave streaming or oversized bodies untouched
+    let fits = response
+        .body()
+        .size_hint()
+        .upper()
+        .map(|n| n as usize <= MAX_CACHEABLE_RESPONSE_BYTES)
+        .unwrap_or(false);
+    if !fits {
+        return Ok(response);
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = to_bytes(body, MAX_CACHEABLE_RESPONSE_BYTES)
+        .await
+        .map_err(|e| AppError::Internal(format!("Failed to read response body: {}", e)))?;
+
+    let content_type = parts
+        .headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json")
+        .to_string();
+    cache.set(
+        ResponseCache::namespace_for_path(&path),
+        &key,
+        bytes.to_vec(),
+        parts.status.as_u16(),
+        &content_type,
+        cache.ttl_for_path(&path),
+    );
+
+    parts.headers.insert("X-Cache", HeaderValue::from_static("MISS"));
+    Ok(Response::from_parts(parts, Body::from(bytes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every test entry is 2-byte key + 100-byte body + 4-byte content type
+    const ENTRY: usize = 2 + 100 + 4 + ENTRY_OVERHEAD_BYTES;
+    const TTL: Duration = Duration::from_secs(60);
+
+    fn cache(max_bytes: usize, auth: usize, search: usize, default: usize) -> ResponseCache {
+        ResponseCache::new(CacheConfig {
+            max_bytes,
+            namespace_quotas: NamespaceQuotas {
+                auth_bytes: auth,
+                search_bytes: search,
+                default_bytes: default,
+            },
+            ..CacheConfig::default()
+        })
+    }
+
+    fn put(cache: &ResponseCache, namespace: CacheNamespace, key: &str) {
+        assert_eq!(key.len(), 2);
+        cache.set(namespace, key, vec![0; 100], 200, "json", TTL);
+    }
+
+    fn cached(cache: &ResponseCache, key: &str) -> bool {
+        cache.get(key).is_some()
+    }
+
+    fn namespace_stats(cache: &ResponseCache, namespace: CacheNamespace) -> NamespaceStats {
+        cache
+            .stats()
+            .namespaces
+            .into_iter()
+            .find(|ns| ns.namespace == namespace.as_str())
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn byte_budget_evicts_least_recently_used() {
+        let cache = cache(3 * ENTRY, usize::MAX, usize::MAX, usize::MAX);
+        put(&cache, CacheNamespace::Default, "k1");
+        put(&cache, CacheNamespace::Default, "k2");
+        put(&cache, CacheNamespace::Default, "k3");
+        assert_eq!(cache.stats().bytes, 3 * ENTRY);
+
+        // Reading k1 makes k2 the least recently used
+        assert!(cached(&cache, "k1"));
+        put(&cache, CacheNamespace::Default, "k4");
+
+        assert!(!cached(&cache, "k2"));
+        assert!(cached(&cache, "k1"));
+        assert!(cached(&cache, "k3"));
+        assert!(cached(&cache, "k4"));
+        let stats = cache.stats();
+        assert_eq!(stats.bytes, 3 * ENTRY);
+        assert_eq!(stats.evictions, 1);
+    }
+
+    #[tokio::test]
+    async fn namespace_quota_only_evicts_its_own_entries() {
+        let cache = cache(100 * ENTRY, 2 * ENTRY, 2 * ENTRY, 2 * ENTRY);
+        put(&cache, CacheNamespace::Auth, "a1");
+        put(&cache, CacheNamespace::Search, "s1");
+        put(&cache, CacheNamespace::Search, "s2");
+        put(&cache, CacheNamespace::Search, "s3");
+
+        // a1 is the oldest entry overall but its namespace has room
+        assert!(cached(&cache, "a1"));
+        assert!(!cached(&cache, "s1"));
+        assert!(cached(&cache, "s2"));
+        assert!(cached(&cache, "s3"));
+
+        let search = namespace_stats(&cache, CacheNamespace::Search);
+        assert_eq!(search.entries, 2);
+        assert_eq!(search.bytes, 2 * ENTRY);
+        assert_eq!(search.evictions, 1);
+        assert_eq!(namespace_stats(&cache, CacheNamespace::Auth).evictions, 0);
+    }
+
+    #[tokio::test]
+    async fn global_budget_evicts_oldest_across_namespaces() {
+        let cache = cache(3 * ENTRY, 3 * ENTRY, 3 * ENTRY, 3 * ENTRY);
+        put(&cache, CacheNamespace::Search, "s1");
+        put(&cache, CacheNamespace::Auth, "a1");
+        put(&cache, CacheNamespace::Default, "d1");
+        put(&cache, CacheNamespace::Auth, "a2");
+
+        assert!(!cached(&cache, "s1"));
+        assert!(cached(&cache, "a1"));
+        assert!(cached(&cache, "d1"));
+        assert!(cached(&cache, "a2"));
+        assert_eq!(namespace_stats(&cache, CacheNamespace::Search).evictions, 1);
+    }
+
+    #[tokio::test]
+    async fn entries_larger_than_the_quota_are_not_cached() {
+        let cache = cache(100 * ENTRY, ENTRY - 1, usize::MAX, usize::MAX);
+        put(&cache, CacheNamespace::Auth, "a1");
+        assert!(!cached(&cache, "a1"));
+        assert_eq!(cache.stats().bytes, 0);
+    }
+
+    #[tokio::test]
+    async fn replacing_a_key_does_not_double_count() {
+        let cache = cache(100 * ENTRY, usize::MAX, usize::MAX, usize::MAX);
+        put(&cache, CacheNamespace::Default, "k1");
+        put(&cache, CacheNamespace::Default, "k1");
+        let stats = cache.stats();
+        assert_eq!(stats.entries, 1);
+        assert_eq!(stats.bytes, ENTRY);
+    }
+
+    #[tokio::test]
+    async fn invalidation_releases_bytes() {
+        let cache = cache(100 * ENTRY, usize::MAX, usize::MAX, usize::MAX);
+        let key = ResponseCache::build_key("GET", "/v1/sources", None, Some("u1"), Some("w1"), None);
+        cache.set(CacheNamespace::Default, &key, vec![0; 100], 200, "json", TTL);
+        put(&cache, CacheNamespace::Default, "k1");
+
+        cache.invalidate_sources("u1", Some("w1"));
+        assert!(!cached(&cache, &key));
+        assert!(cached(&cache, "k1"));
+        assert_eq!(cache.stats().bytes, ENTRY);
     }
 }
@@ -2,29 +2,58 @@
 //!
 //! Implements a three-state circuit breaker (Closed/Open/HalfOpen)
 //! to isolate failures and prevent cascading outages.
+//!
+//! Each breaker's state lives behind its own mutex so every transition is
+//! atomic. Calls take a [`CallPermit`] and report their outcome through it;
+//! in half-open only a bounded number of probe permits are handed out, and a
+//! permit dropped without an outcome gives its probe slot back. Outcomes are
+//! tagged with the generation the call started in, so a slow call that began
+//! before a transition cannot affect the state that followed it.
 
 use dashmap::DashMap;
-use std::sync::atomic::{AtomicU64, AtomicU32, Ordering};
-use std::sync::Arc;
+use serde::Serialize;
+use schemars::JsonSchema;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
+use crate::metrics::METRICS;
+
 /// Circuit breaker states
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
 pub enum CircuitState {
     Closed,
     Open,
     HalfOpen,
 }
 
+impl CircuitState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
 /// Configuration for a circuit breaker
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct CircuitBreakerConfig {
-    /// Number of consecutive failures to trip the breaker
+    /// Number of consecutive failures to trip the breaker (0 = disabled)
     pub failure_threshold: u32,
     /// Duration the circuit stays open before trying half-open
     pub open_duration: Duration,
     /// Number of successful probes in half-open to close the circuit
     pub half_open_successes: u32,
+    /// Trip when this percentage of the sliding window failed (None = disabled)
+    pub failure_rate_threshold: Option<u32>,
+    /// Number of most recent calls in the sliding window
+    pub window_size: u32,
+    /// Calls required in the window before the failure rate is evaluated
+    pub min_calls: u32,
 }
 
 impl Default for CircuitBreakerConfig {
@@ -33,34 +62,337 @@ impl Default for CircuitBreakerConfig {
             failure_threshold: 5,
             open_duration: Duration::from_secs(30),
             half_open_successes: 2,
+            failure_rate_threshold: None,
+            window_size: 20,
+            min_calls: 10,
+        }
+    }
+}
+
+/// Source of time for breakers; swap in [`ManualClock`] to drive them deterministically
+pub trait Clock: Send + Sync {
+    /// Milliseconds since an arbitrary fixed point
+    fn now_millis(&self) -> u64;
+}
+
+/// Wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_millis(&self) -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+}
+
+/// Clock that only moves when told to
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    now: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(start_millis: u64) -> Self {
+        Self {
+            now: Arc::new(AtomicU64::new(start_millis)),
+        }
+    }
+
+    pub fn advance(&self, by: Duration) {
+        self.now.fetch_add(by.as_millis() as u64, Ordering::SeqCst);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_millis(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
+
+/// Position in the state machine
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Closed,
+    Open { until: u64 },
+    HalfOpen { in_flight: u32, successes: u32 },
+}
+
+impl Phase {
+    fn state(&self) -> CircuitState {
+        match self {
+            Phase::Closed => CircuitState::Closed,
+            Phase::Open { .. } => CircuitState::Open,
+            Phase::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+/// Mutable breaker state, only touched with the breaker's lock held
+struct Inner {
+    phase: Phase,
+    /// Operator override pinning the breaker open or closed
+    forced: Option<CircuitState>,
+    /// Bumped on every transition; permits from older generations are ignored
+    generation: u64,
+    consecutive_failures: u32,
+    consecutive_successes: u32,
+    /// Outcomes of the most recent closed-state calls (true = failure)
+    window: VecDeque<bool>,
+    window_failures: u32,
+    /// Number of state transitions since startup
+    transitions: u64,
+}
+
+/// Per-service circuit breaker
+struct Breaker {
+    service: String,
+    config: CircuitBreakerConfig,
+    clock: Arc<dyn Clock>,
+    inner: Mutex<Inner>,
+}
+
+impl Breaker {
+    fn new(service: &str, config: CircuitBreakerConfig, clock: Arc<dyn Clock>) -> Self {
+        Self {
+            service: service.to_string(),
+            config,
+            clock,
+            inner: Mutex::new(Inner {
+                phase: Phase::Closed,
+                forced: None,
+                generation: 0,
+                consecutive_failures: 0,
+                consecutive_successes: 0,
+                window: VecDeque::new(),
+                window_failures: 0,
+                transitions: 0,
+            }),
+        }
+    }
+
+    fn max_probes(&self) -> u32 {
+        self.config.half_open_successes.max(1)
+    }
+
+    /// Move to `phase`, count the change and emit a structured event for it
+    fn transition(&self, inner: &mut Inner, phase: Phase, reason: &str) {
+        let from = inner.phase.state();
+        let to = phase.state();
+        inner.phase = phase;
+        inner.generation += 1;
+        inner.transitions += 1;
+        METRICS.record_breaker_transition(&self.service, from, to);
+
+        if to == CircuitState::Open {
+            tracing::warn!(
+                target: "circuit_breaker",
+                service = %self.service,
+                from = from.as_str(),
+                to = to.as_str(),
+                reason = reason,
+                failures = inner.consecutive_failures,
+                "Circuit breaker state transition"
+            );
+        } else {
+            tracing::info!(
+                target: "circuit_breaker",
+                service = %self.service,
+                from = from.as_str(),
+                to = to.as_str(),
+                reason = reason,
+                failures = inner.consecutive_failures,
+                "Circuit breaker state transition"
+            );
+        }
+    }
+
+    fn trip(&self, inner: &mut Inner, reason: &str) {
+        let until = self.clock.now_millis() + self.config.open_duration.as_millis() as u64;
+        self.transition(inner, Phase::Open { until }, reason);
+    }
+
+    fn close(&self, inner: &mut Inner, reason: &str) {
+        inner.consecutive_failures = 0;
+        inner.window.clear();
+        inner.window_failures = 0;
+        self.transition(inner, Phase::Closed, reason);
+    }
+
+    /// State as callers would currently observe it
+    fn state(&self, inner: &Inner) -> CircuitState {
+        if let Some(forced) = inner.forced {
+            return forced;
+        }
+        match inner.phase {
+            Phase::Open { until } if self.clock.now_millis() >= until => CircuitState::HalfOpen,
+            phase => phase.state(),
+        }
+    }
+
+    fn acquire(self: &Arc<Self>) -> Option<CallPermit> {
+        let mut inner = self.inner.lock().unwrap();
+
+        match inner.forced {
+            Some(CircuitState::Open) => return None,
+            Some(_) => return Some(CallPermit::new(self.clone(), inner.generation)),
+            None => {}
         }
+
+        if let Phase::Open { until } = inner.phase {
+            if self.clock.now_millis() < until {
+                return None;
+            }
+            self.transition(&mut inner, Phase::HalfOpen { in_flight: 0, successes: 0 }, "open_duration_elapsed");
+        }
+
+        if let Phase::HalfOpen { in_flight, successes } = inner.phase {
+            if in_flight >= self.max_probes() {
+                return None;
+            }
+            inner.phase = Phase::HalfOpen { in_flight: in_flight + 1, successes };
+        }
+
+        Some(CallPermit::new(self.clone(), inner.generation))
+    }
+
+    /// Apply a call outcome; `None` means the call was abandoned
+    fn complete(&self, generation: u64, failed: Option<bool>) {
+        let mut inner = self.inner.lock().unwrap();
+        if generation != inner.generation {
+            return;
+        }
+
+        if let Some(failed) = failed {
+            if failed {
+                inner.consecutive_failures += 1;
+                inner.consecutive_successes = 0;
+            } else {
+                inner.consecutive_successes += 1;
+                inner.consecutive_failures = 0;
+            }
+        }
+
+        match (inner.phase, failed) {
+            (Phase::Closed, Some(failed)) => {
+                inner.window.push_back(failed);
+                if failed {
+                    inner.window_failures += 1;
+                }
+                while inner.window.len() > self.config.window_size.max(1) as usize {
+                    if inner.window.pop_front() == Some(true) {
+                        inner.window_failures -= 1;
+                    }
+                }
+
+                if inner.forced.is_none() && failed {
+                    if self.config.failure_threshold > 0
+                        && inner.consecutive_failures >= self.config.failure_threshold
+                    {
+                        self.trip(&mut inner, "failure_threshold");
+                    } else if self.failure_rate_exceeded(&inner) {
+                        self.trip(&mut inner, "failure_rate");
+                    }
+                }
+            }
+            (Phase::HalfOpen { in_flight, successes }, outcome) => {
+                let in_flight = in_flight.saturating_sub(1);
+                match outcome {
+                    Some(true) => self.trip(&mut inner, "probe_failed"),
+                    Some(false) if successes + 1 >= self.config.half_open_successes => {
+                        self.close(&mut inner, "service_recovered");
+                    }
+                    Some(false) => inner.phase = Phase::HalfOpen { in_flight, successes: successes + 1 },
+                    None => inner.phase = Phase::HalfOpen { in_flight, successes },
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn failure_rate_exceeded(&self, inner: &Inner) -> bool {
+        let Some(threshold) = self.config.failure_rate_threshold else {
+            return false;
+        };
+        let calls = inner.window.len() as u32;
+        calls >= self.config.min_calls.max(1) && inner.window_failures * 100 >= threshold * calls
     }
 }
 
-/// Per-service circuit breaker state
-struct BreakerState {
-    consecutive_failures: AtomicU32,
-    consecutive_successes: AtomicU32,
-    opened_at: AtomicU64,     // epoch millis, 0 = closed
-    half_open_probes: AtomicU32,
+/// Permission to make one call through a breaker
+///
+/// Report the outcome with [`CallPermit::success`] or [`CallPermit::failure`].
+/// Dropping the permit without either releases it without recording anything.
+pub struct CallPermit {
+    breaker: Arc<Breaker>,
+    generation: u64,
+    done: bool,
 }
 
-impl BreakerState {
-    fn new() -> Self {
+impl CallPermit {
+    fn new(breaker: Arc<Breaker>, generation: u64) -> Self {
         Self {
-            consecutive_failures: AtomicU32::new(0),
-            consecutive_successes: AtomicU32::new(0),
-            opened_at: AtomicU64::new(0),
-            half_open_probes: AtomicU32::new(0),
+            breaker,
+            generation,
+            done: false,
+        }
+    }
+
+    /// Record a successful call
+    pub fn success(mut self) {
+        self.done = true;
+        self.breaker.complete(self.generation, Some(false));
+    }
+
+    /// Record a failed call
+    pub fn failure(mut self) {
+        self.done = true;
+        self.breaker.complete(self.generation, Some(true));
+    }
+}
+
+impl Drop for CallPermit {
+    fn drop(&mut self) {
+        if !self.done {
+            self.breaker.complete(self.generation, None);
         }
     }
 }
 
+/// Point-in-time view of one breaker, for the admin API and metrics
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BreakerSnapshot {
+    pub service: String,
+    pub state: CircuitState,
+    /// Set when an operator has pinned the breaker open or closed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub forced: Option<CircuitState>,
+    pub consecutive_failures: u32,
+    pub consecutive_successes: u32,
+    /// Calls in the sliding window and the share of them that failed (0-100)
+    pub window_calls: u32,
+    pub window_failure_rate: u32,
+    /// Milliseconds until an open breaker lets a probe through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub half_open_in_ms: Option<u64>,
+    pub transitions: u64,
+    pub failure_threshold: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub failure_rate_threshold: Option<u32>,
+    pub open_duration_secs: u64,
+    pub half_open_successes: u32,
+}
+
 /// Registry of circuit breakers keyed by service name
 #[derive(Clone)]
 pub struct CircuitBreakerRegistry {
-    breakers: Arc<DashMap<String, Arc<BreakerState>>>,
+    breakers: Arc<DashMap<String, Arc<Breaker>>>,
     config: CircuitBreakerConfig,
+    /// Per-service configuration, taking precedence over `config`
+    overrides: Arc<HashMap<String, CircuitBreakerConfig>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl CircuitBreakerRegistry {
@@ -68,108 +400,137 @@ impl CircuitBreakerRegistry {
         Self {
             breakers: Arc::new(DashMap::new()),
             config,
+            overrides: Arc::new(HashMap::new()),
+            clock: Arc::new(SystemClock),
         }
     }
 
-    fn get_or_create(&self, service: &str) -> Arc<BreakerState> {
+    /// Use a different configuration for the named services
+    pub fn with_overrides(mut self, overrides: HashMap<String, CircuitBreakerConfig>) -> Self {
+        self.overrides = Arc::new(overrides);
+        self
+    }
+
+    /// Use a different time source (applies to breakers created afterwards)
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Effective configuration for a service
+    pub fn config_for(&self, service: &str) -> &CircuitBreakerConfig {
+        self.overrides.get(service).unwrap_or(&self.config)
+    }
+
+    /// Track a service so it is listed before its first call
+    pub fn register(&self, service: &str) {
+        self.get_or_create(service);
+    }
+
+    /// Whether a breaker exists for the service
+    pub fn contains(&self, service: &str) -> bool {
+        self.breakers.contains_key(service)
+    }
+
+    fn get_or_create(&self, service: &str) -> Arc<Breaker> {
         self.breakers
             .entry(service.to_string())
-            .or_insert_with(|| Arc::new(BreakerState::new()))
+            .or_insert_with(|| {
+                Arc::new(Breaker::new(service, self.config_for(service).clone(), self.clock.clone()))
+            })
             .clone()
     }
 
-    fn now_millis() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64
-    }
-
     /// Get the current state of the circuit for a given service
     pub fn state(&self, service: &str) -> CircuitState {
         let breaker = self.get_or_create(service);
-        let opened = breaker.opened_at.load(Ordering::Relaxed);
-        if opened == 0 {
-            return CircuitState::Closed;
-        }
-        let elapsed = Self::now_millis() - opened;
-        if elapsed >= self.config.open_duration.as_millis() as u64 {
-            CircuitState::HalfOpen
-        } else {
-            CircuitState::Open
-        }
+        let inner = breaker.inner.lock().unwrap();
+        breaker.state(&inner)
     }
 
-    /// Check if a request is allowed through the circuit
-    pub fn allow_request(&self, service: &str) -> bool {
-        match self.state(service) {
-            CircuitState::Closed => true,
-            CircuitState::Open => false,
-            CircuitState::HalfOpen => {
-                // Allow limited probes in half-open
-                let breaker = self.get_or_create(service);
-                let probes = breaker.half_open_probes.fetch_add(1, Ordering::Relaxed);
-                probes < self.config.half_open_successes + 1
-            }
-        }
+    /// Ask to make a call; `None` means the circuit is rejecting calls
+    pub fn try_acquire(&self, service: &str) -> Option<CallPermit> {
+        self.get_or_create(service).acquire()
     }
 
-    /// Record a successful request
-    pub fn record_success(&self, service: &str) {
+    /// Pin a breaker open or closed until it is reset
+    pub fn force(&self, service: &str, state: CircuitState) {
         let breaker = self.get_or_create(service);
-        breaker.consecutive_failures.store(0, Ordering::Relaxed);
-        let successes = breaker.consecutive_successes.fetch_add(1, Ordering::Relaxed) + 1;
-
-        let opened = breaker.opened_at.load(Ordering::Relaxed);
-        if opened > 0 {
-            // In half-open state, check if enough successes to close
-            if successes >= self.config.half_open_successes {
-                breaker.opened_at.store(0, Ordering::Relaxed);
-                breaker.consecutive_successes.store(0, Ordering::Relaxed);
-                breaker.half_open_probes.store(0, Ordering::Relaxed);
-                tracing::info!(
-                    service = service,
-                    "Circuit breaker CLOSED — service recovered"
-                );
+        let mut inner = breaker.inner.lock().unwrap();
+        let forced = match state {
+            CircuitState::Open => CircuitState::Open,
+            _ => CircuitState::Closed,
+        };
+        inner.forced = Some(forced);
+        if inner.phase.state() != forced {
+            match forced {
+                CircuitState::Open => breaker.transition(&mut inner, Phase::Open { until: u64::MAX }, "forced"),
+                _ => breaker.close(&mut inner, "forced"),
             }
         }
     }
 
-    /// Record a failed request
-    pub fn record_failure(&self, service: &str) {
+    /// Clear any override and counters, returning the breaker to closed
+    pub fn reset(&self, service: &str) {
         let breaker = self.get_or_create(service);
-        breaker.consecutive_successes.store(0, Ordering::Relaxed);
-        let failures = breaker.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
-
-        if failures >= self.config.failure_threshold {
-            let opened = breaker.opened_at.load(Ordering::Relaxed);
-            if opened == 0 {
-                breaker.opened_at.store(Self::now_millis(), Ordering::Relaxed);
-                breaker.half_open_probes.store(0, Ordering::Relaxed);
-                tracing::warn!(
-                    service = service,
-                    failures = failures,
-                    "Circuit breaker OPENED — isolating failing service"
-                );
-            } else {
-                // Re-open in half-open state on failure
-                breaker.opened_at.store(Self::now_millis(), Ordering::Relaxed);
-                breaker.half_open_probes.store(0, Ordering::Relaxed);
-                tracing::warn!(
-                    service = service,
-                    "Circuit breaker re-OPENED from half-open probe failure"
-                );
-            }
+        let mut inner = breaker.inner.lock().unwrap();
+        inner.forced = None;
+        inner.consecutive_successes = 0;
+        if matches!(inner.phase, Phase::Closed) {
+            inner.consecutive_failures = 0;
+            inner.window.clear();
+            inner.window_failures = 0;
+        } else {
+            breaker.close(&mut inner, "reset");
         }
     }
 
     /// Get metrics for monitoring
     pub fn metrics(&self, service: &str) -> (CircuitState, u32, u32) {
         let breaker = self.get_or_create(service);
+        let inner = breaker.inner.lock().unwrap();
         (
-            self.state(service),
-            breaker.consecutive_failures.load(Ordering::Relaxed),
-            breaker.consecutive_successes.load(Ordering::Relaxed),
+            breaker.state(&inner),
+            inner.consecutive_failures,
+            inner.consecutive_successes,
         )
     }
+
+    /// Snapshot of a single breaker
+    pub fn snapshot(&self, service: &str) -> BreakerSnapshot {
+        let breaker = self.get_or_create(service);
+        let inner = breaker.inner.lock().unwrap();
+        let config = &breaker.config;
+        let state = breaker.state(&inner);
+        let half_open_in_ms = match (inner.phase, inner.forced) {
+            (Phase::Open { until }, None) if state == CircuitState::Open => {
+                Some(until.saturating_sub(breaker.clock.now_millis()))
+            }
+            _ => None,
+        };
+        let window_calls = inner.window.len() as u32;
+
+        BreakerSnapshot {
+            service: breaker.service.clone(),
+            state,
+            forced: inner.forced,
+            consecutive_failures: inner.consecutive_failures,
+            consecutive_successes: inner.consecutive_successes,
+            window_calls,
+            window_failure_rate: (inner.window_failures * 100).checked_div(window_calls).unwrap_or(0),
+            half_open_in_ms,
+            transitions: inner.transitions,
+            failure_threshold: config.failure_threshold,
+            failure_rate_threshold: config.failure_rate_threshold,
+            open_duration_secs: config.open_duration.as_secs(),
+            half_open_successes: config.half_open_successes,
+        }
+    }
+
+    /// Snapshots of every known breaker, sorted by service name
+    pub fn snapshot_all(&self) -> Vec<BreakerSnapshot> {
+        let mut services: Vec<String> = self.breakers.iter().map(|e| e.key().clone()).collect();
+        services.sort();
+        services.iter().map(|s| self.snapshot(s)).collect()
+    }
 }
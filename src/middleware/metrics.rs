@@ -0,0 +1,31 @@
+//! Request metrics middleware
+//!
+//! Counts requests and records latency labelled by method, route template
+//! and status. Mount with `Router::layer` on the fully built router so the
+//! matched route template is available.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::time::Instant;
+
+use crate::metrics::METRICS;
+
+/// Request metrics middleware
+pub async fn metrics_middleware(request: Request, next: Next) -> Response {
+    let start = Instant::now();
+    let method = request.method().as_str().to_string();
+    // Label by template (`/v1/sources/:id`), never by raw path, to bound cardinality
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+
+    let response = next.run(request).await;
+
+    METRICS.observe_request(&method, &route, response.status().as_u16(), start.elapsed());
+    response
+}
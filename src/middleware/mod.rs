@@ -1,13 +1,28 @@
 //! Middleware for request processing
 
 pub mod auth;
+pub mod auth_cache;
+pub mod jwt;
+pub mod permissions;
 pub mod rate_limit;
+pub mod rate_limit_policy;
 pub mod circuit_breaker;
 pub mod cache;
+pub mod metrics;
+pub mod request_span;
 pub mod security_headers;
+pub mod service_identity;
 pub mod zero_trust;
 
 pub use auth::AuthLayer;
-pub use circuit_breaker::{CircuitBreakerRegistry, CircuitBreakerConfig, CircuitState};
-pub use cache::{ResponseCache, CacheConfig};
+pub use auth_cache::AuthCache;
+pub use jwt::JwtVerifier;
+pub use permissions::Permission;
+pub use circuit_breaker::{
+    BreakerSnapshot, CallPermit, CircuitBreakerRegistry, CircuitBreakerConfig, CircuitState, Clock, ManualClock, SystemClock,
+};
+pub use cache::{ResponseCache, CacheConfig, CacheNamespace, CacheStats};
 pub use zero_trust::ZeroTrustLayer;
+pub use service_identity::ServiceIdentity;
+pub use rate_limit::{RateLimitConfig, RateLimitStore, InMemoryRateLimitStore, PostgresRateLimitStore};
+pub use rate_limit_policy::{PolicyRegistry, PolicySet, RateLimitPolicy};
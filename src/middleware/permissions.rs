@@ -0,0 +1,170 @@
+//! Role-based access control
+//!
+//! Routes declare the permission they require with `require_permission`,
+//! mounted per route inside `auth_middleware`. A user's permissions come
+//! from `User.roles`, each either a role name, expanded through
+//! `role_permissions`, or a permission string such as `search:read`. An API
+//! key has no roles; each of its scopes grants the permission it names
+//! (`scope_permission`), and no scope grants `admin`.
+
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use std::fmt;
+
+use crate::error::AppError;
+use crate::models::User;
+use super::auth::AuthenticatedUser;
+
+/// An action a caller may be allowed to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Permission {
+    SourcesRead,
+    SourcesWrite,
+    SearchRead,
+    ProcessingRead,
+    ProcessingWrite,
+    AgentsInvoke,
+    /// Create, update and delete `/api/*` URLs, repositories, documents and agents
+    ResourcesWrite,
+    /// Export or erase one's own data (GDPR)
+    AccountData,
+    ComplianceRead,
+    /// Manage one's outbound webhook subscriptions
+    WebhooksManage,
+    Admin,
+}
+
+impl Permission {
+    pub const ALL: &'static [Permission] = &[
+        Permission::SourcesRead,
+        Permission::SourcesWrite,
+        Permission::SearchRead,
+        Permission::ProcessingRead,
+        Permission::ProcessingWrite,
+        Permission::AgentsInvoke,
+        Permission::ResourcesWrite,
+        Permission::AccountData,
+        Permission::ComplianceRead,
+        Permission::WebhooksManage,
+        Permission::Admin,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Permission::SourcesRead => "sources:read",
+            Permission::SourcesWrite => "sources:write",
+            Permission::SearchRead => "search:read",
+            Permission::ProcessingRead => "processing:read",
+            Permission::ProcessingWrite => "processing:write",
+            Permission::AgentsInvoke => "agents:invoke",
+            Permission::ResourcesWrite => "resources:write",
+            Permission::AccountData => "account:data",
+            Permission::ComplianceRead => "compliance:read",
+            Permission::WebhooksManage => "webhooks:manage",
+            Permission::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.as_str() == value)
+    }
+}
+
+impl fmt::Display for Permission {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Permissions granted by a role
+pub fn role_permissions(role: &str) -> &'static [Permission] {
+    use Permission::*;
+    match role {
+        "admin" => Permission::ALL,
+        "user" => &[
+            SourcesRead,
+            SourcesWrite,
+            SearchRead,
+            ProcessingRead,
+            ProcessingWrite,
+            AgentsInvoke,
+            ResourcesWrite,
+            AccountData,
+            WebhooksManage,
+        ],
+        "viewer" => &[SourcesRead, SearchRead, ProcessingRead],
+        "auditor" => &[ComplianceRead],
+        _ => &[],
+    }
+}
+
+/// Permission granted by an API key scope; role names grant nothing
+pub fn scope_permission(scope: &str) -> Option<Permission> {
+    Permission::parse(scope).filter(|p| *p != Permission::Admin)
+}
+
+/// Whether any of the user's roles or API key scopes grants the permission
+pub fn has_permission(user: &User, permission: Permission) -> bool {
+    let by_role = user.roles.iter().any(|entry| {
+        role_permissions(entry).contains(&permission) || Permission::parse(entry) == Some(permission)
+    });
+    by_role || user.scopes.iter().any(|scope| scope_permission(scope) == Some(permission))
+}
+
+/// Reject callers without the permission given as state
+///
+/// Mount inside `auth_middleware` so the user extension is present.
+pub async fn require_permission(
+    State(permission): State<Permission>,
+    request: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let allowed = request
+        .extensions()
+        .get::<AuthenticatedUser>()
+        .map(|u| has_permission(&u.0, permission))
+        .unwrap_or(false);
+
+    if !allowed {
+        return Err(AppError::Forbidden(format!("Missing permission: {}", permission)));
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn caller(roles: &[&str], scopes: &[&str]) -> User {
+        User {
+            id: "u1".to_string(),
+            email: "u1@example.com".to_string(),
+            name: None,
+            picture: None,
+            roles: roles.iter().map(|r| r.to_string()).collect(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+            workspace_id: None,
+        }
+    }
+
+    #[test]
+    fn roles_expand_to_their_permissions() {
+        assert!(has_permission(&caller(&["user"], &[]), Permission::ResourcesWrite));
+        assert!(!has_permission(&caller(&["viewer"], &[]), Permission::ResourcesWrite));
+        assert!(has_permission(&caller(&["admin"], &[]), Permission::Admin));
+    }
+
+    #[test]
+    fn scopes_grant_only_the_permission_they_name() {
+        let key = caller(&[], &["search:read", "user", "admin"]);
+        assert!(has_permission(&key, Permission::SearchRead));
+        assert!(!has_permission(&key, Permission::SourcesRead));
+        assert!(!has_permission(&key, Permission::Admin));
+        assert_eq!(scope_permission("resources:write"), Some(Permission::ResourcesWrite));
+        assert_eq!(scope_permission("admin"), None);
+    }
+}
@@ -1,61 +1,190 @@
 //! Rate limiting middleware
 //!
-//! In-memory sliding window rate limiting using DashMap
+//! Token bucket rate limiting (implemented as GCRA) with a pluggable state
+//! store. Which bucket applies to a request is decided by the policy engine
+//! in `rate_limit_policy`. The in-memory store keeps per-replica state in a
+//! DashMap; the Postgres store lets several gateway replicas share buckets.
 
 use axum::{
-    extract::{Request, State},
-    http::{StatusCode, HeaderValue},
+    extract::{OriginalUri, Request, State},
+    http::{StatusCode, HeaderValue, header},
     middleware::Next,
     response::{IntoResponse, Response},
 };
 use dashmap::DashMap;
+use sqlx::PgPool;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::task::JoinHandle;
+
+use crate::error::AppError;
+use crate::metrics::METRICS;
+use super::auth::{AuthenticatedApiKey, AuthenticatedUser};
+use super::rate_limit_policy::{Principal, PolicyRegistry, RateLimitPolicy};
+
+/// How often full (idle) buckets are swept from the store
+const SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Outcome of trying to take a token from a bucket
+#[derive(Debug, Clone, Copy)]
+pub struct Acquire {
+    pub allowed: bool,
+    /// Theoretical arrival time (epoch micros) after this request
+    pub tat: u64,
+}
+
+/// Backend storage for token bucket state
+///
+/// Each bucket is a single theoretical arrival time (TAT). A request at `now`
+/// is allowed when `max(tat, now) - now <= tolerance`, after which the TAT
+/// advances by `interval`. The bucket is full again once `now >= tat`.
+#[async_trait::async_trait]
+pub trait RateLimitStore: Send + Sync {
+    /// Try to take a token from the bucket for `key`
+    async fn acquire(&self, key: &str, now: u64, interval: u64, tolerance: u64) -> Result<Acquire, AppError>;
+
+    /// Remove buckets that are full at `now`. Returns the number removed.
+    async fn sweep(&self, now: u64) -> Result<u64, AppError>;
+}
+
+/// Per-replica store backed by a DashMap of arrival times
+#[derive(Clone, Default)]
+pub struct InMemoryRateLimitStore {
+    buckets: Arc<DashMap<String, u64>>,
+}
+
+impl InMemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of buckets currently tracked
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for InMemoryRateLimitStore {
+    async fn acquire(&self, key: &str, now: u64, interval: u64, tolerance: u64) -> Result<Acquire, AppError> {
+        let mut tat = self.buckets.entry(key.to_string()).or_insert(now);
+        let start = (*tat).max(now);
+
+        if start - now > tolerance {
+            return Ok(Acquire { allowed: false, tat: *tat });
+        }
+
+        *tat = start + interval;
+        Ok(Acquire { allowed: true, tat: *tat })
+    }
+
+    async fn sweep(&self, now: u64) -> Result<u64, AppError> {
+        let before = self.buckets.len();
+        self.buckets.retain(|_, tat| *tat > now);
+        Ok(before.saturating_sub(self.buckets.len()) as u64)
+    }
+}
+
+/// Shared store backed by a Postgres table of arrival times
+#[derive(Clone)]
+pub struct PostgresRateLimitStore {
+    pool: PgPool,
+}
+
+impl PostgresRateLimitStore {
+    /// The `rate_limit_buckets` table is created by the schema migrations
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimitStore for PostgresRateLimitStore {
+    async fn acquire(&self, key: &str, now: u64, interval: u64, tolerance: u64) -> Result<Acquire, AppError> {
+        // Conditional upsert: no row comes back when the bucket is empty
+        let updated: Option<(i64,)> = sqlx::query_as(
+            "INSERT INTO rate_limit_buckets AS b (key, tat) VALUES ($1, $2 + $3)
+            ON CONFLICT (key) DO UPDATE
+                SET tat = GREATEST(b.tat, $2) + $3
+                WHERE GREATEST(b.tat, $2) - $2 <= $4
+            RETURNING tat",
+        )
+        .bind(key)
+        .bind(now as i64)
+        .bind(interval as i64)
+        .bind(tolerance as i64)
+        .fetch_optional(&self.pool)
+        .await?;
+
+        if let Some((tat,)) = updated {
+            return Ok(Acquire { allowed: true, tat: tat as u64 });
+        }
+
+        let (tat,): (i64,) = sqlx::query_as("SELECT tat FROM rate_limit_buckets WHERE key = $1")
+            .bind(key)
+            .fetch_one(&self.pool)
+            .await?;
+
+        Ok(Acquire { allowed: false, tat: tat as u64 })
+    }
+
+    async fn sweep(&self, now: u64) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM rate_limit_buckets WHERE tat <= $1")
+            .bind(now as i64)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected())
+    }
+}
 
 /// Rate limit configuration
 #[derive(Clone)]
 pub struct RateLimitConfig {
-    pub counters: Arc<DashMap<String, Vec<u64>>>,
-    pub default_limit: u32,
-    pub search_limit: u32,
-    pub sources_limit: u32,
-    pub sync_limit: u32,
-    pub window_secs: u64,
+    pub store: Arc<dyn RateLimitStore>,
+    pub policies: PolicyRegistry,
     pub skip_rate_limiting: bool,
 }
 
 impl RateLimitConfig {
-    pub fn new(default_limit: u32, search_limit: u32, sources_limit: u32, sync_limit: u32, window_secs: u64, skip_rate_limiting: bool) -> Self {
+    pub fn new(store: Arc<dyn RateLimitStore>, policies: PolicyRegistry, skip_rate_limiting: bool) -> Self {
         Self {
-            counters: Arc::new(DashMap::new()),
-            default_limit,
-            search_limit,
-            sources_limit,
-            sync_limit,
-            window_secs,
+            store,
+            policies,
             skip_rate_limiting,
         }
     }
 
-    /// Get limit for an endpoint path
-    pub fn get_limit_for_path(&self, path: &str) -> u32 {
-        if path.contains("/search") {
-            self.search_limit
-        } else if path.contains("/sources") {
-            self.sources_limit
-        } else if path.contains("/sync") {
-            self.sync_limit
-        } else {
-            self.default_limit
-        }
+    /// Start the task that periodically drops buckets that have refilled completely
+    pub fn spawn_sweeper(&self) -> JoinHandle<()> {
+        let store = self.store.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match store.sweep(now_micros()).await {
+                    Ok(removed) if removed > 0 => {
+                        tracing::debug!(removed = removed, "Rate limit sweep removed idle buckets");
+                    }
+                    Ok(_) => {}
+                    Err(e) => tracing::warn!("Rate limit sweep failed: {}", e),
+                }
+            }
+        })
     }
 }
 
 /// Rate limit info for response headers
 pub struct RateLimitInfo {
+    pub policy: String,
     pub limit: u32,
     pub remaining: u32,
     pub reset: u64,
+    /// Seconds until a token is available (set when the limit is exceeded)
+    pub retry_after: Option<u64>,
 }
 
 /// Rate limiting middleware
@@ -68,63 +197,90 @@ pub async fn rate_limit_middleware(
     if config.skip_rate_limiting {
         return next.run(request).await;
     }
-    
-    // Get client identifier (IP or user ID)
-    let client_id = get_client_id(&request);
-    let path = request.uri().path().to_string();
-    let limit = config.get_limit_for_path(&path);
-    
-    // Check rate limit
-    match check_rate_limit(&config, &client_id, &path, limit) {
-        Ok(info) => {
-            let mut response = next.run(request).await;
-            
-            // Add rate limit headers
-            if let Ok(val) = HeaderValue::from_str(&info.limit.to_string()) {
-                response.headers_mut().insert("X-RateLimit-Limit", val);
-            }
-            if let Ok(val) = HeaderValue::from_str(&info.remaining.to_string()) {
-                response.headers_mut().insert("X-RateLimit-Remaining", val);
-            }
-            if let Ok(val) = HeaderValue::from_str(&info.reset.to_string()) {
-                response.headers_mut().insert("X-RateLimit-Reset", val);
-            }
-            
-            response
+
+    // Nested routers strip their prefix from the URI; policies match the full path
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let method = request.method().as_str().to_string();
+    let principal = get_principal(&request);
+
+    let policies = config.policies.current();
+    let Some(policy) = policies.select(&method, &path, &principal) else {
+        return next.run(request).await;
+    };
+
+    // Check rate limit; fail open if the store is unavailable
+    let info = match check_rate_limit(config.store.as_ref(), policy, &principal, now_micros()).await {
+        Ok(info) => info,
+        Err(e) => {
+            tracing::warn!(principal = %principal.key(), "Rate limit store error, allowing request: {}", e);
+            return next.run(request).await;
         }
-        Err(_) => {
-            // Rate limit exceeded
-            let mut response = (
-                StatusCode::TOO_MANY_REQUESTS,
-                serde_json::json!({
-                    "error": {
-                        "code": "RATE_LIMITED",
-                        "message": "Too many requests"
-                    }
-                }).to_string(),
-            ).into_response();
-            
-            if let Ok(val) = HeaderValue::from_str(&limit.to_string()) {
-                response.headers_mut().insert("X-RateLimit-Limit", val);
-            }
-            if let Ok(val) = HeaderValue::from_str("0") {
-                response.headers_mut().insert("X-RateLimit-Remaining", val);
-            }
-            
-            response
+    };
+
+    if let Some(retry_after) = info.retry_after {
+        // Rate limit exceeded
+        METRICS.record_rate_limited(&info.policy);
+        let mut response = (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::CONTENT_TYPE, "application/json")],
+            serde_json::json!({
+                "error": {
+                    "code": "RATE_LIMITED",
+                    "message": "Too many requests"
+                }
+            }).to_string(),
+        ).into_response();
+
+        insert_rate_limit_headers(&mut response, &info);
+        if let Ok(val) = HeaderValue::from_str(&retry_after.to_string()) {
+            response.headers_mut().insert(header::RETRY_AFTER, val);
         }
+
+        return response;
+    }
+
+    let mut response = next.run(request).await;
+    insert_rate_limit_headers(&mut response, &info);
+    response
+}
+
+/// Add the X-RateLimit-* headers to a response
+fn insert_rate_limit_headers(response: &mut Response, info: &RateLimitInfo) {
+    if let Ok(val) = HeaderValue::from_str(&info.limit.to_string()) {
+        response.headers_mut().insert("X-RateLimit-Limit", val);
+    }
+    if let Ok(val) = HeaderValue::from_str(&info.remaining.to_string()) {
+        response.headers_mut().insert("X-RateLimit-Remaining", val);
+    }
+    if let Ok(val) = HeaderValue::from_str(&info.reset.to_string()) {
+        response.headers_mut().insert("X-RateLimit-Reset", val);
+    }
+    if let Ok(val) = HeaderValue::from_str(&info.policy) {
+        response.headers_mut().insert("X-RateLimit-Policy", val);
     }
 }
 
-/// Get client identifier for rate limiting
-fn get_client_id(request: &Request) -> String {
+/// Identify the caller for rate limiting
+fn get_principal(request: &Request) -> Principal {
+    // API key callers are classified by key (and its scopes), not by owning user
+    if let Some(key) = request.extensions().get::<AuthenticatedApiKey>() {
+        return Principal::ApiKey {
+            id: key.0.id.clone(),
+            scopes: key.0.scopes.clone(),
+        };
+    }
+
     // Try user ID from auth middleware
-    if let Some(user) = request.extensions().get::<super::auth::AuthenticatedUser>() {
-        return format!("user:{}", user.0.id);
+    if let Some(user) = request.extensions().get::<AuthenticatedUser>() {
+        return Principal::User { id: user.0.id.clone() };
     }
-    
+
     // Fall back to IP address
-    request
+    let ip = request
         .headers()
         .get("X-Forwarded-For")
         .and_then(|v| v.to_str().ok())
@@ -137,41 +293,130 @@ fn get_client_id(request: &Request) -> String {
                 .and_then(|v| v.to_str().ok())
                 .map(|s| s.to_string())
         })
-        .unwrap_or_else(|| "unknown".to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    Principal::Anonymous { ip }
 }
 
-/// Check rate limit using in-memory sliding window
-fn check_rate_limit(
-    config: &RateLimitConfig,
-    client_id: &str,
-    path: &str,
-    limit: u32,
-) -> Result<RateLimitInfo, ()> {
-    let now = SystemTime::now()
+fn now_micros() -> u64 {
+    SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .unwrap()
-        .as_secs();
-    
-    let window_start = now - config.window_secs;
-    let key = format!("ratelimit:{}:{}", client_id, path.replace('/', "_"));
-    
-    let mut entry = config.counters.entry(key).or_insert_with(Vec::new);
-    
-    // Remove old entries outside the window
-    entry.retain(|&ts| ts > window_start);
-    
-    // Add current request timestamp
-    entry.push(now);
-    
-    let count = entry.len() as u32;
-    
-    if count > limit {
-        Err(())
-    } else {
-        Ok(RateLimitInfo {
-            limit,
-            remaining: limit.saturating_sub(count),
-            reset: now + config.window_secs,
-        })
+        .as_micros() as u64
+}
+
+/// Take a token from the policy's bucket for this principal at `now` (epoch micros)
+async fn check_rate_limit(
+    store: &dyn RateLimitStore,
+    policy: &RateLimitPolicy,
+    principal: &Principal,
+    now: u64,
+) -> Result<RateLimitInfo, AppError> {
+    let interval = policy.emission_interval_us();
+    let tolerance = policy.burst_tolerance_us();
+    let key = format!("ratelimit:{}:{}", policy.name, principal.key());
+
+    let result = store.acquire(&key, now, interval, tolerance).await?;
+
+    // Tokens left = how many more intervals fit before the TAT exceeds the tolerance
+    let remaining = (now + tolerance + interval).saturating_sub(result.tat) / interval;
+    let retry_after = (!result.allowed)
+        .then(|| result.tat.saturating_sub(now + tolerance).div_ceil(1_000_000).max(1));
+
+    Ok(RateLimitInfo {
+        policy: policy.name.clone(),
+        limit: policy.burst,
+        remaining: (remaining as u32).min(policy.burst),
+        reset: result.tat.div_ceil(1_000_000),
+        retry_after,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::rate_limit_policy::PrincipalMatch;
+
+    const SECOND: u64 = 1_000_000;
+
+    /// 3 back-to-back requests, then one every 10 seconds
+    fn policy() -> RateLimitPolicy {
+        RateLimitPolicy {
+            name: "test".to_string(),
+            route: "*".to_string(),
+            methods: vec![],
+            principal: PrincipalMatch::Any,
+            scopes: vec![],
+            burst: 3,
+            per_minute: 6,
+        }
+    }
+
+    fn user() -> Principal {
+        Principal::User { id: "u1".to_string() }
+    }
+
+    #[tokio::test]
+    async fn burst_is_allowed_then_limited() {
+        let store = InMemoryRateLimitStore::new();
+        let now = 1_000 * SECOND;
+
+        for expected_remaining in [2, 1, 0] {
+            let info = check_rate_limit(&store, &policy(), &user(), now).await.unwrap();
+            assert!(info.retry_after.is_none());
+            assert_eq!(info.remaining, expected_remaining);
+            assert_eq!(info.limit, 3);
+        }
+
+        let info = check_rate_limit(&store, &policy(), &user(), now).await.unwrap();
+        assert_eq!(info.retry_after, Some(10));
+        assert_eq!(info.remaining, 0);
+    }
+
+    #[tokio::test]
+    async fn tokens_refill_at_the_emission_interval() {
+        let store = InMemoryRateLimitStore::new();
+        let now = 1_000 * SECOND;
+        for _ in 0..3 {
+            check_rate_limit(&store, &policy(), &user(), now).await.unwrap();
+        }
+
+        // One token back after an interval, not before
+        let early = check_rate_limit(&store, &policy(), &user(), now + 9 * SECOND).await.unwrap();
+        assert_eq!(early.retry_after, Some(1));
+        let refilled = check_rate_limit(&store, &policy(), &user(), now + 10 * SECOND).await.unwrap();
+        assert!(refilled.retry_after.is_none());
+        assert_eq!(refilled.remaining, 0);
+
+        // A full bucket after the whole window
+        let full = check_rate_limit(&store, &policy(), &user(), now + 60 * SECOND).await.unwrap();
+        assert_eq!(full.remaining, 2);
+    }
+
+    #[tokio::test]
+    async fn buckets_are_per_principal() {
+        let store = InMemoryRateLimitStore::new();
+        let now = 1_000 * SECOND;
+        for _ in 0..4 {
+            check_rate_limit(&store, &policy(), &user(), now).await.unwrap();
+        }
+
+        let other = Principal::Anonymous { ip: "10.0.0.1".to_string() };
+        let info = check_rate_limit(&store, &policy(), &other, now).await.unwrap();
+        assert!(info.retry_after.is_none());
+        assert_eq!(store.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn sweep_removes_only_full_buckets() {
+        let store = InMemoryRateLimitStore::new();
+        let interval = 10 * SECOND;
+        store.acquire("idle", 0, interval, 0).await.unwrap();
+        store.acquire("busy", 100 * SECOND, interval, 0).await.unwrap();
+
+        assert_eq!(store.sweep(100 * SECOND).await.unwrap(), 1);
+        assert_eq!(store.len(), 1);
+        assert_eq!(store.sweep(110 * SECOND).await.unwrap(), 1);
+        assert!(store.is_empty());
     }
 }
@@ -0,0 +1,419 @@
+//! Rate limit policy engine
+//!
+//! Policies select a token bucket (burst + sustained rate) by route pattern,
+//! HTTP method and principal class. They are loaded from a JSON file and
+//! hot-reloaded when the file changes; built-in defaults derived from
+//! `Config` are always evaluated last as a fallback.
+//!
+//! Example policy file:
+//!
+//! ```json
+//! {
+//!   "policies": [
+//!     { "name": "search-enterprise", "route": "/v1/search/*", "principal": "api_key",
+//!       "scopes": ["plan:enterprise"], "burst": 200, "per_minute": 1200 },
+//!     { "name": "sync-users", "route": "/v1/sync/:source_id", "methods": ["POST"],
+//!       "principal": "user", "burst": 3, "per_minute": 10 }
+//!   ]
+//! }
+//! ```
+
+use serde::Deserialize;
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::task::JoinHandle;
+
+use crate::config::{Config, ConfigError};
+
+/// Class of caller a policy applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PrincipalMatch {
+    #[default]
+    Any,
+    /// JWT-authenticated user
+    User,
+    /// API key caller, optionally narrowed by scopes
+    ApiKey,
+    /// Unauthenticated caller identified by IP
+    Anonymous,
+}
+
+/// The caller of a request, as seen by the rate limiter
+#[derive(Debug, Clone)]
+pub enum Principal {
+    User { id: String },
+    ApiKey { id: String, scopes: Vec<String> },
+    Anonymous { ip: String },
+}
+
+impl Principal {
+    /// Stable identifier used in bucket keys
+    pub fn key(&self) -> String {
+        match self {
+            Principal::User { id } => format!("user:{}", id),
+            Principal::ApiKey { id, .. } => format!("apikey:{}", id),
+            Principal::Anonymous { ip } => format!("ip:{}", ip),
+        }
+    }
+}
+
+/// A single rate limit policy
+#[derive(Debug, Clone, Deserialize)]
+pub struct RateLimitPolicy {
+    /// Policy name; buckets are keyed by policy and principal
+    pub name: String,
+    /// Route pattern: `:param` matches one segment, a trailing `*` matches the rest
+    pub route: String,
+    /// HTTP methods this policy applies to (empty = all)
+    #[serde(default)]
+    pub methods: Vec<String>,
+    #[serde(default)]
+    pub principal: PrincipalMatch,
+    /// For API key principals: at least one of these scopes is required (empty = any key)
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Maximum number of requests that can be made back-to-back
+    pub burst: u32,
+    /// Sustained refill rate
+    pub per_minute: u32,
+}
+
+impl RateLimitPolicy {
+    /// Whether this policy applies to the given request
+    pub fn matches(&self, method: &str, path: &str, principal: &Principal) -> bool {
+        if !self.methods.is_empty() && !self.methods.iter().any(|m| m.eq_ignore_ascii_case(method)) {
+            return false;
+        }
+
+        let principal_ok = match (self.principal, principal) {
+            (PrincipalMatch::Any, _) => true,
+            (PrincipalMatch::User, Principal::User { .. }) => true,
+            (PrincipalMatch::ApiKey, Principal::ApiKey { scopes, .. }) => {
+                self.scopes.is_empty() || self.scopes.iter().any(|s| scopes.contains(s))
+            }
+            (PrincipalMatch::Anonymous, Principal::Anonymous { .. }) => true,
+            _ => false,
+        };
+
+        principal_ok && route_matches(&self.route, path)
+    }
+
+    /// Time between token refills, in microseconds
+    pub fn emission_interval_us(&self) -> u64 {
+        60_000_000 / self.per_minute.max(1) as u64
+    }
+
+    /// How far ahead of `now` the bucket may be drawn down, in microseconds
+    pub fn burst_tolerance_us(&self) -> u64 {
+        self.emission_interval_us() * self.burst.saturating_sub(1) as u64
+    }
+
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.burst == 0 || self.per_minute == 0 {
+            return Err(ConfigError::InvalidValue(format!(
+                "rate limit policy '{}': burst and per_minute must be at least 1",
+                self.name
+            )));
+        }
+        if !self.route.starts_with('/') && self.route != "*" {
+            return Err(ConfigError::InvalidValue(format!(
+                "rate limit policy '{}': route must start with '/'",
+                self.name
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Match a path against a route pattern
+pub fn route_matches(pattern: &str, path: &str) -> bool {
+    if pattern == "*" {
+        return true;
+    }
+
+    let mut pattern_segments = pattern.trim_matches('/').split('/');
+    let mut path_segments = path.trim_matches('/').split('/');
+
+    loop {
+        match (pattern_segments.next(), path_segments.next()) {
+            (Some("*"), _) => return true,
+            (Some(p), Some(s)) if p.starts_with(':') && !s.is_empty() => continue,
+            (Some(p), Some(s)) if p == s => continue,
+            (None, None) => return true,
+            _ => return false,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PolicyFile {
+    policies: Vec<RateLimitPolicy>,
+}
+
+/// Ordered set of policies; the first match wins
+#[derive(Debug, Clone)]
+pub struct PolicySet {
+    policies: Vec<RateLimitPolicy>,
+}
+
+impl PolicySet {
+    /// Built-in policies matching the documented per-endpoint limits
+    pub fn defaults(config: &Config) -> Self {
+        let window = config.rate_limit_window_secs.max(1);
+        let policy = |name: &str, route: &str, limit: u32| RateLimitPolicy {
+            name: name.to_string(),
+            route: route.to_string(),
+            methods: vec![],
+            principal: PrincipalMatch::Any,
+            scopes: vec![],
+            burst: limit.max(1),
+            per_minute: ((limit as u64 * 60) / window).max(1) as u32,
+        };
+
+        Self {
+            policies: vec![
+                policy("search", "/v1/search/*", config.rate_limit_search),
+                policy("sources", "/v1/sources/*", config.rate_limit_sources),
+                policy("sync", "/v1/sync/*", config.rate_limit_sync),
+                policy("default", "*", config.rate_limit_default),
+            ],
+        }
+    }
+
+    /// Parse a policy file and place its policies ahead of `fallback`
+    pub fn from_json(contents: &str, fallback: &PolicySet) -> Result<Self, ConfigError> {
+        let file: PolicyFile = serde_json::from_str(contents)
+            .map_err(|e| ConfigError::InvalidValue(format!("rate limit policy file: {}", e)))?;
+
+        for policy in &file.policies {
+            policy.validate()?;
+        }
+
+        let mut policies = file.policies;
+        policies.extend(fallback.policies.iter().cloned());
+        Ok(Self { policies })
+    }
+
+    /// Find the first policy that applies to a request
+    pub fn select(&self, method: &str, path: &str, principal: &Principal) -> Option<&RateLimitPolicy> {
+        self.policies.iter().find(|p| p.matches(method, path, principal))
+    }
+
+    pub fn len(&self) -> usize {
+        self.policies.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.policies.is_empty()
+    }
+}
+
+/// Hot-reloadable handle to the active policy set
+#[derive(Clone)]
+pub struct PolicyRegistry {
+    current: Arc<RwLock<Arc<PolicySet>>>,
+}
+
+impl PolicyRegistry {
+    pub fn new(policies: PolicySet) -> Self {
+        Self {
+            current: Arc::new(RwLock::new(Arc::new(policies))),
+        }
+    }
+
+    /// Snapshot of the active policies
+    pub fn current(&self) -> Arc<PolicySet> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replace the active policies
+    pub fn replace(&self, policies: PolicySet) {
+        *self.current.write().unwrap() = Arc::new(policies);
+    }
+
+    /// Load policies from the configured file (if any) and, when a file is set,
+    /// poll it for changes and swap in the new policies on modification.
+    /// An invalid file at startup is an error; an invalid file on reload is
+    /// logged and the previous policies are kept.
+    pub fn load(config: &Config) -> Result<Self, ConfigError> {
+        let defaults = PolicySet::defaults(config);
+
+        let Some(path) = config.rate_limit_policy_file.clone().map(PathBuf::from) else {
+            return Ok(Self::new(defaults));
+        };
+
+        let contents = std::fs::read_to_string(&path).map_err(|e| {
+            ConfigError::InvalidValue(format!("rate limit policy file {}: {}", path.display(), e))
+        })?;
+        let registry = Self::new(PolicySet::from_json(&contents, &defaults)?);
+        tracing::info!(
+            path = %path.display(),
+            policies = registry.current().len(),
+            "Rate limit policies loaded"
+        );
+
+        let reload_every = Duration::from_secs(config.rate_limit_policy_reload_secs.max(1));
+        registry.spawn_reloader(path, defaults, reload_every);
+
+        Ok(registry)
+    }
+
+    /// Poll `path` every `every` and swap in its policies when it changes
+    fn spawn_reloader(&self, path: PathBuf, defaults: PolicySet, every: Duration) -> JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut last_modified = modified_at(&path);
+            let mut interval = tokio::time::interval(every);
+            loop {
+                interval.tick().await;
+                let modified = modified_at(&path);
+                if modified == last_modified {
+                    continue;
+                }
+                last_modified = modified;
+
+                match tokio::fs::read_to_string(&path).await {
+                    Ok(contents) => match PolicySet::from_json(&contents, &defaults) {
+                        Ok(policies) => {
+                            tracing::info!(
+                                path = %path.display(),
+                                policies = policies.len(),
+                                "Rate limit policies reloaded"
+                            );
+                            registry.replace(policies);
+                        }
+                        Err(e) => tracing::error!("Keeping previous rate limit policies: {}", e),
+                    },
+                    Err(e) => tracing::error!(
+                        path = %path.display(),
+                        "Failed to read rate limit policy file: {}",
+                        e
+                    ),
+                }
+            }
+        })
+    }
+}
+
+fn modified_at(path: &PathBuf) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fallback() -> PolicySet {
+        PolicySet {
+            policies: vec![RateLimitPolicy {
+                name: "default".to_string(),
+                route: "*".to_string(),
+                methods: vec![],
+                principal: PrincipalMatch::Any,
+                scopes: vec![],
+                burst: 120,
+                per_minute: 120,
+            }],
+        }
+    }
+
+    fn api_key(scopes: &[&str]) -> Principal {
+        Principal::ApiKey {
+            id: "key1".to_string(),
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+
+    fn user() -> Principal {
+        Principal::User { id: "u1".to_string() }
+    }
+
+    const POLICIES: &str = r#"{
+        "policies": [
+            { "name": "search-enterprise", "route": "/v1/search/*", "principal": "api_key",
+              "scopes": ["plan:enterprise"], "burst": 200, "per_minute": 1200 },
+            { "name": "sync-users", "route": "/v1/sync/:source_id", "methods": ["POST"],
+              "principal": "user", "burst": 3, "per_minute": 10 }
+        ]
+    }"#;
+
+    #[test]
+    fn route_patterns() {
+        assert!(route_matches("*", "/anything"));
+        assert!(route_matches("/v1/search/*", "/v1/search/semantic"));
+        assert!(route_matches("/v1/search/*", "/v1/search"));
+        assert!(route_matches("/v1/sync/:source_id", "/v1/sync/abc"));
+        assert!(!route_matches("/v1/sync/:source_id", "/v1/sync/abc/status"));
+        assert!(!route_matches("/v1/sync/:source_id", "/v1/sync/"));
+        assert!(!route_matches("/v1/sources", "/v1/sources/abc"));
+    }
+
+    #[test]
+    fn first_matching_policy_wins() {
+        let set = PolicySet::from_json(POLICIES, &fallback()).unwrap();
+        assert_eq!(set.len(), 3);
+
+        let pick = |method, path, principal: &Principal| set.select(method, path, principal).unwrap().name.clone();
+        assert_eq!(pick("GET", "/v1/search/semantic", &api_key(&["plan:enterprise"])), "search-enterprise");
+        assert_eq!(pick("GET", "/v1/search/semantic", &api_key(&["plan:free"])), "default");
+        assert_eq!(pick("GET", "/v1/search/semantic", &user()), "default");
+        assert_eq!(pick("post", "/v1/sync/abc", &user()), "sync-users");
+        assert_eq!(pick("GET", "/v1/sync/abc", &user()), "default");
+        assert_eq!(pick("POST", "/v1/sync/abc", &api_key(&[])), "default");
+    }
+
+    #[test]
+    fn invalid_policies_are_rejected() {
+        let zero_burst = r#"{ "policies": [ { "name": "p", "route": "/x", "burst": 0, "per_minute": 1 } ] }"#;
+        assert!(PolicySet::from_json(zero_burst, &fallback()).is_err());
+        let relative = r#"{ "policies": [ { "name": "p", "route": "x", "burst": 1, "per_minute": 1 } ] }"#;
+        assert!(PolicySet::from_json(relative, &fallback()).is_err());
+        assert!(PolicySet::from_json("not json", &fallback()).is_err());
+    }
+
+    #[test]
+    fn bucket_timing() {
+        let set = PolicySet::from_json(POLICIES, &fallback()).unwrap();
+        let sync = set.select("POST", "/v1/sync/abc", &user()).unwrap();
+        assert_eq!(sync.emission_interval_us(), 6_000_000);
+        assert_eq!(sync.burst_tolerance_us(), 12_000_000);
+    }
+
+    /// Wait until the active policies have `len` entries
+    async fn wait_for_len(registry: &PolicyRegistry, len: usize) -> bool {
+        for _ in 0..100 {
+            if registry.current().len() == len {
+                return true;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn changed_file_is_reloaded_and_invalid_file_is_ignored() {
+        let path = std::env::temp_dir().join(format!("rate-limit-policies-{}.json", uuid::Uuid::new_v4()));
+        std::fs::write(&path, POLICIES).unwrap();
+        let registry = PolicyRegistry::new(PolicySet::from_json(POLICIES, &fallback()).unwrap());
+        let reloader = registry.spawn_reloader(path.clone(), fallback(), Duration::from_millis(10));
+
+        // Modification times can be coarse; make sure the rewrite moves them
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::write(&path, r#"{ "policies": [] }"#).unwrap();
+        assert!(wait_for_len(&registry, 1).await, "policies were not reloaded");
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::write(&path, "{ not json").unwrap();
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert_eq!(registry.current().len(), 1);
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        std::fs::write(&path, POLICIES).unwrap();
+        assert!(wait_for_len(&registry, 3).await, "policies were not reloaded after a bad file");
+
+        reloader.abort();
+        let _ = std::fs::remove_file(&path);
+    }
+}
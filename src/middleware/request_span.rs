@@ -0,0 +1,47 @@
+//! Per-request tracing spans
+//!
+//! Opens a server span named after the matched route template, continues
+//! any W3C trace context sent by the caller and records the correlation ID
+//! set by `zero_trust_middleware`. Mount with `Router::layer` on the fully
+//! built router so the route template is available.
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+use crate::telemetry;
+
+/// Request span middleware
+pub async fn request_span_middleware(request: Request, next: Next) -> Response {
+    let method = request.method().as_str().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let correlation_id = request
+        .headers()
+        .get("X-Correlation-Id")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_string();
+
+    let span = tracing::info_span!(
+        "http_request",
+        otel.name = %format!("{} {}", method, route),
+        otel.kind = "server",
+        http.request.method = %method,
+        http.route = %route,
+        http.response.status_code = tracing::field::Empty,
+        correlation_id = %correlation_id,
+    );
+    span.set_parent(telemetry::extract_context(request.headers()));
+
+    let response = next.run(request).instrument(span.clone()).await;
+    span.record("http.response.status_code", response.status().as_u16());
+    response
+}
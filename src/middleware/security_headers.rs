@@ -31,10 +31,13 @@ pub async fn security_headers_middleware(
         "Permissions-Policy",
         HeaderValue::from_static("camera=(), microphone=(), geolocation=(), payment=()"),
     );
-    headers.insert(
-        "Content-Security-Policy",
-        HeaderValue::from_static("default-src 'none'; frame-ancestors 'none'"),
-    );
+    // Pages such as /docs set a narrower policy of their own
+    if !headers.contains_key("Content-Security-Policy") {
+        headers.insert(
+            "Content-Security-Policy",
+            HeaderValue::from_static("default-src 'none'; frame-ancestors 'none'"),
+        );
+    }
     headers.insert(
         "Cache-Control",
         HeaderValue::from_static("no-store, no-cache, must-revalidate"),
@@ -2,20 +2,51 @@
 //!
 //! Every request is validated for:
 //! - Token freshness (not just valid but recently issued)
-//! - Request integrity (correlation IDs, timestamps)
-//! - Service identity (mutual service authentication)
+//! - Request integrity (correlation IDs, timestamps, single-use nonces)
+//! - Service identity (signed service-to-service requests, see `service_identity`)
 //! - Workspace-scoped access control
+//!
+//! Checks are driven by `ZeroTrustLayer`, built from `ZERO_TRUST_*`
+//! settings. In audit-only mode violations are logged and counted but the
+//! request is let through, so policies can be tried out before enforcing.
 
 use axum::{
-    extract::Request,
-    http::{header, HeaderValue, StatusCode},
+    body::{to_bytes, Body},
+    extract::{Request, State},
+    http::{HeaderValue, StatusCode},
     middleware::Next,
     response::{IntoResponse, Response},
     Json,
 };
+use dashmap::DashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Zero Trust configuration
+use crate::config::Config;
+use crate::error::AppError;
+use crate::metrics::METRICS;
+use crate::request_context::RequestContext;
+use super::service_identity::{
+    signing_string, ServiceIdentity, ServiceKeys, HEADER_SERVICE_NAME, HEADER_SERVICE_SIGNATURE,
+};
+
+/// Longest accepted `X-Request-Nonce`
+const MAX_NONCE_LEN: usize = 128;
+
+/// Seconds between sweeps of expired nonces
+const NONCE_SWEEP_INTERVAL_SECS: u64 = 10;
+
+/// Most nonces remembered at once per signing service
+const MAX_NONCES_PER_SERVICE: usize = 100_000;
+
+/// Most nonces remembered at once per authenticated user
+const MAX_NONCES_PER_USER: usize = 10_000;
+
+/// Largest body buffered to verify a service signature
+const MAX_SIGNED_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Zero Trust configuration and replay-protection state
 #[derive(Clone)]
 pub struct ZeroTrustLayer {
     /// Maximum age of a request timestamp before rejection (seconds)
@@ -26,6 +57,14 @@ pub struct ZeroTrustLayer {
     pub require_correlation_id: bool,
     /// Whether service identity validation is enabled
     pub enforce_service_identity: bool,
+    /// Log violations instead of rejecting the request
+    pub audit_only: bool,
+    /// Keys services sign their requests with
+    pub service_keys: ServiceKeys,
+    /// Nonces of verified service requests, per service
+    service_nonces: Arc<PartitionedNonces>,
+    /// Nonces of other requests, per user; recorded by `auth_middleware`
+    user_nonces: Arc<PartitionedNonces>,
 }
 
 impl Default for ZeroTrustLayer {
@@ -35,39 +74,351 @@ impl Default for ZeroTrustLayer {
             enforce_timestamps: true,
             require_correlation_id: false,
             enforce_service_identity: true,
+            audit_only: false,
+            service_keys: ServiceKeys::default(),
+            service_nonces: Arc::new(PartitionedNonces::new(MAX_NONCES_PER_SERVICE)),
+            user_nonces: Arc::new(PartitionedNonces::new(MAX_NONCES_PER_USER)),
+        }
+    }
+}
+
+impl ZeroTrustLayer {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_request_age_secs: config.zero_trust_max_request_age_secs,
+            enforce_timestamps: config.zero_trust_enforce_timestamps,
+            require_correlation_id: config.zero_trust_require_correlation_id,
+            enforce_service_identity: config.zero_trust_enforce_service_identity,
+            audit_only: config.zero_trust_audit_only,
+            service_keys: ServiceKeys::new(&config.service_identity_keys),
+            ..Self::default()
+        }
+    }
+
+    /// Handle a failed check: the rejection to send, or `None` in audit-only mode
+    fn violation(&self, violation: Violation, correlation_id: &str, path: &str) -> Option<Response> {
+        let action = if self.audit_only { "audited" } else { "rejected" };
+        tracing::warn!(
+            check = violation.check,
+            code = violation.code,
+            action,
+            path = %path,
+            correlation_id = %correlation_id,
+            "Zero Trust: {}",
+            violation.message
+        );
+        METRICS.record_zero_trust_violation(violation.check, action);
+
+        if self.audit_only {
+            return None;
         }
+
+        let mut response = (
+            violation.status,
+            Json(serde_json::json!({
+                "error": {
+                    "code": violation.code,
+                    "message": violation.message
+                }
+            })),
+        )
+            .into_response();
+        if let Ok(val) = HeaderValue::from_str(correlation_id) {
+            response.headers_mut().insert("X-Correlation-Id", val);
+        }
+        Some(response)
     }
 }
 
+/// A failed Zero Trust check
+struct Violation {
+    /// Check that failed, used as the metric label
+    check: &'static str,
+    code: &'static str,
+    message: String,
+    status: StatusCode,
+}
+
+impl Violation {
+    fn new(check: &'static str, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            code,
+            message: message.into(),
+            status: StatusCode::BAD_REQUEST,
+        }
+    }
+}
+
+/// Outcome of recording a nonce
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NonceCheck {
+    Fresh,
+    Replayed,
+    /// The cache is full of unexpired nonces; the nonce was not recorded
+    Full,
+}
+
+/// Nonces seen within the timestamp window, at most `capacity` at a time
+struct NonceCache {
+    /// Nonce -> time (epoch secs) after which it may be forgotten
+    seen: DashMap<String, u64>,
+    capacity: usize,
+    last_sweep: AtomicU64,
+}
+
+impl NonceCache {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            seen: DashMap::new(),
+            capacity,
+            last_sweep: AtomicU64::new(0),
+        }
+    }
+
+    fn sweep(&self, now: u64) {
+        self.last_sweep.store(now, Ordering::Relaxed);
+        self.seen.retain(|_, expiry| *expiry >= now);
+    }
+
+    /// Record a nonce unless it was already used and has not expired
+    ///
+    /// Evicting unexpired nonces would let them be replayed, so a full
+    /// cache refuses new nonces instead.
+    fn insert(&self, nonce: &str, now: u64, expires_at: u64) -> NonceCheck {
+        if now.saturating_sub(self.last_sweep.load(Ordering::Relaxed)) >= NONCE_SWEEP_INTERVAL_SECS {
+            self.sweep(now);
+        }
+        if self.seen.len() >= self.capacity && !self.seen.contains_key(nonce) {
+            self.sweep(now);
+            if self.seen.len() >= self.capacity {
+                return NonceCheck::Full;
+            }
+        }
+
+        match self.seen.entry(nonce.to_string()) {
+            dashmap::mapref::entry::Entry::Occupied(mut entry) => {
+                if *entry.get() >= now {
+                    return NonceCheck::Replayed;
+                }
+                entry.insert(expires_at);
+                NonceCheck::Fresh
+            }
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                entry.insert(expires_at);
+                NonceCheck::Fresh
+            }
+        }
+    }
+}
+
+/// Nonce caches kept apart per caller, so one caller filling its cache
+/// cannot stop another's requests
+struct PartitionedNonces {
+    partitions: DashMap<String, NonceCache>,
+    capacity: usize,
+    last_sweep: AtomicU64,
+}
+
+impl PartitionedNonces {
+    fn new(capacity: usize) -> Self {
+        Self {
+            partitions: DashMap::new(),
+            capacity,
+            last_sweep: AtomicU64::new(0),
+        }
+    }
+
+    /// Record a nonce in `partition`'s cache
+    fn insert(&self, partition: &str, nonce: &str, now: u64, expires_at: u64) -> NonceCheck {
+        if now.saturating_sub(self.last_sweep.load(Ordering::Relaxed)) >= NONCE_SWEEP_INTERVAL_SECS {
+            self.last_sweep.store(now, Ordering::Relaxed);
+            self.partitions.retain(|_, cache| {
+                cache.sweep(now);
+                !cache.seen.is_empty()
+            });
+        }
+        // The entry guard is held while recording, so a sweep cannot drop
+        // the partition in between
+        self.partitions
+            .entry(partition.to_string())
+            .or_insert_with(|| NonceCache::with_capacity(self.capacity))
+            .insert(nonce, now, expires_at)
+    }
+}
+
+/// Nonce of a request not yet tied to a caller
+///
+/// Added by `zero_trust_middleware` and recorded with `record_nonce` once
+/// `auth_middleware` knows the user, so unauthenticated requests cannot
+/// fill the nonce caches.
+#[derive(Clone)]
+pub struct PendingNonce {
+    layer: ZeroTrustLayer,
+    nonce: String,
+    expires_at: u64,
+    correlation_id: String,
+}
+
+/// Record the request's pending nonce for `user_id`
+///
+/// Returns the rejection to send when the nonce was replayed or the user's
+/// nonce cache is full (and not in audit-only mode).
+pub fn record_nonce(request: &mut Request, user_id: &str) -> Option<Response> {
+    let pending = request.extensions_mut().remove::<PendingNonce>()?;
+    let check = pending
+        .layer
+        .user_nonces
+        .insert(user_id, &pending.nonce, now_epoch(), pending.expires_at);
+    let violation = nonce_violation(check)?;
+    pending
+        .layer
+        .violation(violation, &pending.correlation_id, request.uri().path())
+}
+
+/// Whether a nonce is 1 to `MAX_NONCE_LEN` visible ASCII characters
+fn valid_nonce(nonce: &str) -> bool {
+    !nonce.is_empty() && nonce.len() <= MAX_NONCE_LEN && nonce.bytes().all(|b| b.is_ascii_graphic())
+}
+
+/// Rejection for a nonce that was replayed or could not be recorded
+fn nonce_violation(check: NonceCheck) -> Option<Violation> {
+    match check {
+        NonceCheck::Fresh => None,
+        NonceCheck::Replayed => Some(Violation {
+            status: StatusCode::CONFLICT,
+            ..Violation::new("nonce", "REPLAYED_REQUEST", "Request nonce has already been used")
+        }),
+        NonceCheck::Full => Some(Violation {
+            status: StatusCode::SERVICE_UNAVAILABLE,
+            ..Violation::new("nonce", "NONCE_CAPACITY", "Too many recent nonces; retry later")
+        }),
+    }
+}
+
+/// Check a service request's signature and nonce, buffering the body to hash it
+///
+/// Returns the request rebuilt around the buffered body.
+async fn verify_service(
+    layer: &ZeroTrustLayer,
+    request: Request,
+    now: u64,
+) -> (Result<ServiceIdentity, Violation>, Request) {
+    let invalid = |message: String| Violation {
+        status: StatusCode::UNAUTHORIZED,
+        ..Violation::new("service_identity", "INVALID_SERVICE_IDENTITY", message)
+    };
+
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, MAX_SIGNED_BODY_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            let violation = invalid(format!("Failed to read request body: {}", e));
+            return (Err(violation), Request::from_parts(parts, Body::empty()));
+        }
+    };
+
+    let header = |name: &str| parts.headers.get(name).and_then(|v| v.to_str().ok());
+    let result = (|| {
+        let service = header(HEADER_SERVICE_NAME).unwrap_or("");
+        let signature = header(HEADER_SERVICE_SIGNATURE)
+            .ok_or_else(|| invalid("X-Service-Signature header is required".to_string()))?;
+        let timestamp = header("X-Request-Timestamp")
+            .ok_or_else(|| invalid("Signed requests need X-Request-Timestamp".to_string()))?;
+        let nonce = header("X-Request-Nonce")
+            .filter(|nonce| valid_nonce(nonce))
+            .ok_or_else(|| invalid("Signed requests need X-Request-Nonce".to_string()))?;
+
+        // Signatures are only valid within the timestamp window, even when
+        // timestamps are not otherwise enforced
+        let signed_at = timestamp
+            .parse::<u64>()
+            .ok()
+            .filter(|ts| now.abs_diff(*ts) <= layer.max_request_age_secs)
+            .ok_or_else(|| invalid("Service signature has expired".to_string()))?;
+
+        let path_and_query = parts
+            .uri
+            .path_and_query()
+            .map(|pq| pq.as_str())
+            .unwrap_or("/");
+        let signed = signing_string(parts.method.as_str(), path_and_query, timestamp, nonce, &bytes);
+        let identity = layer
+            .service_keys
+            .verify(service, signature, &signed)
+            .map_err(|e| match e {
+                AppError::Unauthorized(message) => invalid(message),
+                other => invalid(other.to_string()),
+            })?;
+
+        // Only verified signatures use up a nonce, so forgeries cannot burn
+        // a service's nonces or fill the cache
+        let expires_at = signed_at.max(now) + layer.max_request_age_secs;
+        match nonce_violation(layer.service_nonces.insert(&identity.name, nonce, now, expires_at)) {
+            Some(violation) => Err(violation),
+            None => Ok(identity),
+        }
+    })();
+
+    (result, Request::from_parts(parts, Body::from(bytes)))
+}
+
+fn now_epoch() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
 /// Zero Trust middleware: validates every request against strict security policies
 pub async fn zero_trust_middleware(
+    State(layer): State<ZeroTrustLayer>,
     mut request: Request,
     next: Next,
 ) -> Response {
     let path = request.uri().path().to_string();
 
-    // Skip health/readiness endpoints
-    if path.starts_with("/health") || path == "/status" || path == "/metrics" {
+    // Skip health/readiness and API description endpoints
+    if path.starts_with("/health")
+        || path == "/status"
+        || path == "/metrics"
+        || path == "/openapi.json"
+        || path == "/docs"
+    {
         return next.run(request).await;
     }
 
-    // 1. Ensure correlation ID exists (generate if missing)
-    let correlation_id = request
+    let now = now_epoch();
+
+    // 1. Ensure correlation ID exists (generate if missing, unless required)
+    let provided_correlation_id = request
         .headers()
         .get("X-Correlation-Id")
         .or_else(|| request.headers().get("X-Request-Id"))
         .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string())
-        .unwrap_or_else(|| {
-            format!(
-                "zt-{}-{}",
-                SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_millis(),
-                uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("0000")
-            )
-        });
+        .map(|s| s.to_string());
+    let missing_correlation_id = provided_correlation_id.is_none();
+    let correlation_id = provided_correlation_id.unwrap_or_else(|| {
+        format!(
+            "zt-{}-{}",
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap()
+                .as_millis(),
+            uuid::Uuid::new_v4().to_string().split('-').next().unwrap_or("0000")
+        )
+    });
+
+    if missing_correlation_id && layer.require_correlation_id {
+        let violation = Violation::new(
+            "correlation_id",
+            "MISSING_CORRELATION_ID",
+            "X-Correlation-Id header is required",
+        );
+        if let Some(rejection) = layer.violation(violation, &correlation_id, &path) {
+            return rejection;
+        }
+    }
 
     // Inject correlation ID into request for downstream propagation
     request.headers_mut().insert(
@@ -76,73 +427,107 @@ pub async fn zero_trust_middleware(
     );
 
     // 2. Validate request timestamp (prevents replay attacks)
-    if let Some(ts_header) = request.headers().get("X-Request-Timestamp") {
-        if let Ok(ts_str) = ts_header.to_str() {
-            if let Ok(ts) = ts_str.parse::<u64>() {
-                let now = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .unwrap()
-                    .as_secs();
-                let drift = if now > ts { now - ts } else { ts - now };
-                if drift > 300 {
-                    tracing::warn!(
-                        correlation_id = %correlation_id,
-                        drift_secs = drift,
-                        "Zero Trust: request timestamp too far from server time"
+    let mut timestamp = None;
+    let mut timestamp_ok = true;
+    if layer.enforce_timestamps {
+        if let Some(ts_header) = request.headers().get("X-Request-Timestamp") {
+            match ts_header.to_str().ok().and_then(|s| s.parse::<u64>().ok()) {
+                Some(ts) => {
+                    let drift = now.abs_diff(ts);
+                    if drift > layer.max_request_age_secs {
+                        timestamp_ok = false;
+                        let violation = Violation::new(
+                            "timestamp",
+                            "STALE_REQUEST",
+                            format!(
+                                "Request timestamp is outside acceptable window ({}s drift)",
+                                drift
+                            ),
+                        );
+                        if let Some(rejection) = layer.violation(violation, &correlation_id, &path) {
+                            return rejection;
+                        }
+                    } else {
+                        timestamp = Some(ts);
+                    }
+                }
+                None => {
+                    timestamp_ok = false;
+                    let violation = Violation::new(
+                        "timestamp",
+                        "INVALID_TIMESTAMP",
+                        "X-Request-Timestamp must be seconds since the Unix epoch",
                     );
-                    return (
-                        StatusCode::BAD_REQUEST,
-                        Json(serde_json::json!({
-                            "error": {
-                                "code": "STALE_REQUEST",
-                                "message": "Request timestamp is outside acceptable window"
-                            }
-                        })),
-                    )
-                        .into_response();
+                    if let Some(rejection) = layer.violation(violation, &correlation_id, &path) {
+                        return rejection;
+                    }
                 }
             }
         }
     }
 
-    // 3. Validate service-to-service identity
-    if let Some(service_name) = request.headers().get("X-Service-Name") {
-        let _name = service_name.to_str().unwrap_or("unknown");
-        // Log service-to-service calls for audit trail
-        tracing::debug!(
-            service = _name,
-            path = %path,
-            correlation_id = %correlation_id,
-            "Zero Trust: inter-service call"
-        );
+    // 3. Reject replayed nonces. A nonce is remembered for as long as a
+    //    request carrying it could still pass the timestamp check; requests
+    //    that failed that check (in audit-only mode) do not use up a slot.
+    //    Signed service requests have their nonce checked in step 4, once
+    //    the signature covering it has been verified. Other nonces are
+    //    checked here for format only, then recorded per user by
+    //    auth_middleware, so unauthenticated callers cannot fill the caches.
+    let signed = layer.enforce_service_identity && request.headers().contains_key(HEADER_SERVICE_NAME);
+    if let Some(nonce) = request.headers().get("X-Request-Nonce").filter(|_| timestamp_ok && !signed) {
+        let nonce = nonce.to_str().unwrap_or("").to_string();
+        if valid_nonce(&nonce) {
+            let pending = PendingNonce {
+                layer: layer.clone(),
+                nonce,
+                expires_at: timestamp.unwrap_or(now).max(now) + layer.max_request_age_secs,
+                correlation_id: correlation_id.clone(),
+            };
+            request.extensions_mut().insert(pending);
+        } else {
+            let violation = Violation::new(
+                "nonce",
+                "INVALID_NONCE",
+                format!("X-Request-Nonce must be 1-{} visible ASCII characters", MAX_NONCE_LEN),
+            );
+            if let Some(rejection) = layer.violation(violation, &correlation_id, &path) {
+                return rejection;
+            }
+        }
     }
 
-    // 4. Enforce workspace isolation on protected routes
-    if path.starts_with("/v1/") {
-        if let Some(user) = request
-            .extensions()
-            .get::<super::auth::AuthenticatedUser>()
-        {
-            let workspace_header = request
-                .headers()
-                .get("X-Workspace-Id")
-                .and_then(|v| v.to_str().ok())
-                .map(|s| s.to_string());
-
-            // If workspace is required but not provided, use the user's default
-            if let Some(ws_id) = &workspace_header {
+    // 4. Verify service-to-service identity: callers naming themselves in
+    //    X-Service-Name must sign the request with their service key
+    if signed {
+        let (verified, rebuilt) = verify_service(&layer, request, now).await;
+        request = rebuilt;
+        match verified {
+            Ok(identity) => {
+                // Log service-to-service calls for audit trail
                 tracing::debug!(
-                    user_id = %user.0.id,
-                    workspace_id = %ws_id,
+                    service = %identity.name,
+                    path = %path,
                     correlation_id = %correlation_id,
-                    "Zero Trust: workspace-scoped request"
+                    "Zero Trust: inter-service call"
                 );
+                request.extensions_mut().insert(identity);
+            }
+            Err(violation) => {
+                if let Some(rejection) = layer.violation(violation, &correlation_id, &path) {
+                    return rejection;
+                }
             }
         }
     }
 
-    // 5. Execute request and add security headers to response
-    let mut response = next.run(request).await;
+    // 5. Workspace isolation is enforced by auth_middleware, which checks
+    //    X-Workspace-Id against the caller's workspace memberships
+
+    // 6. Execute request (with the correlation ID available to service clients)
+    //    and add security headers to response
+    let mut response = RequestContext::with_correlation_id(correlation_id.clone())
+        .scope(next.run(request))
+        .await;
 
     // Add correlation ID to response
     if let Ok(val) = HeaderValue::from_str(&correlation_id) {
@@ -152,8 +537,169 @@ pub async fn zero_trust_middleware(
     // Add security context header
     response.headers_mut().insert(
         "X-Zero-Trust",
-        HeaderValue::from_static("enforced"),
+        HeaderValue::from_static(if layer.audit_only { "audit" } else { "enforced" }),
     );
 
     response
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nonces_are_single_use_until_they_expire() {
+        let cache = NonceCache::with_capacity(10);
+        assert_eq!(cache.insert("n1", 100, 400), NonceCheck::Fresh);
+        assert_eq!(cache.insert("n1", 200, 500), NonceCheck::Replayed);
+        assert_eq!(cache.insert("n1", 401, 700), NonceCheck::Fresh);
+    }
+
+    #[test]
+    fn a_full_cache_refuses_new_nonces_until_some_expire() {
+        let cache = NonceCache::with_capacity(2);
+        assert_eq!(cache.insert("n1", 100, 400), NonceCheck::Fresh);
+        assert_eq!(cache.insert("n2", 100, 500), NonceCheck::Fresh);
+        assert_eq!(cache.insert("n3", 101, 500), NonceCheck::Full);
+
+        // Remembered nonces are still caught at the cap
+        assert_eq!(cache.insert("n1", 101, 500), NonceCheck::Replayed);
+
+        // n1 has expired, which makes room
+        assert_eq!(cache.insert("n3", 401, 700), NonceCheck::Fresh);
+        assert_eq!(cache.seen.len(), 2);
+    }
+
+    #[test]
+    fn one_full_partition_does_not_block_another() {
+        let nonces = PartitionedNonces::new(1);
+        assert_eq!(nonces.insert("alice", "n1", 100, 400), NonceCheck::Fresh);
+        assert_eq!(nonces.insert("alice", "n2", 100, 400), NonceCheck::Full);
+        assert_eq!(nonces.insert("bob", "n2", 100, 400), NonceCheck::Fresh);
+
+        // Expired partitions are dropped by the next sweep
+        assert_eq!(nonces.insert("bob", "n3", 401, 700), NonceCheck::Fresh);
+        assert!(!nonces.partitions.contains_key("alice"));
+    }
+
+    #[test]
+    fn nonce_format() {
+        assert!(valid_nonce("3f2a-9c1e"));
+        assert!(valid_nonce(&"a".repeat(MAX_NONCE_LEN)));
+        assert!(!valid_nonce(""));
+        assert!(!valid_nonce(&"a".repeat(MAX_NONCE_LEN + 1)));
+        assert!(!valid_nonce("has space"));
+    }
+
+    /// `zero_trust_middleware` over a route that records nonces for `u1`, as
+    /// `auth_middleware` does, and a route without authentication
+    fn app(layer: &ZeroTrustLayer) -> axum::Router {
+        use axum::{routing::get, Router};
+
+        let authenticated = Router::new()
+            .route("/v1/sources", get(|| async { "ok" }))
+            .layer(axum::middleware::from_fn(|mut request: Request, next: Next| async move {
+                match record_nonce(&mut request, "u1") {
+                    Some(rejection) => rejection,
+                    None => next.run(request).await,
+                }
+            }));
+        Router::new()
+            .route("/v1/auth/refresh", get(|| async { "ok" }))
+            .merge(authenticated)
+            .layer(axum::middleware::from_fn_with_state(layer.clone(), zero_trust_middleware))
+    }
+
+    async fn send(app: &axum::Router, path: &str, timestamp: u64, nonce: &str) -> StatusCode {
+        use tower::ServiceExt;
+
+        let request = Request::builder()
+            .uri(path)
+            .header("X-Request-Timestamp", timestamp.to_string())
+            .header("X-Request-Nonce", nonce)
+            .body(Body::empty())
+            .unwrap();
+        app.clone().oneshot(request).await.unwrap().status()
+    }
+
+    fn recorded(layer: &ZeroTrustLayer, user: &str, nonce: &str) -> bool {
+        layer
+            .user_nonces
+            .partitions
+            .get(user)
+            .map(|cache| cache.seen.contains_key(nonce))
+            .unwrap_or(false)
+    }
+
+    #[tokio::test]
+    async fn nonces_of_stale_requests_are_not_recorded() {
+        let layer = ZeroTrustLayer {
+            audit_only: true,
+            ..ZeroTrustLayer::default()
+        };
+        let app = app(&layer);
+
+        send(&app, "/v1/sources", now_epoch() - 3600, "stale").await;
+        assert!(!recorded(&layer, "u1", "stale"));
+
+        send(&app, "/v1/sources", now_epoch(), "fresh").await;
+        assert!(recorded(&layer, "u1", "fresh"));
+    }
+
+    #[tokio::test]
+    async fn user_nonces_are_recorded_after_auth() {
+        let layer = ZeroTrustLayer::default();
+        let app = app(&layer);
+
+        assert_eq!(send(&app, "/v1/sources", now_epoch(), "n1").await, StatusCode::OK);
+        assert_eq!(send(&app, "/v1/sources", now_epoch(), "n1").await, StatusCode::CONFLICT);
+
+        // Unauthenticated requests use up no slot
+        assert_eq!(send(&app, "/v1/auth/refresh", now_epoch(), "n2").await, StatusCode::OK);
+        assert_eq!(layer.user_nonces.partitions.len(), 1);
+        assert!(!recorded(&layer, "u1", "n2"));
+    }
+
+    #[tokio::test]
+    async fn signed_requests_need_a_fresh_signed_nonce() {
+        use axum::{routing::post, Extension, Router};
+        use hmac::{Hmac, Mac};
+        use sha2::Sha256;
+        use std::collections::HashMap;
+        use tower::ServiceExt;
+
+        let keys = HashMap::from([("auth-middleware".to_string(), "service-key".to_string())]);
+        let layer = ZeroTrustLayer {
+            service_keys: ServiceKeys::new(&keys),
+            ..ZeroTrustLayer::default()
+        };
+        let app = Router::new()
+            .route("/v1/internal/ping", post(|Extension(identity): Extension<ServiceIdentity>| async move { identity.name }))
+            .layer(axum::middleware::from_fn_with_state(layer, zero_trust_middleware));
+
+        let send = |signed_nonce: &str, sent_nonce: Option<&str>| {
+            let timestamp = now_epoch().to_string();
+            let mut mac = Hmac::<Sha256>::new_from_slice(b"service-key").unwrap();
+            mac.update(signing_string("POST", "/v1/internal/ping", &timestamp, signed_nonce, b"{}").as_bytes());
+            let mut request = Request::builder()
+                .method("POST")
+                .uri("/v1/internal/ping")
+                .header(HEADER_SERVICE_NAME, "auth-middleware")
+                .header(HEADER_SERVICE_SIGNATURE, hex::encode(mac.finalize().into_bytes()))
+                .header("X-Request-Timestamp", timestamp);
+            if let Some(nonce) = sent_nonce {
+                request = request.header("X-Request-Nonce", nonce);
+            }
+            let app = app.clone();
+            async move { app.oneshot(request.body(Body::from("{}")).unwrap()).await.unwrap().status() }
+        };
+
+        assert_eq!(send("n1", Some("n1")).await, StatusCode::OK);
+        assert_eq!(send("n1", Some("n1")).await, StatusCode::CONFLICT);
+        assert_eq!(send("n2", None).await, StatusCode::UNAUTHORIZED);
+        // The nonce is covered by the signature
+        assert_eq!(send("n3", Some("n4")).await, StatusCode::UNAUTHORIZED);
+        // A forgery did not use up the nonce it carried
+        assert_eq!(send("n4", Some("n4")).await, StatusCode::OK);
+    }
+}
@@ -4,8 +4,12 @@ pub mod user;
 pub mod source;
 pub mod search;
 pub mod responses;
+pub mod resources;
+pub mod webhook;
 
 pub use user::*;
 pub use source::*;
 pub use search::*;
 pub use responses::*;
+pub use resources::*;
+pub use webhook::*;
@@ -0,0 +1,108 @@
+//! Records for the agents, documents, repositories and URLs managed through `/api/*`
+//!
+//! Every record belongs to the user and workspace that created it.
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+/// A connected AI agent
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AgentRecord {
+    pub id: String,
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub name: String,
+    pub agent_type: String,
+    pub endpoint: Option<String>,
+    /// Credential for the agent's endpoint; never returned by the API
+    #[serde(skip_serializing, default)]
+    pub api_key: String,
+    /// Masked form of `api_key` shown in responses
+    #[serde(skip_deserializing)]
+    pub api_key_hint: String,
+    pub permissions: Vec<String>,
+    pub status: String,
+    pub config: AgentConfig,
+    pub usage_stats: AgentUsageStats,
+    pub created_at: String,
+    pub updated_at: String,
+    pub last_used: Option<String>,
+}
+
+/// Masked form of an agent API key: its last 4 characters, or nothing for short keys
+pub fn mask_api_key(api_key: &str) -> String {
+    let chars: Vec<char> = api_key.chars().collect();
+    if chars.len() < 8 {
+        return "****".to_string();
+    }
+    let last: String = chars[chars.len() - 4..].iter().collect();
+    format!("****{}", last)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct AgentConfig {
+    pub model: Option<String>,
+    pub temperature: Option<f64>,
+    pub max_tokens: Option<u32>,
+    pub timeout: Option<u32>,
+    pub custom_instructions: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct AgentUsageStats {
+    pub total_requests: u32,
+    pub total_tokens: u32,
+    pub avg_response_time: Option<f64>,
+    pub last_error: Option<String>,
+}
+
+/// An uploaded or linked document
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct DocumentRecord {
+    pub id: String,
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub name: String,
+    pub doc_type: String,
+    pub source: String,
+    pub size: String,
+    pub tags: Vec<String>,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A connected code repository
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct RepositoryRecord {
+    pub id: String,
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub name: String,
+    pub provider: String,
+    pub url: String,
+    pub branch: String,
+    pub status: String,
+    pub last_sync: Option<String>,
+    pub files_indexed: u32,
+    pub created_at: String,
+}
+
+/// A saved web URL
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct UrlRecord {
+    pub id: String,
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub url: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub tags: Vec<String>,
+    pub status: String,
+    pub created_at: String,
+    pub updated_at: String,
+}
@@ -1,10 +1,21 @@
 //! Common response models
 
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::collections::HashMap;
 
+/// Envelope returned by the `/api/*` management routes
+#[derive(Debug, Serialize, JsonSchema)]
+#[schemars(rename = "ApiResponse_{T}")]
+pub struct ApiResponse<T> {
+    pub success: bool,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<T>,
+}
+
 /// Health check response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct HealthResponse {
     pub status: String,
     pub version: String,
@@ -16,7 +27,7 @@ pub struct HealthResponse {
 }
 
 /// Individual service health
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct ServiceHealth {
     pub status: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -24,7 +35,7 @@ pub struct ServiceHealth {
 }
 
 /// Sources list response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SourcesListResponse {
     pub sources: Vec<super::Source>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -32,7 +43,7 @@ pub struct SourcesListResponse {
 }
 
 /// MCP tool definition
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct McpTool {
     pub name: String,
     pub description: String,
@@ -41,7 +52,7 @@ pub struct McpTool {
 }
 
 /// MCP capabilities response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct McpCapabilities {
     pub tools: Vec<McpTool>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -1,9 +1,10 @@
 //! Search-related models
 
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 /// Search request
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchRequest {
     pub query: String,
     #[serde(default = "default_limit")]
@@ -17,7 +18,7 @@ pub struct SearchRequest {
 fn default_limit() -> u32 { 10 }
 
 /// Search filters
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchFilters {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sources: Option<Vec<String>>,
@@ -28,7 +29,7 @@ pub struct SearchFilters {
 }
 
 /// Search options
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchOptions {
     #[serde(default)]
     pub include_graph: bool,
@@ -41,7 +42,7 @@ pub struct SearchOptions {
 fn default_graph_hops() -> u32 { 2 }
 
 /// Search result
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResult {
     pub id: String,
     pub content: String,
@@ -52,7 +53,7 @@ pub struct SearchResult {
 }
 
 /// Search result source info
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResultSource {
     pub id: String,
     #[serde(rename = "type")]
@@ -61,7 +62,7 @@ pub struct SearchResultSource {
 }
 
 /// Search result metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResultMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub language: Option<String>,
@@ -72,7 +73,7 @@ pub struct SearchResultMetadata {
 }
 
 /// Search response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchResponse {
     pub results: Vec<SearchResult>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -81,7 +82,7 @@ pub struct SearchResponse {
 }
 
 /// Related entity in search results
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct RelatedEntity {
     pub id: String,
     #[serde(rename = "type")]
@@ -91,14 +92,14 @@ pub struct RelatedEntity {
 }
 
 /// Search statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SearchStats {
     pub total_results: u64,
     pub search_time_ms: u64,
 }
 
 /// Entity with relationships
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Entity {
     pub id: String,
     #[serde(rename = "type")]
@@ -113,7 +114,7 @@ pub struct Entity {
 }
 
 /// Entity source location
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EntitySource {
     pub path: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -123,7 +124,7 @@ pub struct EntitySource {
 }
 
 /// Entity relationships
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EntityRelationships {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub called_by: Option<Vec<String>>,
@@ -136,7 +137,7 @@ pub struct EntityRelationships {
 }
 
 /// Entity documentation reference
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct EntityDoc {
     pub chunk_id: String,
     pub content: String,
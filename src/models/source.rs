@@ -1,10 +1,11 @@
 //! Source-related models
 
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use std::collections::HashMap;
 
 /// Source types supported by the platform
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SourceType {
     Github,
@@ -22,7 +23,7 @@ pub enum SourceType {
 }
 
 /// Source status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SourceStatus {
     Pending,
@@ -33,7 +34,7 @@ pub enum SourceStatus {
 }
 
 /// Data source representation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct Source {
     pub id: String,
     #[serde(rename = "type")]
@@ -49,7 +50,7 @@ pub struct Source {
 }
 
 /// Source statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SourceStats {
     pub files: u64,
     pub chunks: u64,
@@ -57,7 +58,7 @@ pub struct SourceStats {
 }
 
 /// Request to create a new source
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SourceCreateRequest {
     #[serde(rename = "type")]
     pub source_type: SourceType,
@@ -67,7 +68,7 @@ pub struct SourceCreateRequest {
 }
 
 /// Source configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct SourceConfig {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub owner: Option<String>,
@@ -93,7 +94,7 @@ pub struct SyncJob {
 }
 
 /// Job status
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum JobStatus {
     Queued,
@@ -104,7 +105,7 @@ pub enum JobStatus {
 }
 
 /// Job status response
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct JobStatusResponse {
     pub job_id: String,
     pub status: JobStatus,
@@ -1,9 +1,10 @@
 //! User-related models
 
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 /// Authenticated user information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
 pub struct User {
     pub id: String,
     pub email: String,
@@ -12,6 +13,9 @@ pub struct User {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub picture: Option<String>,
     pub roles: Vec<String>,
+    /// Scopes of the API key the request was made with; empty for users
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub workspace_id: Option<String>,
 }
@@ -28,6 +32,17 @@ pub struct ApiKeyInfo {
     pub expires_at: Option<String>,
 }
 
+/// A user's membership in a workspace
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceMembership {
+    pub workspace_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    /// Workspace used when a request does not name one
+    #[serde(default)]
+    pub is_default: bool,
+}
+
 /// Token pair for auth refresh
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TokenPair {
@@ -0,0 +1,98 @@
+//! Webhooks received from providers and sent to subscribers
+//!
+//! Each verified delivery from a provider is recorded once in the inbox,
+//! keyed by the provider's delivery ID, and forwarded by the background
+//! worker. Outbound, users subscribe endpoints to platform events; every
+//! notification sent to a subscription is kept in its delivery log.
+
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use std::collections::HashMap;
+
+/// A sync job finished successfully
+pub const EVENT_SYNC_COMPLETED: &str = "sync.completed";
+
+/// A sync job failed or was cancelled
+pub const EVENT_SYNC_FAILED: &str = "sync.failed";
+
+/// A source was deleted
+pub const EVENT_SOURCE_DELETED: &str = "source.deleted";
+
+/// Event types a subscription can ask for
+pub const SUBSCRIPTION_EVENT_TYPES: &[&str] =
+    &[EVENT_SYNC_COMPLETED, EVENT_SYNC_FAILED, EVENT_SOURCE_DELETED];
+
+/// Waiting for its first or next attempt
+pub const DELIVERY_PENDING: &str = "pending";
+
+/// Forwarded or sent successfully
+pub const DELIVERY_DELIVERED: &str = "delivered";
+
+/// Gave up after the maximum number of attempts
+pub const DELIVERY_FAILED: &str = "failed";
+
+/// A webhook delivery received from a provider
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookDelivery {
+    pub id: String,
+    /// `github`, `gitlab` or `bitbucket`
+    pub provider: String,
+    /// The provider's ID for the delivery (e.g. `X-GitHub-Delivery`); retries reuse it
+    pub delivery_id: String,
+    /// Event type (e.g. `X-GitHub-Event`)
+    pub event: Option<String>,
+    /// Provider headers forwarded with the payload
+    pub headers: HashMap<String, String>,
+    pub payload: serde_json::Value,
+    /// Sources whose webhook secret (or the provider-wide secret, for sources
+    /// without one) signed the delivery, with the secret removed
+    #[serde(default)]
+    pub verified_sources: Vec<super::Source>,
+    /// `pending`, `delivered` or `failed`
+    pub status: String,
+    pub attempts: u32,
+    pub last_error: Option<String>,
+    pub next_attempt_at: String,
+    pub received_at: String,
+    pub delivered_at: Option<String>,
+}
+
+/// An endpoint notified of platform events
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct WebhookSubscription {
+    pub id: String,
+    pub user_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    pub url: String,
+    pub event_types: Vec<String>,
+    /// HMAC key for `X-Confuse-Signature`; only returned when the subscription is created
+    #[serde(skip_serializing, default)]
+    pub secret: String,
+    pub enabled: bool,
+    /// Failed attempts since the last successful one
+    pub consecutive_failures: u32,
+    /// Why the subscription was disabled automatically
+    pub disabled_reason: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// A notification sent, or waiting to be sent, to a subscription
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct SubscriptionDelivery {
+    pub id: String,
+    pub subscription_id: String,
+    pub event_type: String,
+    /// The signed request body
+    pub payload: serde_json::Value,
+    /// `pending`, `delivered` or `failed`
+    pub status: String,
+    pub attempts: u32,
+    /// HTTP status of the last attempt, if the endpoint answered
+    pub response_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub next_attempt_at: String,
+    pub created_at: String,
+    pub delivered_at: Option<String>,
+}
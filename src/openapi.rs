@@ -0,0 +1,711 @@
+//! OpenAPI 3.1 description of the gateway
+//!
+//! Every route mounted by `routes::v1::v1_router` has an operation here.
+//! Request, query and response schemas are generated from the types the
+//! handlers extract and return; `schemars` emits JSON Schema 2020-12, the
+//! dialect OpenAPI 3.1 uses, so they go into `components.schemas` as-is.
+//! `tests/openapi.rs` fails when a route is mounted without an operation or
+//! an operation describes a route that does not exist.
+//!
+//! Served at `/openapi.json` and rendered at `/docs` (see `routes::v1::docs`).
+
+use once_cell::sync::Lazy;
+use schemars::{
+    generate::{SchemaGenerator, SchemaSettings},
+    JsonSchema,
+};
+use serde_json::{json, Map, Value};
+
+use crate::error::ErrorResponse;
+use crate::middleware::permissions::Permission;
+use crate::middleware::BreakerSnapshot;
+use crate::models::{
+    AgentRecord, ApiResponse, DocumentRecord, Entity, HealthResponse, JobStatusResponse,
+    McpCapabilities, RepositoryRecord, SearchRequest, SearchResponse, Source,
+    SourceCreateRequest, SourcesListResponse, UrlRecord, WebhookDelivery, WebhookSubscription,
+};
+use crate::routes::v1::{
+    admin, agents, auth, compliance, dashboard, documents, entities, mcp, processing,
+    repositories, sources, sync, urls, webhook_subscriptions,
+};
+use crate::routes::webhooks::WebhookAck;
+
+/// Error body of the `/api/*` management routes
+type ApiError = ApiResponse<()>;
+
+static DOCUMENT: Lazy<Value> = Lazy::new(build);
+
+/// The OpenAPI document, generated on first use
+pub fn document() -> &'static Value {
+    &DOCUMENT
+}
+
+fn build() -> Value {
+    let mut spec = Spec::new();
+    health_operations(&mut spec);
+    sources_operations(&mut spec);
+    search_operations(&mut spec);
+    sync_operations(&mut spec);
+    mcp_operations(&mut spec);
+    processing_operations(&mut spec);
+    compliance_operations(&mut spec);
+    session_operations(&mut spec);
+    subscriptions_operations(&mut spec);
+    admin_operations(&mut spec);
+    management_operations(&mut spec);
+    webhooks_operations(&mut spec);
+    spec.finish()
+}
+
+fn health_operations(spec: &mut Spec) {
+    spec.op("get", "/health", "Health", "Basic health check")
+        .json::<HealthResponse>(200, "The gateway is up")
+        .add();
+    spec.op("get", "/health/detailed", "Health", "Health of the gateway and downstream services")
+        .json::<HealthResponse>(200, "Per-service health")
+        .add();
+    spec.op("get", "/health/ready", "Health", "Readiness probe")
+        .json::<HealthResponse>(200, "Per-service health")
+        .add();
+    spec.op("get", "/health/live", "Health", "Liveness probe")
+        .json::<Value>(200, "The process is alive")
+        .add();
+    spec.op("get", "/status", "Health", "Detailed status with downstream service health")
+        .json::<HealthResponse>(200, "Per-service health")
+        .add();
+    spec.op("get", "/metrics", "Health", "Prometheus metrics")
+        .text(200, "text/plain", "Metrics in the Prometheus text format")
+        .add();
+    spec.op("get", "/openapi.json", "Docs", "This OpenAPI document")
+        .json::<Value>(200, "OpenAPI 3.1 document")
+        .add();
+    spec.op("get", "/docs", "Docs", "Interactive API documentation")
+        .text(200, "text/html", "Swagger UI page rendering `/openapi.json`")
+        .add();
+}
+
+fn sources_operations(spec: &mut Spec) {
+    spec.op("get", "/v1/sources", "Sources", "List the caller's sources")
+        .permission(Permission::SourcesRead)
+        .query::<sources::ListSourcesQuery>()
+        .json::<SourcesListResponse>(200, "Sources")
+        .add();
+    spec.op("post", "/v1/sources", "Sources", "Connect a source")
+        .permission(Permission::SourcesWrite)
+        .body::<SourceCreateRequest>()
+        .json::<Source>(200, "The created source")
+        .add();
+    spec.op("get", "/v1/sources/:id", "Sources", "Get a source")
+        .permission(Permission::SourcesRead)
+        .json::<Source>(200, "The source")
+        .add();
+    spec.op("delete", "/v1/sources/:id", "Sources", "Delete a source")
+        .permission(Permission::SourcesWrite)
+        .json::<Value>(200, "The source was deleted; notifies `source.deleted` subscribers")
+        .add();
+}
+
+fn search_operations(spec: &mut Spec) {
+    spec.op("post", "/v1/search", "Search", "Hybrid vector and graph search")
+        .permission(Permission::SearchRead)
+        .body::<SearchRequest>()
+        .json::<SearchResponse>(200, "Ranked results")
+        .add();
+    spec.op("post", "/v1/search/vector", "Search", "Vector search")
+        .permission(Permission::SearchRead)
+        .body::<SearchRequest>()
+        .json::<SearchResponse>(200, "Ranked results")
+        .add();
+    spec.op("post", "/v1/search/graph", "Search", "Knowledge graph search")
+        .permission(Permission::SearchRead)
+        .body::<SearchRequest>()
+        .json::<SearchResponse>(200, "Ranked results")
+        .add();
+    spec.op("get", "/v1/entities/:id", "Search", "Get an entity")
+        .permission(Permission::SearchRead)
+        .json::<Entity>(200, "The entity and its direct relationships")
+        .add();
+    spec.op("get", "/v1/entities/:id/neighbors", "Search", "Get an entity with its neighbourhood")
+        .permission(Permission::SearchRead)
+        .query::<entities::GetNeighborsQuery>()
+        .json::<Entity>(200, "The entity and relationships up to `hops` away")
+        .add();
+}
+
+fn sync_operations(spec: &mut Spec) {
+    spec.op("post", "/v1/sync/:source_id", "Sync", "Request a sync of a source")
+        .permission(Permission::SourcesWrite)
+        .json::<sync::SyncRequestResponse>(200, "The sync was requested")
+        .add();
+    spec.op("get", "/v1/sync/:job_id/status", "Sync", "Get the status of a sync job")
+        .permission(Permission::SourcesRead)
+        .json::<JobStatusResponse>(200, "Job status")
+        .add();
+}
+
+fn mcp_operations(spec: &mut Spec) {
+    spec.op("post", "/v1/mcp/search", "MCP", "Search for AI agents")
+        .permission(Permission::SearchRead)
+        .body::<SearchRequest>()
+        .json::<SearchResponse>(200, "Ranked results")
+        .add();
+    spec.op("post", "/v1/mcp/context", "MCP", "Get the context of a chunk")
+        .permission(Permission::SearchRead)
+        .body::<mcp::McpContextRequest>()
+        .json::<Value>(200, "Chunk context")
+        .add();
+    spec.op("get", "/v1/mcp/capabilities", "MCP", "List MCP tools")
+        .permission(Permission::SearchRead)
+        .json::<McpCapabilities>(200, "Available tools and resources")
+        .add();
+}
+
+fn processing_operations(spec: &mut Spec) {
+    spec.op("post", "/v1/process", "Processing", "Process files through the unified pipeline")
+        .permission(Permission::ProcessingWrite)
+        .body::<processing::ProcessRequest>()
+        .json::<Value>(200, "Processing result from unified-processor")
+        .add();
+    spec.op("post", "/v1/chunk", "Processing", "Chunk content")
+        .permission(Permission::ProcessingWrite)
+        .body::<processing::ChunkRequest>()
+        .json::<Value>(200, "Chunks")
+        .add();
+    spec.op("post", "/v1/embed", "Processing", "Embed a text")
+        .permission(Permission::ProcessingWrite)
+        .body::<processing::EmbedRequest>()
+        .json::<Value>(200, "Embedding")
+        .add();
+    spec.op("post", "/v1/embed/batch", "Processing", "Embed several texts")
+        .permission(Permission::ProcessingWrite)
+        .body::<processing::BatchEmbedRequest>()
+        .json::<Value>(200, "Embeddings")
+        .add();
+    spec.op("post", "/v1/search/semantic", "Processing", "Semantic search over processed content")
+        .permission(Permission::SearchRead)
+        .body::<processing::SearchRequest>()
+        .json::<Value>(200, "Results from unified-processor")
+        .add();
+    spec.op("get", "/v1/processor/status", "Processing", "Status of unified-processor")
+        .permission(Permission::ProcessingRead)
+        .json::<Value>(200, "Processor status")
+        .add();
+}
+
+fn compliance_operations(spec: &mut Spec) {
+    spec.op("get", "/v1/compliance/dashboard", "Compliance", "GDPR and SOC2 compliance dashboard")
+        .permission(Permission::ComplianceRead)
+        .json::<compliance::ComplianceDashboard>(200, "Compliance status")
+        .add();
+    spec.op("get", "/v1/compliance/audit-logs", "Compliance", "The caller's audit trail")
+        .permission(Permission::ComplianceRead)
+        .json::<compliance::AuditLogResponse>(200, "Audit events")
+        .add();
+    spec.op("post", "/v1/compliance/gdpr/export", "Compliance", "Export the caller's data")
+        .permission(Permission::AccountData)
+        .json::<compliance::DataExportResponse>(200, "The export was scheduled")
+        .add();
+    spec.op("post", "/v1/compliance/gdpr/delete", "Compliance", "Erase the caller's data")
+        .permission(Permission::AccountData)
+        .json::<compliance::DataDeletionResponse>(200, "The deletion was scheduled")
+        .add();
+}
+
+fn session_operations(spec: &mut Spec) {
+    spec.op("get", "/v1/auth/me", "Session", "The caller, effective workspace and permissions")
+        .authenticated()
+        .json::<auth::SessionResponse>(200, "The session")
+        .add();
+    spec.op("post", "/v1/auth/logout", "Session", "End the session and clear the refresh cookie")
+        .authenticated()
+        .optional_body::<auth::LogoutRequest>()
+        .empty(204, "Logged out")
+        .add();
+    spec.op("post", "/v1/auth/refresh", "Session", "Exchange a refresh token for a new token pair")
+        .describe("The refresh token is read from the body or the `refresh_token` cookie.")
+        .optional_body::<auth::RefreshRequest>()
+        .json::<auth::RefreshResponse>(200, "New tokens")
+        .add();
+}
+
+fn subscriptions_operations(spec: &mut Spec) {
+    use webhook_subscriptions as ws;
+
+    spec.op("get", "/v1/webhook-subscriptions", "Webhook subscriptions", "List the caller's subscriptions")
+        .permission(Permission::WebhooksManage)
+        .json::<ws::SubscriptionsResponse>(200, "Subscriptions")
+        .add();
+    spec.op("post", "/v1/webhook-subscriptions", "Webhook subscriptions", "Subscribe an endpoint to events")
+        .permission(Permission::WebhooksManage)
+        .body::<ws::CreateSubscriptionRequest>()
+        .json::<ws::CreatedSubscriptionResponse>(201, "The subscription, with its signing secret")
+        .add();
+    spec.op("get", "/v1/webhook-subscriptions/:id", "Webhook subscriptions", "Get a subscription")
+        .permission(Permission::WebhooksManage)
+        .json::<WebhookSubscription>(200, "The subscription")
+        .add();
+    spec.op("put", "/v1/webhook-subscriptions/:id", "Webhook subscriptions", "Change, enable or disable a subscription")
+        .permission(Permission::WebhooksManage)
+        .body::<ws::UpdateSubscriptionRequest>()
+        .json::<WebhookSubscription>(200, "The updated subscription")
+        .add();
+    spec.op("delete", "/v1/webhook-subscriptions/:id", "Webhook subscriptions", "Delete a subscription")
+        .permission(Permission::WebhooksManage)
+        .empty(204, "Deleted with its delivery log")
+        .add();
+    spec.op("get", "/v1/webhook-subscriptions/:id/deliveries", "Webhook subscriptions", "The subscription's delivery log")
+        .permission(Permission::WebhooksManage)
+        .query::<ws::DeliveriesQuery>()
+        .json::<ws::DeliveriesResponse>(200, "Deliveries, newest first")
+        .add();
+}
+
+fn admin_operations(spec: &mut Spec) {
+    spec.op("get", "/v1/admin/circuit-breakers", "Admin", "List circuit breakers")
+        .permission(Permission::Admin)
+        .json::<admin::CircuitBreakersResponse>(200, "Every breaker and its state")
+        .add();
+    spec.op("get", "/v1/admin/circuit-breakers/:service", "Admin", "Get a circuit breaker")
+        .permission(Permission::Admin)
+        .json::<BreakerSnapshot>(200, "The breaker")
+        .add();
+    spec.op("post", "/v1/admin/circuit-breakers/:service/open", "Admin", "Force a breaker open")
+        .permission(Permission::Admin)
+        .json::<BreakerSnapshot>(200, "The breaker")
+        .add();
+    spec.op("post", "/v1/admin/circuit-breakers/:service/close", "Admin", "Force a breaker closed")
+        .permission(Permission::Admin)
+        .json::<BreakerSnapshot>(200, "The breaker")
+        .add();
+    spec.op("post", "/v1/admin/circuit-breakers/:service/reset", "Admin", "Clear a breaker's override and counters")
+        .permission(Permission::Admin)
+        .json::<BreakerSnapshot>(200, "The breaker")
+        .add();
+    spec.op("post", "/v1/admin/auth-cache/invalidate", "Admin", "Drop cached credential verifications")
+        .permission(Permission::Admin)
+        .body::<admin::InvalidateAuthCacheRequest>()
+        .empty(204, "Invalidated")
+        .add();
+    spec.op("get", "/v1/admin/webhooks/deliveries", "Admin", "List webhook inbox deliveries")
+        .permission(Permission::Admin)
+        .query::<admin::WebhookDeliveriesQuery>()
+        .json::<admin::WebhookDeliveriesResponse>(200, "Deliveries, newest first")
+        .add();
+    spec.op("get", "/v1/admin/webhooks/deliveries/:id", "Admin", "Get a webhook inbox delivery")
+        .permission(Permission::Admin)
+        .json::<WebhookDelivery>(200, "The delivery")
+        .add();
+    spec.op("post", "/v1/admin/webhooks/deliveries/:id/redeliver", "Admin", "Queue a delivery to be forwarded again")
+        .permission(Permission::Admin)
+        .empty(202, "Queued")
+        .add();
+    spec.op("post", "/v1/internal/auth-cache/invalidate", "Internal", "Drop cached credential verifications")
+        .service()
+        .body::<admin::InvalidateAuthCacheRequest>()
+        .empty(204, "Invalidated")
+        .add();
+}
+
+/// `/api/*` routes used by the web app
+fn management_operations(spec: &mut Spec) {
+    spec.op("get", "/api/urls", "URLs", "List URLs")
+        .authenticated()
+        .json::<ApiResponse<Vec<UrlRecord>>>(200, "URLs")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("post", "/api/urls", "URLs", "Add a URL")
+        .permission(Permission::ResourcesWrite)
+        .body::<urls::CreateUrlRequest>()
+        .json::<ApiResponse<UrlRecord>>(201, "The created URL")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("get", "/api/urls/:id", "URLs", "Get a URL")
+        .authenticated()
+        .json::<ApiResponse<UrlRecord>>(200, "The URL")
+        .json::<ApiError>(404, "No such URL")
+        .add();
+    spec.op("delete", "/api/urls/:id", "URLs", "Delete a URL")
+        .permission(Permission::ResourcesWrite)
+        .json::<ApiError>(200, "Deleted")
+        .json::<ApiError>(404, "No such URL")
+        .add();
+
+    spec.op("get", "/api/dashboard/stats", "Dashboard", "Dashboard statistics")
+        .authenticated()
+        .json::<dashboard::DashboardStats>(200, "Resource counts")
+        .json::<ApiError>(500, "Store error")
+        .add();
+
+    spec.op("get", "/api/repositories", "Repositories", "List repositories")
+        .authenticated()
+        .json::<ApiResponse<Vec<RepositoryRecord>>>(200, "Repositories")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("post", "/api/repositories", "Repositories", "Add a repository")
+        .permission(Permission::ResourcesWrite)
+        .body::<repositories::CreateRepositoryRequest>()
+        .json::<ApiResponse<RepositoryRecord>>(201, "The created repository")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("get", "/api/repositories/:id", "Repositories", "Get a repository")
+        .authenticated()
+        .json::<ApiResponse<RepositoryRecord>>(200, "The repository")
+        .json::<ApiError>(404, "No such repository")
+        .add();
+    spec.op("delete", "/api/repositories/:id", "Repositories", "Delete a repository")
+        .permission(Permission::ResourcesWrite)
+        .json::<ApiError>(200, "Deleted")
+        .json::<ApiError>(404, "No such repository")
+        .add();
+
+    spec.op("get", "/api/documents", "Documents", "List documents")
+        .authenticated()
+        .query::<documents::SearchQuery>()
+        .json::<ApiResponse<documents::DocumentListResponse>>(200, "Documents")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("post", "/api/documents", "Documents", "Add a document")
+        .permission(Permission::ResourcesWrite)
+        .body::<documents::CreateDocumentRequest>()
+        .json::<ApiResponse<DocumentRecord>>(201, "The created document")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("delete", "/api/documents/:id", "Documents", "Delete a document")
+        .permission(Permission::ResourcesWrite)
+        .json::<ApiError>(200, "Deleted")
+        .json::<ApiError>(404, "No such document")
+        .add();
+    spec.op("get", "/api/documents/analytics", "Documents", "Document analytics")
+        .authenticated()
+        .json::<ApiResponse<Value>>(200, "Analytics")
+        .add();
+
+    spec.op("get", "/api/agents", "Agents", "List agents")
+        .authenticated()
+        .json::<ApiResponse<Vec<AgentRecord>>>(200, "Agents")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("post", "/api/agents", "Agents", "Register an agent")
+        .permission(Permission::ResourcesWrite)
+        .body::<agents::CreateAgentRequest>()
+        .json::<ApiResponse<AgentRecord>>(201, "The created agent")
+        .json::<ApiError>(500, "Store error")
+        .add();
+    spec.op("get", "/api/agents/:id", "Agents", "Get an agent")
+        .authenticated()
+        .json::<ApiResponse<AgentRecord>>(200, "The agent")
+        .json::<ApiError>(404, "No such agent")
+        .add();
+    spec.op("put", "/api/agents/:id", "Agents", "Update an agent")
+        .permission(Permission::ResourcesWrite)
+        .body::<agents::UpdateAgentRequest>()
+        .json::<ApiResponse<AgentRecord>>(200, "The updated agent")
+        .json::<ApiError>(404, "No such agent")
+        .add();
+    spec.op("delete", "/api/agents/:id", "Agents", "Delete an agent")
+        .permission(Permission::ResourcesWrite)
+        .json::<ApiError>(200, "Deleted")
+        .json::<ApiError>(404, "No such agent")
+        .add();
+    spec.op("post", "/api/agents/:id/test", "Agents", "Test an agent's connection")
+        .authenticated()
+        .json::<ApiResponse<Value>>(200, "Test result")
+        .json::<ApiError>(404, "No such agent")
+        .add();
+    spec.op("post", "/api/agents/:id/invoke", "Agents", "Invoke an agent")
+        .permission(Permission::AgentsInvoke)
+        .body::<agents::AgentInvokeRequest>()
+        .json::<ApiResponse<agents::AgentInvokeResponse>>(200, "The agent's response")
+        .json::<ApiError>(404, "No such agent")
+        .add();
+    spec.op("get", "/api/agents/:id/context", "Agents", "Context available to an agent")
+        .authenticated()
+        .json::<ApiResponse<Value>>(200, "Context")
+        .json::<ApiError>(404, "No such agent")
+        .add();
+}
+
+fn webhooks_operations(spec: &mut Spec) {
+    let providers = [
+        ("/webhooks/github", "GitHub", "X-Hub-Signature-256", "`sha256=` and the hex HMAC-SHA256 of the body keyed with the source's `webhook_secret` (else `GITHUB_WEBHOOK_SECRET`)"),
+        ("/webhooks/gitlab", "GitLab", "X-Gitlab-Token", "Equal to the source's `webhook_secret` (else `GITLAB_WEBHOOK_TOKEN`)"),
+        ("/webhooks/bitbucket", "Bitbucket", "X-Hub-Signature", "`sha256=` and the hex HMAC-SHA256 of the body keyed with the source's `webhook_secret` (else `BITBUCKET_WEBHOOK_SECRET`)"),
+    ];
+    for (path, provider, signature_header, signature) in providers {
+        spec.op("post", path, "Webhooks", provider)
+            .describe("Verified and recorded in the webhook inbox; forwarded in the background.")
+            .header(signature_header, signature)
+            .body::<Value>()
+            .json::<WebhookAck>(202, "Queued")
+            .json::<WebhookAck>(200, "Already received; not queued again")
+            .add();
+    }
+}
+
+/// Operations collected so far and the generator holding their schemas
+struct Spec {
+    generator: SchemaGenerator,
+    paths: Map<String, Value>,
+    tags: Vec<&'static str>,
+}
+
+impl Spec {
+    fn new() -> Self {
+        let generator = SchemaSettings::draft2020_12()
+            .with(|s| s.definitions_path = "/components/schemas".into())
+            .into_generator();
+        Self { generator, paths: Map::new(), tags: Vec::new() }
+    }
+
+    /// Start an operation; `path` uses axum's `:param` syntax
+    fn op(
+        &mut self,
+        method: &'static str,
+        path: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+    ) -> Operation<'_> {
+        if !self.tags.contains(&tag) {
+            self.tags.push(tag);
+        }
+        let mut operation = Map::new();
+        operation.insert("tags".to_string(), json!([tag]));
+        operation.insert("summary".to_string(), json!(summary));
+        Operation {
+            spec: self,
+            method,
+            path,
+            operation,
+            parameters: Vec::new(),
+            responses: Map::new(),
+        }
+    }
+
+    fn finish(mut self) -> Value {
+        let error = self.generator.subschema_for::<ErrorResponse>();
+        let schemas = self.generator.take_definitions(true);
+        json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": "ConFuse API Gateway",
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": "Central API gateway for the ConFuse Knowledge Intelligence Platform. \
+                    Errors use the `Error` response unless an operation lists its own.",
+            },
+            "tags": self.tags.iter().map(|name| json!({ "name": name })).collect::<Vec<_>>(),
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "responses": {
+                    "Error": {
+                        "description": "Error",
+                        "content": { "application/json": { "schema": error } },
+                    },
+                },
+                "securitySchemes": {
+                    "bearerAuth": {
+                        "type": "http",
+                        "scheme": "bearer",
+                        "bearerFormat": "JWT",
+                        "description": "Access token issued by auth-middleware",
+                    },
+                    "apiKeyAuth": {
+                        "type": "apiKey",
+                        "in": "header",
+                        "name": "X-API-Key",
+                    },
+                    "serviceSignature": {
+                        "type": "apiKey",
+                        "in": "header",
+                        "name": "X-Service-Signature",
+                        "description": "HMAC-SHA256 request signature, sent with `X-Service-Name`, \
+                            `X-Request-Timestamp` and a single-use `X-Request-Nonce`",
+                    },
+                },
+            },
+        })
+    }
+}
+
+/// One operation being described
+struct Operation<'a> {
+    spec: &'a mut Spec,
+    method: &'static str,
+    path: &'static str,
+    operation: Map<String, Value>,
+    parameters: Vec<Value>,
+    responses: Map<String, Value>,
+}
+
+impl Operation<'_> {
+    fn describe(mut self, description: &str) -> Self {
+        self.operation.insert("description".to_string(), json!(description));
+        self
+    }
+
+    /// Requires a bearer token or API key (`auth_middleware`)
+    fn authenticated(mut self) -> Self {
+        self.operation.insert(
+            "security".to_string(),
+            json!([{ "bearerAuth": [] }, { "apiKeyAuth": [] }]),
+        );
+        self.responses
+            .insert("401".to_string(), json!({ "$ref": "#/components/responses/Error" }));
+        self
+    }
+
+    /// Requires authentication and a permission (`require_permission`)
+    fn permission(mut self, permission: Permission) -> Self {
+        self.operation
+            .insert("x-permission".to_string(), json!(permission.as_str()));
+        self.responses
+            .insert("403".to_string(), json!({ "$ref": "#/components/responses/Error" }));
+        self.authenticated()
+    }
+
+    /// Requires a signed service identity (`require_service_identity`)
+    fn service(mut self) -> Self {
+        self.operation
+            .insert("security".to_string(), json!([{ "serviceSignature": [] }]));
+        self.responses
+            .insert("401".to_string(), json!({ "$ref": "#/components/responses/Error" }));
+        self
+    }
+
+    /// A required request header
+    fn header(mut self, name: &str, description: &str) -> Self {
+        self.parameters.push(json!({
+            "name": name,
+            "in": "header",
+            "required": true,
+            "description": description,
+            "schema": { "type": "string" },
+        }));
+        self
+    }
+
+    /// Query parameters, one per field of the `Query` extractor's type
+    fn query<T: JsonSchema>(mut self) -> Self {
+        let schema = Value::from(T::json_schema(&mut self.spec.generator));
+        let required = schema
+            .get("required")
+            .and_then(Value::as_array)
+            .cloned()
+            .unwrap_or_default();
+        let properties = schema
+            .get("properties")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
+        for (name, mut property) in properties {
+            let description = property
+                .as_object_mut()
+                .and_then(|p| p.remove("description"));
+            let mut parameter = json!({
+                "name": name,
+                "in": "query",
+                "required": required.contains(&json!(name)),
+                "schema": property,
+            });
+            if let Some(description) = description {
+                parameter["description"] = description;
+            }
+            self.parameters.push(parameter);
+        }
+        self
+    }
+
+    /// A required JSON body
+    fn body<T: JsonSchema>(self) -> Self {
+        self.request_body::<T>(true)
+    }
+
+    /// A JSON body that may be omitted
+    fn optional_body<T: JsonSchema>(self) -> Self {
+        self.request_body::<T>(false)
+    }
+
+    fn request_body<T: JsonSchema>(mut self, required: bool) -> Self {
+        let schema = self.spec.generator.subschema_for::<T>();
+        self.operation.insert(
+            "requestBody".to_string(),
+            json!({
+                "required": required,
+                "content": { "application/json": { "schema": schema } },
+            }),
+        );
+        self
+    }
+
+    /// A JSON response
+    fn json<T: JsonSchema>(mut self, status: u16, description: &str) -> Self {
+        let schema = self.spec.generator.subschema_for::<T>();
+        self.responses.insert(
+            status.to_string(),
+            json!({
+                "description": description,
+                "content": { "application/json": { "schema": schema } },
+            }),
+        );
+        self
+    }
+
+    /// A text response of the given media type
+    fn text(mut self, status: u16, media_type: &str, description: &str) -> Self {
+        self.responses.insert(
+            status.to_string(),
+            json!({
+                "description": description,
+                "content": { media_type: { "schema": { "type": "string" } } },
+            }),
+        );
+        self
+    }
+
+    /// A response without a body
+    fn empty(mut self, status: u16, description: &str) -> Self {
+        self.responses
+            .insert(status.to_string(), json!({ "description": description }));
+        self
+    }
+
+    fn add(mut self) {
+        // `:param` segments become `{param}` with a path parameter each
+        let mut path_parameters = Vec::new();
+        let path = self
+            .path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(name) => {
+                    path_parameters.push(json!({
+                        "name": name,
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    }));
+                    format!("{{{}}}", name)
+                }
+                None => segment.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+        path_parameters.append(&mut self.parameters);
+        if !path_parameters.is_empty() {
+            self.operation
+                .insert("parameters".to_string(), Value::Array(path_parameters));
+        }
+
+        self.responses
+            .entry("default")
+            .or_insert_with(|| json!({ "$ref": "#/components/responses/Error" }));
+        self.operation
+            .insert("responses".to_string(), Value::Object(self.responses));
+
+        let item = self
+            .spec
+            .paths
+            .entry(path)
+            .or_insert_with(|| Value::Object(Map::new()));
+        item[self.method] = Value::Object(self.operation);
+    }
+}
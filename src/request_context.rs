@@ -0,0 +1,70 @@
+//! Request-scoped context propagated to downstream services
+//!
+//! The correlation ID (set by `zero_trust_middleware`) and the authenticated
+//! user (set by the auth middleware) are held in a task-local for the
+//! duration of a request. Service clients read it to attach the matching
+//! headers to every outbound call, so downstream logs can be joined to
+//! gateway logs.
+
+use std::future::Future;
+
+use crate::models::{User, UserContext};
+
+/// Header carrying the request's correlation ID
+pub const HEADER_CORRELATION_ID: &str = "X-Correlation-Id";
+
+tokio::task_local! {
+    static CURRENT: RequestContext;
+}
+
+/// Identity and correlation data for the request being handled
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    pub correlation_id: Option<String>,
+    pub user_id: Option<String>,
+    pub workspace_id: Option<String>,
+    pub user_email: Option<String>,
+}
+
+impl RequestContext {
+    /// Context of the current request, if running inside one
+    pub fn current() -> Option<Self> {
+        CURRENT.try_with(|ctx| ctx.clone()).ok()
+    }
+
+    /// Current context (or an empty one) extended with a correlation ID
+    pub fn with_correlation_id(correlation_id: String) -> Self {
+        Self {
+            correlation_id: Some(correlation_id),
+            ..Self::current().unwrap_or_default()
+        }
+    }
+
+    /// Current context (or an empty one) extended with an authenticated user
+    pub fn with_user(user: &User) -> Self {
+        Self {
+            user_id: Some(user.id.clone()),
+            workspace_id: user.workspace_id.clone(),
+            user_email: Some(user.email.clone()),
+            ..Self::current().unwrap_or_default()
+        }
+    }
+
+    /// Run `f` with this context installed
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        CURRENT.scope(self, f).await
+    }
+
+    /// Header name/value pairs to send downstream
+    pub fn headers(&self) -> Vec<(&'static str, &str)> {
+        [
+            (HEADER_CORRELATION_ID, self.correlation_id.as_deref()),
+            (UserContext::HEADER_USER_ID, self.user_id.as_deref()),
+            (UserContext::HEADER_WORKSPACE_ID, self.workspace_id.as_deref()),
+            (UserContext::HEADER_USER_EMAIL, self.user_email.as_deref()),
+        ]
+        .into_iter()
+        .filter_map(|(name, value)| value.map(|v| (name, v)))
+        .collect()
+    }
+}
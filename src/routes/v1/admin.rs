@@ -0,0 +1,212 @@
+//! Admin endpoints for operating the gateway
+//!
+//! Circuit breaker inspection and manual control, invalidation of cached
+//! credential verifications, and inspection and redelivery of the webhook
+//! inbox. Mounted behind `auth_middleware` and
+//! require the `admin` permission; auth cache invalidation is also exposed
+//! to signed service callers under `/v1/internal`.
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+use crate::error::{AppError, Result};
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::{BreakerSnapshot, CircuitState, ServiceIdentity};
+use crate::models::{WebhookDelivery, DELIVERY_DELIVERED, DELIVERY_FAILED, DELIVERY_PENDING};
+use super::AppState;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CircuitBreakersResponse {
+    pub breakers: Vec<BreakerSnapshot>,
+}
+
+/// GET /v1/admin/circuit-breakers - List every breaker and its state
+pub async fn list_circuit_breakers(
+    State(state): State<AppState>,
+) -> Json<CircuitBreakersResponse> {
+    Json(CircuitBreakersResponse {
+        breakers: state.circuit_breaker.snapshot_all(),
+    })
+}
+
+/// GET /v1/admin/circuit-breakers/:service - Get one breaker
+pub async fn get_circuit_breaker(
+    State(state): State<AppState>,
+    Path(service): Path<String>,
+) -> Result<Json<BreakerSnapshot>> {
+    ensure_known(&state, &service)?;
+    Ok(Json(state.circuit_breaker.snapshot(&service)))
+}
+
+/// POST /v1/admin/circuit-breakers/:service/open - Force a breaker open
+pub async fn force_open(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(service): Path<String>,
+) -> Result<Json<BreakerSnapshot>> {
+    ensure_known(&state, &service)?;
+    tracing::warn!(service = %service, admin = %user.0.id, "Circuit breaker forced open");
+    state.circuit_breaker.force(&service, CircuitState::Open);
+    Ok(Json(state.circuit_breaker.snapshot(&service)))
+}
+
+/// POST /v1/admin/circuit-breakers/:service/close - Force a breaker closed
+pub async fn force_close(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(service): Path<String>,
+) -> Result<Json<BreakerSnapshot>> {
+    ensure_known(&state, &service)?;
+    tracing::warn!(service = %service, admin = %user.0.id, "Circuit breaker forced closed");
+    state.circuit_breaker.force(&service, CircuitState::Closed);
+    Ok(Json(state.circuit_breaker.snapshot(&service)))
+}
+
+/// POST /v1/admin/circuit-breakers/:service/reset - Clear overrides and counters
+pub async fn reset(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(service): Path<String>,
+) -> Result<Json<BreakerSnapshot>> {
+    ensure_known(&state, &service)?;
+    tracing::info!(service = %service, admin = %user.0.id, "Circuit breaker reset");
+    state.circuit_breaker.reset(&service);
+    Ok(Json(state.circuit_breaker.snapshot(&service)))
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InvalidateAuthCacheRequest {
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub api_key_id: Option<String>,
+}
+
+/// POST /v1/admin/auth-cache/invalidate - Drop cached verifications for a
+/// user's credentials and/or an API key, e.g. after revocation
+pub async fn invalidate_auth_cache(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(req): Json<InvalidateAuthCacheRequest>,
+) -> Result<StatusCode> {
+    invalidate(&state, &req)?;
+    tracing::info!(
+        user_id = ?req.user_id,
+        api_key_id = ?req.api_key_id,
+        admin = %user.0.id,
+        "Auth cache invalidated"
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// POST /v1/internal/auth-cache/invalidate - Same, for services such as
+/// auth-middleware revoking credentials
+pub async fn service_invalidate_auth_cache(
+    State(state): State<AppState>,
+    service: ServiceIdentity,
+    Json(req): Json<InvalidateAuthCacheRequest>,
+) -> Result<StatusCode> {
+    invalidate(&state, &req)?;
+    tracing::info!(
+        user_id = ?req.user_id,
+        api_key_id = ?req.api_key_id,
+        service = %service.name,
+        "Auth cache invalidated"
+    );
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct WebhookDeliveriesQuery {
+    /// `pending`, `delivered` or `failed`
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default = "default_deliveries_limit")]
+    pub limit: usize,
+}
+
+fn default_deliveries_limit() -> usize {
+    50
+}
+
+/// Most deliveries returned by one listing
+const MAX_DELIVERIES_LIMIT: usize = 500;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDelivery>,
+}
+
+/// GET /v1/admin/webhooks/deliveries - List inbox deliveries, newest first
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    Query(query): Query<WebhookDeliveriesQuery>,
+) -> Result<Json<WebhookDeliveriesResponse>> {
+    if let Some(status) = query.status.as_deref() {
+        if ![DELIVERY_PENDING, DELIVERY_DELIVERED, DELIVERY_FAILED].contains(&status) {
+            return Err(AppError::ValidationError(format!("Unknown delivery status '{}'", status)));
+        }
+    }
+    let deliveries = state
+        .stores
+        .webhooks
+        .list(query.status.as_deref(), query.limit.clamp(1, MAX_DELIVERIES_LIMIT))
+        .await?;
+    Ok(Json(WebhookDeliveriesResponse { deliveries }))
+}
+
+/// GET /v1/admin/webhooks/deliveries/:id - Get one delivery with its payload
+pub async fn get_webhook_delivery(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<WebhookDelivery>> {
+    state
+        .stores
+        .webhooks
+        .get(&id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound(format!("No webhook delivery '{}'", id)))
+}
+
+/// POST /v1/admin/webhooks/deliveries/:id/redeliver - Forward a delivery
+/// again, whatever its status, with a fresh set of attempts
+pub async fn redeliver_webhook(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.stores.webhooks.redeliver(&id).await? {
+        return Err(AppError::NotFound(format!("No webhook delivery '{}'", id)));
+    }
+    tracing::info!(id = %id, admin = %user.0.id, "Webhook delivery queued for redelivery");
+    Ok(StatusCode::ACCEPTED)
+}
+
+fn invalidate(state: &AppState, req: &InvalidateAuthCacheRequest) -> Result<()> {
+    if req.user_id.is_none() && req.api_key_id.is_none() {
+        return Err(AppError::ValidationError("user_id or api_key_id is required".to_string()));
+    }
+
+    let cache = &state.auth_layer.cache;
+    if let Some(user_id) = &req.user_id {
+        cache.revoke_user(user_id);
+    }
+    if let Some(key_id) = &req.api_key_id {
+        cache.revoke_api_key(key_id);
+    }
+    Ok(())
+}
+
+fn ensure_known(state: &AppState, service: &str) -> Result<()> {
+    if state.circuit_breaker.contains(service) {
+        Ok(())
+    } else {
+        Err(AppError::NotFound(format!("No circuit breaker for service '{}'", service)))
+    }
+}
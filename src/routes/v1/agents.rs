@@ -1,110 +1,19 @@
 //! AI Agent management routes
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, State, Extension},
     http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use once_cell::sync::Lazy;
+use schemars::JsonSchema;
 
-use super::AppState;
+use crate::error::AppError;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::{mask_api_key, AgentConfig, AgentRecord, AgentUsageStats, ApiResponse};
+use crate::store::{Owner, Stores};
 
-/// In-memory storage for agents (for development)
-static AGENT_STORE: Lazy<Arc<RwLock<Vec<AgentRecord>>>> = Lazy::new(|| {
-    Arc::new(RwLock::new(vec![
-        AgentRecord {
-            id: "agent-001".to_string(),
-            user_id: "user-rishabh-001".to_string(),
-            name: "GitHub Copilot".to_string(),
-            agent_type: "copilot".to_string(),
-            endpoint: None,
-            api_key: "sk-***hidden***".to_string(),
-            permissions: vec!["read".to_string(), "context".to_string()],
-            status: "Connected".to_string(),
-            config: AgentConfig {
-                model: Some("gpt-4".to_string()),
-                temperature: Some(0.7),
-                max_tokens: Some(4096),
-                timeout: Some(30),
-                custom_instructions: None,
-            },
-            usage_stats: AgentUsageStats {
-                total_requests: 1247,
-                total_tokens: 45000,
-                avg_response_time: Some(1.2),
-                last_error: None,
-            },
-            created_at: "2026-01-10T08:00:00Z".to_string(),
-            updated_at: "2026-01-27T10:00:00Z".to_string(),
-            last_used: Some("2026-01-27T11:30:00Z".to_string()),
-        },
-        AgentRecord {
-            id: "agent-002".to_string(),
-            user_id: "user-rishabh-001".to_string(),
-            name: "Amazon Q".to_string(),
-            agent_type: "amazon_q".to_string(),
-            endpoint: None,
-            api_key: "amz-***hidden***".to_string(),
-            permissions: vec!["read".to_string(), "context".to_string(), "write".to_string()],
-            status: "Connected".to_string(),
-            config: AgentConfig {
-                model: None,
-                temperature: None,
-                max_tokens: None,
-                timeout: Some(60),
-                custom_instructions: None,
-            },
-            usage_stats: AgentUsageStats {
-                total_requests: 892,
-                total_tokens: 32000,
-                avg_response_time: Some(0.9),
-                last_error: None,
-            },
-            created_at: "2026-01-12T10:00:00Z".to_string(),
-            updated_at: "2026-01-26T15:00:00Z".to_string(),
-            last_used: Some("2026-01-27T09:45:00Z".to_string()),
-        },
-    ]))
-});
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentRecord {
-    pub id: String,
-    pub user_id: String,
-    pub name: String,
-    pub agent_type: String,
-    pub endpoint: Option<String>,
-    pub api_key: String,
-    pub permissions: Vec<String>,
-    pub status: String,
-    pub config: AgentConfig,
-    pub usage_stats: AgentUsageStats,
-    pub created_at: String,
-    pub updated_at: String,
-    pub last_used: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentConfig {
-    pub model: Option<String>,
-    pub temperature: Option<f64>,
-    pub max_tokens: Option<u32>,
-    pub timeout: Option<u32>,
-    pub custom_instructions: Option<String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct AgentUsageStats {
-    pub total_requests: u32,
-    pub total_tokens: u32,
-    pub avg_response_time: Option<f64>,
-    pub last_error: Option<String>,
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateAgentRequest {
     pub name: String,
     pub agent_type: String,
@@ -114,7 +23,7 @@ pub struct CreateAgentRequest {
     pub config: AgentConfig,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct UpdateAgentRequest {
     pub name: Option<String>,
     pub endpoint: Option<String>,
@@ -124,246 +33,223 @@ pub struct UpdateAgentRequest {
     pub status: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct AgentInvokeRequest {
     pub message: String,
     pub context_type: Option<String>,
     pub include_history: Option<bool>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct AgentInvokeResponse {
     pub response: String,
     pub usage: InvokeUsage,
     pub context_used: Vec<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct InvokeUsage {
     pub tokens_used: u32,
     pub response_time_ms: u32,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
+type ApiError = (StatusCode, Json<ApiResponse<()>>);
+
+fn not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, Json(ApiResponse {
+        success: false,
+        message: "Agent not found".to_string(),
+        data: None,
+    }))
+}
+
+fn store_error(err: AppError) -> ApiError {
+    tracing::error!("Agent store error: {}", err);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
+        success: false,
+        message: "Failed to access agents".to_string(),
+        data: None,
+    }))
+}
+
+/// Look up one of the caller's agents, or 404
+async fn find_agent(stores: &Stores, user: &AuthenticatedUser, id: &str) -> Result<AgentRecord, ApiError> {
+    stores.agents
+        .get(&Owner::of(&user.0), id)
+        .await
+        .map_err(store_error)?
+        .ok_or_else(not_found)
 }
 
 /// List all agents
 pub async fn list_agents(
-    State(_state): State<AppState>,
-) -> Json<ApiResponse<Vec<AgentRecord>>> {
-    let store = AGENT_STORE.read().await;
-    Json(ApiResponse {
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<ApiResponse<Vec<AgentRecord>>>, ApiError> {
+    let agents = stores.agents.list(&Owner::of(&user.0)).await.map_err(store_error)?;
+    Ok(Json(ApiResponse {
         success: true,
         message: "Agents retrieved successfully".to_string(),
-        data: Some(store.clone()),
-    })
+        data: Some(agents),
+    }))
 }
 
 /// Get a specific agent
 pub async fn get_agent(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<AgentRecord>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let store = AGENT_STORE.read().await;
-    
-    if let Some(agent) = store.iter().find(|a| a.id == id) {
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Agent retrieved successfully".to_string(),
-            data: Some(agent.clone()),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Agent not found".to_string(),
-            data: None,
-        })))
-    }
+) -> Result<Json<ApiResponse<AgentRecord>>, ApiError> {
+    let agent = find_agent(&stores, &user, &id).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Agent retrieved successfully".to_string(),
+        data: Some(agent),
+    }))
 }
 
 /// Create a new agent
 pub async fn create_agent(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Json(payload): Json<CreateAgentRequest>,
-) -> (StatusCode, Json<ApiResponse<AgentRecord>>) {
+) -> Result<(StatusCode, Json<ApiResponse<AgentRecord>>), ApiError> {
     let now = chrono::Utc::now().to_rfc3339();
     let agent = AgentRecord {
         id: uuid::Uuid::new_v4().to_string(),
-        user_id: "user-rishabh-001".to_string(),
+        user_id: user.0.id.clone(),
+        workspace_id: user.0.workspace_id.clone(),
         name: payload.name,
         agent_type: payload.agent_type,
         endpoint: payload.endpoint,
+        api_key_hint: mask_api_key(&payload.api_key),
         api_key: payload.api_key,
         permissions: payload.permissions,
         status: "Pending".to_string(),
         config: payload.config,
-        usage_stats: AgentUsageStats {
-            total_requests: 0,
-            total_tokens: 0,
-            avg_response_time: None,
-            last_error: None,
-        },
+        usage_stats: AgentUsageStats::default(),
         created_at: now.clone(),
         updated_at: now,
         last_used: None,
     };
 
-    let mut store = AGENT_STORE.write().await;
-    store.push(agent.clone());
+    stores.agents.insert(&agent).await.map_err(store_error)?;
 
-    (StatusCode::CREATED, Json(ApiResponse {
+    Ok((StatusCode::CREATED, Json(ApiResponse {
         success: true,
         message: "Agent created successfully".to_string(),
         data: Some(agent),
-    }))
+    })))
 }
 
 /// Update an agent
 pub async fn update_agent(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
     Json(payload): Json<UpdateAgentRequest>,
-) -> Result<Json<ApiResponse<AgentRecord>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let mut store = AGENT_STORE.write().await;
-    
-    if let Some(agent) = store.iter_mut().find(|a| a.id == id) {
-        if let Some(name) = payload.name {
-            agent.name = name;
-        }
-        if let Some(endpoint) = payload.endpoint {
-            agent.endpoint = Some(endpoint);
-        }
-        if let Some(api_key) = payload.api_key {
-            agent.api_key = api_key;
-        }
-        if let Some(permissions) = payload.permissions {
-            agent.permissions = permissions;
-        }
-        if let Some(config) = payload.config {
-            agent.config = config;
-        }
-        if let Some(status) = payload.status {
-            agent.status = status;
-        }
-        agent.updated_at = chrono::Utc::now().to_rfc3339();
-        
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Agent updated successfully".to_string(),
-            data: Some(agent.clone()),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Agent not found".to_string(),
-            data: None,
-        })))
+) -> Result<Json<ApiResponse<AgentRecord>>, ApiError> {
+    let mut agent = find_agent(&stores, &user, &id).await?;
+
+    if let Some(name) = payload.name {
+        agent.name = name;
+    }
+    if let Some(endpoint) = payload.endpoint {
+        agent.endpoint = Some(endpoint);
+    }
+    if let Some(api_key) = payload.api_key {
+        agent.api_key_hint = mask_api_key(&api_key);
+        agent.api_key = api_key;
+    }
+    if let Some(permissions) = payload.permissions {
+        agent.permissions = permissions;
     }
+    if let Some(config) = payload.config {
+        agent.config = config;
+    }
+    if let Some(status) = payload.status {
+        agent.status = status;
+    }
+    agent.updated_at = chrono::Utc::now().to_rfc3339();
+
+    if !stores.agents.update(&Owner::of(&user.0), &agent).await.map_err(store_error)? {
+        return Err(not_found());
+    }
+
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Agent updated successfully".to_string(),
+        data: Some(agent),
+    }))
 }
 
 /// Delete an agent
 pub async fn delete_agent(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let mut store = AGENT_STORE.write().await;
-    
-    if let Some(pos) = store.iter().position(|a| a.id == id) {
-        store.remove(pos);
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Agent deleted successfully".to_string(),
-            data: None,
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Agent not found".to_string(),
-            data: None,
-        })))
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    if !stores.agents.delete(&Owner::of(&user.0), &id).await.map_err(store_error)? {
+        return Err(not_found());
     }
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Agent deleted successfully".to_string(),
+        data: None,
+    }))
 }
 
 /// Test agent connection
 pub async fn test_agent(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let store = AGENT_STORE.read().await;
-    
-    if store.iter().any(|a| a.id == id) {
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Agent connection test successful".to_string(),
-            data: Some(serde_json::json!({ "connected": true })),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Agent not found".to_string(),
-            data: None,
-        })))
-    }
+) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
+    find_agent(&stores, &user, &id).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Agent connection test successful".to_string(),
+        data: Some(serde_json::json!({ "connected": true })),
+    }))
 }
 
 /// Invoke an agent
 pub async fn invoke_agent(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
     Json(_payload): Json<AgentInvokeRequest>,
-) -> Result<Json<ApiResponse<AgentInvokeResponse>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let store = AGENT_STORE.read().await;
-    
-    if store.iter().any(|a| a.id == id) {
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Agent invoked successfully".to_string(),
-            data: Some(AgentInvokeResponse {
-                response: "This is a mock response from the AI agent. In production, this would connect to the actual AI service.".to_string(),
-                usage: InvokeUsage {
-                    tokens_used: 150,
-                    response_time_ms: 850,
-                },
-                context_used: vec!["repo:frontend-app".to_string(), "doc:API Documentation".to_string()],
-            }),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Agent not found".to_string(),
-            data: None,
-        })))
-    }
+) -> Result<Json<ApiResponse<AgentInvokeResponse>>, ApiError> {
+    find_agent(&stores, &user, &id).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Agent invoked successfully".to_string(),
+        data: Some(AgentInvokeResponse {
+            response: "This is a mock response from the AI agent. In production, this would connect to the actual AI service.".to_string(),
+            usage: InvokeUsage {
+                tokens_used: 150,
+                response_time_ms: 850,
+            },
+            context_used: vec!["repo:frontend-app".to_string(), "doc:API Documentation".to_string()],
+        }),
+    }))
 }
 
 /// Get agent context
 pub async fn get_agent_context(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let store = AGENT_STORE.read().await;
-    
-    if store.iter().any(|a| a.id == id) {
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Agent context retrieved successfully".to_string(),
-            data: Some(serde_json::json!({
-                "repositories": ["frontend-app", "api-backend"],
-                "documents": ["API Documentation", "Architecture Overview"],
-                "urls": ["https://docs.confuse.dev"],
-                "total_tokens": 15000
-            })),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Agent not found".to_string(),
-            data: None,
-        })))
-    }
+) -> Result<Json<ApiResponse<serde_json::Value>>, ApiError> {
+    find_agent(&stores, &user, &id).await?;
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Agent context retrieved successfully".to_string(),
+        data: Some(serde_json::json!({
+            "repositories": ["frontend-app", "api-backend"],
+            "documents": ["API Documentation", "Architecture Overview"],
+            "urls": ["https://docs.confuse.dev"],
+            "total_tokens": 15000
+        })),
+    }))
 }
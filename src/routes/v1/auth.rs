@@ -0,0 +1,164 @@
+//! Session endpoints
+//!
+//! Token refresh, the current session and logout, proxied to auth-middleware
+//! so the web app only talks to the gateway. A refresh token may travel in
+//! the request body or in an HttpOnly `refresh_token` cookie scoped to
+//! `/v1/auth`; once a client uses the cookie, rotated refresh tokens are
+//! only ever set in the cookie.
+
+use axum::{
+    extract::{Extension, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+use crate::error::{AppError, Result};
+use crate::middleware::auth::AuthenticatedUser;
+use crate::middleware::auth_cache::Credential;
+use crate::middleware::permissions::{has_permission, Permission};
+use crate::models::{TokenPair, User};
+use super::AppState;
+
+/// Name of the refresh token cookie
+const REFRESH_COOKIE: &str = "refresh_token";
+
+/// Lifetime of the refresh token cookie
+const REFRESH_COOKIE_MAX_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct RefreshRequest {
+    /// Refresh token; read from the cookie when absent
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+    /// Return the rotated refresh token in an HttpOnly cookie instead of the body
+    #[serde(default)]
+    pub use_cookie: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RefreshResponse {
+    pub access_token: String,
+    /// Omitted when the refresh token is carried in the cookie
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    pub expires_in: u64,
+    pub token_type: String,
+}
+
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct LogoutRequest {
+    #[serde(default)]
+    pub refresh_token: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SessionResponse {
+    pub user: User,
+    /// Workspace requests are scoped to, validated against the user's memberships
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workspace_id: Option<String>,
+    /// Permissions granted by the user's roles or API key scopes
+    pub permissions: Vec<&'static str>,
+}
+
+/// POST /v1/auth/refresh - Exchange a refresh token for a new token pair
+pub async fn refresh(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Option<Json<RefreshRequest>>,
+) -> Result<Response> {
+    let req = body.map(|Json(req)| req).unwrap_or_default();
+    let cookie = refresh_cookie(&headers);
+    let use_cookie = req.use_cookie || (req.refresh_token.is_none() && cookie.is_some());
+
+    let refresh_token = req
+        .refresh_token
+        .or(cookie)
+        .ok_or_else(|| AppError::Unauthorized("No refresh token provided".to_string()))?;
+
+    let tokens: TokenPair = state.auth_client.refresh_token(&refresh_token).await?;
+
+    let mut response = Json(RefreshResponse {
+        access_token: tokens.access_token,
+        refresh_token: (!use_cookie).then(|| tokens.refresh_token.clone()),
+        expires_in: tokens.expires_in,
+        token_type: tokens.token_type,
+    })
+    .into_response();
+
+    if use_cookie {
+        set_refresh_cookie(&mut response, &tokens.refresh_token, REFRESH_COOKIE_MAX_AGE_SECS)?;
+    }
+    Ok(response)
+}
+
+/// GET /v1/auth/me - The authenticated user, effective workspace and permissions
+pub async fn me(Extension(user): Extension<AuthenticatedUser>) -> Json<SessionResponse> {
+    let permissions = Permission::ALL
+        .iter()
+        .filter(|p| has_permission(&user.0, **p))
+        .map(|p| p.as_str())
+        .collect();
+
+    Json(SessionResponse {
+        workspace_id: user.0.workspace_id.clone(),
+        user: user.0,
+        permissions,
+    })
+}
+
+/// POST /v1/auth/logout - End the session and clear the refresh cookie
+pub async fn logout(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    headers: HeaderMap,
+    body: Option<Json<LogoutRequest>>,
+) -> Result<Response> {
+    let access_token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .ok_or_else(|| AppError::ValidationError("Logout requires a bearer token session".to_string()))?;
+
+    let refresh_token = body
+        .and_then(|Json(req)| req.refresh_token)
+        .or_else(|| refresh_cookie(&headers));
+
+    state
+        .auth_client
+        .logout(access_token, refresh_token.as_deref())
+        .await?;
+    state.auth_layer.cache.remove(Credential::BearerToken(access_token));
+    tracing::info!(user_id = %user.0.id, "User logged out");
+
+    let mut response = StatusCode::NO_CONTENT.into_response();
+    set_refresh_cookie(&mut response, "", 0)?;
+    Ok(response)
+}
+
+/// Refresh token from the request's `Cookie` headers
+fn refresh_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == REFRESH_COOKIE)
+        .map(|(_, value)| value.to_string())
+        .filter(|value| !value.is_empty())
+}
+
+fn set_refresh_cookie(response: &mut Response, value: &str, max_age_secs: u64) -> Result<()> {
+    let cookie = format!(
+        "{}={}; Path=/v1/auth; Max-Age={}; HttpOnly; Secure; SameSite=Strict",
+        REFRESH_COOKIE, value, max_age_secs
+    );
+    let cookie = HeaderValue::from_str(&cookie)
+        .map_err(|_| AppError::Internal("Refresh token is not a valid cookie value".to_string()))?;
+    response.headers_mut().append(header::SET_COOKIE, cookie);
+    Ok(())
+}
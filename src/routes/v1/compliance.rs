@@ -12,6 +12,7 @@ use axum::{
     Json,
 };
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 use chrono::{DateTime, Utc};
 
 use crate::error::AppError;
@@ -20,7 +21,7 @@ use super::AppState;
 
 // ── Types ──
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ComplianceDashboard {
     pub gdpr: GdprStatus,
     pub soc2: Soc2Status,
@@ -28,7 +29,7 @@ pub struct ComplianceDashboard {
     pub timestamp: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct GdprStatus {
     pub data_encryption_at_rest: bool,
     pub data_encryption_in_transit: bool,
@@ -41,7 +42,7 @@ pub struct GdprStatus {
     pub dpo_contact: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct Soc2Status {
     pub security: Soc2Control,
     pub availability: Soc2Control,
@@ -50,7 +51,7 @@ pub struct Soc2Status {
     pub privacy: Soc2Control,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct Soc2Control {
     pub status: String,
     pub controls_implemented: u32,
@@ -58,7 +59,7 @@ pub struct Soc2Control {
     pub last_review: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct AuditSummary {
     pub total_events_24h: u64,
     pub auth_events_24h: u64,
@@ -67,7 +68,7 @@ pub struct AuditSummary {
     pub anomalies_24h: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DataExportResponse {
     pub user_id: String,
     pub export_format: String,
@@ -76,7 +77,7 @@ pub struct DataExportResponse {
     pub estimated_size_bytes: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DataDeletionResponse {
     pub user_id: String,
     pub status: String,
@@ -185,7 +186,7 @@ pub async fn gdpr_data_deletion(
     }))
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct AuditLog {
     pub id: String,
     pub event_type: String,
@@ -196,7 +197,7 @@ pub struct AuditLog {
     pub status: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct AuditLogResponse {
     pub logs: Vec<AuditLog>,
     pub total: usize,
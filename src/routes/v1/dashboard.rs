@@ -1,14 +1,18 @@
 //! Dashboard routes - stats and overview endpoints
 
 use axum::{
-    extract::State,
+    extract::{State, Extension},
+    http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
-use super::AppState;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::ApiResponse;
+use crate::store::{Owner, Stores};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DashboardStats {
     pub repositories: u32,
     pub documents: u32,
@@ -19,26 +23,36 @@ pub struct DashboardStats {
     pub security_score: u32,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
-}
-
 /// Get dashboard statistics
+///
+/// Resource counts are the caller's; the remaining figures are mock values.
 pub async fn get_stats(
-    State(_state): State<AppState>,
-) -> Json<DashboardStats> {
-    // Return mock stats for development
-    Json(DashboardStats {
-        repositories: 3,
-        documents: 12,
-        urls: 5,
-        agents: 2,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<DashboardStats>, (StatusCode, Json<ApiResponse<()>>)> {
+    let owner = Owner::of(&user.0);
+    let counts = tokio::try_join!(
+        stores.repositories.list(&owner),
+        stores.documents.list(&owner, None),
+        stores.urls.list(&owner),
+        stores.agents.list(&owner),
+    );
+    let (repositories, documents, urls, agents) = counts.map_err(|err| {
+        tracing::error!("Dashboard store error: {}", err);
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
+            success: false,
+            message: "Failed to load dashboard statistics".to_string(),
+            data: None,
+        }))
+    })?;
+    
+    Ok(Json(DashboardStats {
+        repositories: repositories.len() as u32,
+        documents: documents.len() as u32,
+        urls: urls.len() as u32,
+        agents: agents.len() as u32,
         connections: 4,
         context_requests: 1247,
         security_score: 98,
-    })
+    }))
 }
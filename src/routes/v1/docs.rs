@@ -0,0 +1,64 @@
+//! API description endpoints
+//!
+//! `/openapi.json` serves the generated OpenAPI document (see
+//! `crate::openapi`); `/docs` renders it with a pinned Swagger UI release
+//! loaded from a CDN and checked against subresource integrity hashes.
+
+use axum::{
+    http::header,
+    response::{Html, IntoResponse},
+    Json,
+};
+
+/// Swagger UI release loaded by the docs page; pinned so the hashes below hold
+const SWAGGER_UI: &str = "https://cdn.jsdelivr.net/npm/swagger-ui-dist@5.17.14";
+
+/// Subresource integrity of the release's `swagger-ui.css`
+const SWAGGER_UI_CSS_INTEGRITY: &str =
+    "sha384-wxLW6kwyHktdDGr6Pv1zgm/VGJh99lfUbzSn6HNHBENZlCN7W602k9VkGdxuFvPn";
+
+/// Subresource integrity of the release's `swagger-ui-bundle.js`
+const SWAGGER_UI_BUNDLE_INTEGRITY: &str =
+    "sha384-wmyclcVGX/WhUkdkATwhaK1X1JtiNrr2EoYJ+diV3vj4v6OC5yCeSu+yW13SYJep";
+
+const DOCS_PAGE: &str = r##"<!doctype html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>ConFuse API</title>
+  <link rel="stylesheet" href="{swagger_ui}/swagger-ui.css" integrity="{css_integrity}" crossorigin="anonymous">
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="{swagger_ui}/swagger-ui-bundle.js" integrity="{bundle_integrity}" crossorigin="anonymous"></script>
+  <script nonce="{nonce}">
+    window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// GET /openapi.json - OpenAPI 3.1 document for every route
+pub async fn openapi_json() -> Json<&'static serde_json::Value> {
+    Json(crate::openapi::document())
+}
+
+/// GET /docs - Interactive API documentation
+///
+/// Sets its own Content-Security-Policy, which `security_headers_middleware`
+/// keeps: the page needs the CDN and its inline script, allowed by nonce.
+pub async fn docs() -> impl IntoResponse {
+    let nonce = uuid::Uuid::new_v4().simple().to_string();
+    let csp = format!(
+        "default-src 'none'; script-src 'nonce-{nonce}' {cdn}; style-src 'unsafe-inline' {cdn}; \
+         img-src 'self' data: {cdn}; connect-src 'self'; base-uri 'none'; frame-ancestors 'none'",
+        nonce = nonce,
+        cdn = "https://cdn.jsdelivr.net",
+    );
+    let page = DOCS_PAGE
+        .replace("{swagger_ui}", SWAGGER_UI)
+        .replace("{css_integrity}", SWAGGER_UI_CSS_INTEGRITY)
+        .replace("{bundle_integrity}", SWAGGER_UI_BUNDLE_INTEGRITY)
+        .replace("{nonce}", &nonce);
+    ([(header::CONTENT_SECURITY_POLICY, csp)], Html(page))
+}
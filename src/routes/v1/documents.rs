@@ -1,62 +1,19 @@
 //! Document management routes
 
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Path, Query, State, Extension},
     http::StatusCode,
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use once_cell::sync::Lazy;
+use schemars::JsonSchema;
 
-use super::AppState;
+use crate::error::AppError;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::{ApiResponse, DocumentRecord};
+use crate::store::{Owner, Stores};
 
-/// In-memory storage for documents (for development)
-static DOC_STORE: Lazy<Arc<RwLock<Vec<DocumentRecord>>>> = Lazy::new(|| {
-    Arc::new(RwLock::new(vec![
-        DocumentRecord {
-            id: "doc-001".to_string(),
-            user_id: "user-rishabh-001".to_string(),
-            name: "API Documentation".to_string(),
-            doc_type: "markdown".to_string(),
-            source: "upload".to_string(),
-            size: "125 KB".to_string(),
-            tags: vec!["api".to_string(), "docs".to_string()],
-            status: "active".to_string(),
-            created_at: "2026-01-20T10:00:00Z".to_string(),
-            updated_at: "2026-01-25T14:30:00Z".to_string(),
-        },
-        DocumentRecord {
-            id: "doc-002".to_string(),
-            user_id: "user-rishabh-001".to_string(),
-            name: "Architecture Overview".to_string(),
-            doc_type: "pdf".to_string(),
-            source: "google_drive".to_string(),
-            size: "2.4 MB".to_string(),
-            tags: vec!["architecture".to_string(), "design".to_string()],
-            status: "active".to_string(),
-            created_at: "2026-01-18T08:00:00Z".to_string(),
-            updated_at: "2026-01-22T11:00:00Z".to_string(),
-        },
-    ]))
-});
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct DocumentRecord {
-    pub id: String,
-    pub user_id: String,
-    pub name: String,
-    pub doc_type: String,
-    pub source: String,
-    pub size: String,
-    pub tags: Vec<String>,
-    pub status: String,
-    pub created_at: String,
-    pub updated_at: String,
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateDocumentRequest {
     pub name: String,
     pub doc_type: String,
@@ -65,58 +22,66 @@ pub struct CreateDocumentRequest {
     pub tags: Option<Vec<String>>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct SearchQuery {
     pub search: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct DocumentListResponse {
     pub data: Vec<DocumentRecord>,
     pub total: usize,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
+type ApiError = (StatusCode, Json<ApiResponse<()>>);
+
+fn not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, Json(ApiResponse {
+        success: false,
+        message: "Document not found".to_string(),
+        data: None,
+    }))
+}
+
+fn store_error(err: AppError) -> ApiError {
+    tracing::error!("Document store error: {}", err);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
+        success: false,
+        message: "Failed to access documents".to_string(),
+        data: None,
+    }))
 }
 
 /// List all documents
 pub async fn list_documents(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Query(query): Query<SearchQuery>,
-) -> Json<ApiResponse<DocumentListResponse>> {
-    let store = DOC_STORE.read().await;
-    
-    let filtered: Vec<DocumentRecord> = if let Some(search) = query.search {
-        store.iter()
-            .filter(|d| d.name.to_lowercase().contains(&search.to_lowercase()))
-            .cloned()
-            .collect()
-    } else {
-        store.clone()
-    };
+) -> Result<Json<ApiResponse<DocumentListResponse>>, ApiError> {
+    let documents = stores.documents
+        .list(&Owner::of(&user.0), query.search.as_deref())
+        .await
+        .map_err(store_error)?;
     
-    let total = filtered.len();
-    Json(ApiResponse {
+    let total = documents.len();
+    Ok(Json(ApiResponse {
         success: true,
         message: "Documents retrieved successfully".to_string(),
-        data: Some(DocumentListResponse { data: filtered, total }),
-    })
+        data: Some(DocumentListResponse { data: documents, total }),
+    }))
 }
 
 /// Create a new document
 pub async fn create_document(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Json(payload): Json<CreateDocumentRequest>,
-) -> (StatusCode, Json<ApiResponse<DocumentRecord>>) {
+) -> Result<(StatusCode, Json<ApiResponse<DocumentRecord>>), ApiError> {
     let now = chrono::Utc::now().to_rfc3339();
     let doc = DocumentRecord {
         id: uuid::Uuid::new_v4().to_string(),
-        user_id: "user-rishabh-001".to_string(),
+        user_id: user.0.id.clone(),
+        workspace_id: user.0.workspace_id.clone(),
         name: payload.name,
         doc_type: payload.doc_type,
         source: payload.source,
@@ -127,42 +92,34 @@ pub async fn create_document(
         updated_at: now,
     };
 
-    let mut store = DOC_STORE.write().await;
-    store.push(doc.clone());
+    stores.documents.insert(&doc).await.map_err(store_error)?;
 
-    (StatusCode::CREATED, Json(ApiResponse {
+    Ok((StatusCode::CREATED, Json(ApiResponse {
         success: true,
         message: "Document created successfully".to_string(),
         data: Some(doc),
-    }))
+    })))
 }
 
 /// Delete a document
 pub async fn delete_document(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let mut store = DOC_STORE.write().await;
-    
-    if let Some(pos) = store.iter().position(|d| d.id == id) {
-        store.remove(pos);
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "Document deleted successfully".to_string(),
-            data: None,
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "Document not found".to_string(),
-            data: None,
-        })))
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    if !stores.documents.delete(&Owner::of(&user.0), &id).await.map_err(store_error)? {
+        return Err(not_found());
     }
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "Document deleted successfully".to_string(),
+        data: None,
+    }))
 }
 
 /// Get document analytics
 pub async fn get_analytics(
-    State(_state): State<AppState>,
+    Extension(_user): Extension<AuthenticatedUser>,
 ) -> Json<ApiResponse<serde_json::Value>> {
     Json(ApiResponse {
         success: true,
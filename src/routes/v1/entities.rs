@@ -5,13 +5,14 @@ use axum::{
     Json,
 };
 use serde::Deserialize;
+use schemars::JsonSchema;
 
 use crate::error::Result;
 use crate::middleware::auth::AuthenticatedUser;
 use crate::models::Entity;
 use super::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct GetNeighborsQuery {
     #[serde(default = "default_hops")]
     pub hops: u32,
@@ -2,11 +2,12 @@
 //!
 //! Provides basic health check, detailed status, readiness/liveness probes, and metrics.
 
-use axum::{extract::State, http::StatusCode, Json};
+use axum::{extract::State, http::{header, StatusCode}, response::IntoResponse, Json};
 use chrono::Utc;
 use std::collections::HashMap;
 
 use crate::error::Result;
+use crate::metrics::METRICS;
 use crate::models::{HealthResponse, ServiceHealth};
 use super::AppState;
 
@@ -60,11 +61,12 @@ pub async fn status_check(State(state): State<AppState>) -> Result<Json<HealthRe
 }
 
 /// GET /metrics - Prometheus metrics endpoint
-pub async fn metrics() -> String {
-    // TODO: Implement actual Prometheus metrics collection
-    format!(
-        "# HELP up Service up status\n# TYPE up gauge\nup 1\n# HELP api_requests_total Total requests\n# TYPE api_requests_total counter\napi_requests_total 0\n"
-    )
+pub async fn metrics(State(state): State<AppState>) -> impl IntoResponse {
+    let body = METRICS.render(
+        &state.response_cache.stats(),
+        &state.circuit_breaker.snapshot_all(),
+    );
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
 }
 
 /// Check all downstream services health
@@ -13,17 +13,30 @@ pub mod documents;
 pub mod agents;
 pub mod processing;
 pub mod compliance;
+pub mod admin;
+pub mod auth;
+pub mod webhook_subscriptions;
+pub mod docs;
 
-use axum::{Router, routing::{get, post, delete, put}};
+use axum::{Router, extract::FromRef, routing::{get, post, delete, put}};
 use std::sync::Arc;
 
 use crate::middleware::auth::{AuthLayer, auth_middleware, optional_auth_middleware};
+use crate::middleware::permissions::{Permission, require_permission};
+use crate::middleware::rate_limit::{RateLimitConfig, rate_limit_middleware};
+use crate::middleware::cache::response_cache_middleware;
+use crate::middleware::service_identity::require_service_identity;
+use crate::store::Stores;
 use super::webhooks;
 
 /// Application state shared across routes
 #[derive(Clone)]
 pub struct AppState {
     pub config: Arc<crate::Config>,
+    /// Shared Postgres connection pool
+    pub db: sqlx::PgPool,
+    /// Persistence for agents, documents, repositories, URLs and the webhook inbox
+    pub stores: crate::store::Stores,
     pub auth_client: Arc<crate::clients::AuthClient>,
     pub data_connector_client: Arc<crate::clients::DataConnectorClient>,
     pub relation_graph_client: Arc<crate::clients::RelationGraphClient>,
@@ -31,6 +44,8 @@ pub struct AppState {
     pub unified_processor_client: Arc<crate::clients::UnifiedProcessorClient>,
     pub enhanced_graph_client: Arc<crate::clients::EnhancedGraphClient>,
     pub auth_layer: AuthLayer,
+    /// Rate limiting configuration and counter store
+    pub rate_limit: RateLimitConfig,
     /// Kafka event producer for event-driven operations (optional for graceful fallback)
     pub event_producer: Option<Arc<confuse_common::events::producer::EventProducer>>,
     /// Circuit breaker registry for downstream service calls
@@ -39,10 +54,86 @@ pub struct AppState {
     pub response_cache: Arc<crate::middleware::ResponseCache>,
     /// gRPC clients (required — gRPC-only inter-service communication)
     pub grpc_clients: crate::clients::GrpcClients,
+    /// Notifies outbound webhook subscriptions of sync and source events
+    pub webhook_dispatcher: crate::webhook_dispatch::WebhookDispatcher,
+}
+
+impl FromRef<AppState> for Stores {
+    fn from_ref(state: &AppState) -> Self {
+        state.stores.clone()
+    }
+}
+
+/// `/api/*` management routes (URLs, dashboard, repositories, documents, agents)
+///
+/// Handlers read the caller from the `AuthenticatedUser` extension and only
+/// see that user's records in their workspace, so these routes must be
+/// mounted behind `auth_middleware`. Generic over the state so they can be
+/// served from bare `Stores`.
+pub fn api_routes<S>() -> Router<S>
+where
+    S: Clone + Send + Sync + 'static,
+    Stores: FromRef<S>,
+{
+    // Reads are limited to the caller's own records; writes need a permission
+    let require = |permission: Permission| axum::middleware::from_fn_with_state(
+        permission,
+        require_permission,
+    );
+
+    Router::new()
+        // URLs
+        .route("/api/urls", get(urls::list_urls))
+        .route("/api/urls", post(urls::create_url).layer(require(Permission::ResourcesWrite)))
+        .route("/api/urls/:id", get(urls::get_url))
+        .route("/api/urls/:id", delete(urls::delete_url).layer(require(Permission::ResourcesWrite)))
+        // Dashboard
+        .route("/api/dashboard/stats", get(dashboard::get_stats))
+        // Repositories
+        .route("/api/repositories", get(repositories::list_repositories))
+        .route("/api/repositories", post(repositories::create_repository).layer(require(Permission::ResourcesWrite)))
+        .route("/api/repositories/:id", get(repositories::get_repository))
+        .route("/api/repositories/:id", delete(repositories::delete_repository).layer(require(Permission::ResourcesWrite)))
+        // Documents
+        .route("/api/documents", get(documents::list_documents))
+        .route("/api/documents", post(documents::create_document).layer(require(Permission::ResourcesWrite)))
+        .route("/api/documents/:id", delete(documents::delete_document).layer(require(Permission::ResourcesWrite)))
+        .route("/api/documents/analytics", get(documents::get_analytics))
+        // Agents
+        .route("/api/agents", get(agents::list_agents))
+        .route("/api/agents", post(agents::create_agent).layer(require(Permission::ResourcesWrite)))
+        .route("/api/agents/:id", get(agents::get_agent))
+        .route("/api/agents/:id", put(agents::update_agent).layer(require(Permission::ResourcesWrite)))
+        .route("/api/agents/:id", delete(agents::delete_agent).layer(require(Permission::ResourcesWrite)))
+        .route("/api/agents/:id/test", post(agents::test_agent))
+        .route("/api/agents/:id/invoke", post(agents::invoke_agent).layer(require(Permission::AgentsInvoke)))
+        .route("/api/agents/:id/context", get(agents::get_agent_context))
 }
 
 /// Create the V1 router
 pub fn v1_router(state: AppState) -> Router {
+    // Rate limiting is mounted per route group so that, on protected routes,
+    // it runs after auth and can key counters by user instead of IP
+    let rate_limit = || axum::middleware::from_fn_with_state(
+        state.rate_limit.clone(),
+        rate_limit_middleware,
+    );
+    
+    // Routes declare the permission they need; mount inside auth
+    let require = |permission: Permission| axum::middleware::from_fn_with_state(
+        permission,
+        require_permission,
+    );
+    
+    // Cached routes check their permission before the response cache, so a
+    // cached response is only served to callers allowed to run the handler
+    let cached = |permission: Permission| tower::ServiceBuilder::new()
+        .layer(require(permission))
+        .layer(axum::middleware::from_fn_with_state(
+            state.response_cache.clone(),
+            response_cache_middleware,
+        ));
+    
     // Public routes (no auth required)
     let public_routes = Router::new()
         .route("/health", get(health::health_check))
@@ -50,102 +141,129 @@ pub fn v1_router(state: AppState) -> Router {
         .route("/health/ready", get(health::readiness))
         .route("/health/live", get(health::liveness))
         .route("/status", get(health::status_check))
-        .route("/metrics", get(health::metrics));
+        .route("/metrics", get(health::metrics))
+        .route("/openapi.json", get(docs::openapi_json))
+        .route("/docs", get(docs::docs))
+        .layer(rate_limit());
     
     // Protected routes (auth required)
     let protected_routes = Router::new()
         // Sources
-        .route("/sources", get(sources::list_sources))
-        .route("/sources", post(sources::create_source))
-        .route("/sources/:id", get(sources::get_source))
-        .route("/sources/:id", delete(sources::delete_source))
+        .route("/sources", get(sources::list_sources).layer(cached(Permission::SourcesRead)))
+        .route("/sources", post(sources::create_source).layer(cached(Permission::SourcesWrite)))
+        .route("/sources/:id", get(sources::get_source).layer(cached(Permission::SourcesRead)))
+        .route("/sources/:id", delete(sources::delete_source).layer(cached(Permission::SourcesWrite)))
         // Search
-        .route("/search", post(search::hybrid_search))
-        .route("/search/vector", post(search::vector_search))
-        .route("/search/graph", post(search::graph_search))
+        .route("/search", post(search::hybrid_search).layer(cached(Permission::SearchRead)))
+        .route("/search/vector", post(search::vector_search).layer(cached(Permission::SearchRead)))
+        .route("/search/graph", post(search::graph_search).layer(cached(Permission::SearchRead)))
         // Entities
-        .route("/entities/:id", get(entities::get_entity))
-        .route("/entities/:id/neighbors", get(entities::get_neighbors))
+        .route("/entities/:id", get(entities::get_entity).layer(cached(Permission::SearchRead)))
+        .route("/entities/:id/neighbors", get(entities::get_neighbors).layer(cached(Permission::SearchRead)))
         // Sync
-        .route("/sync/:source_id", post(sync::trigger_sync))
-        .route("/sync/:job_id/status", get(sync::get_sync_status))
+        .route("/sync/:source_id", post(sync::trigger_sync).layer(cached(Permission::SourcesWrite)))
+        .route("/sync/:job_id/status", get(sync::get_sync_status).layer(cached(Permission::SourcesRead)))
         // MCP
-        .route("/mcp/search", post(mcp::mcp_search))
-        .route("/mcp/context", post(mcp::mcp_context))
-        .route("/mcp/capabilities", get(mcp::get_capabilities))
+        .route("/mcp/search", post(mcp::mcp_search).layer(cached(Permission::SearchRead)))
+        .route("/mcp/context", post(mcp::mcp_context).layer(cached(Permission::SearchRead)))
+        .route("/mcp/capabilities", get(mcp::get_capabilities).layer(cached(Permission::SearchRead)))
         // Processing (unified-processor integration)
-        .route("/process", post(processing::process_files))
-        .route("/chunk", post(processing::chunk_content))
-        .route("/embed", post(processing::embed_text))
-        .route("/embed/batch", post(processing::embed_batch))
-        .route("/search/semantic", post(processing::semantic_search))
-        .route("/processor/status", get(processing::get_processor_status));
-    
-    // URL routes (public for now to simplify development)
-    let url_routes = Router::new()
-        .route("/api/urls", get(urls::list_urls))
-        .route("/api/urls", post(urls::create_url))
-        .route("/api/urls/:id", get(urls::get_url))
-        .route("/api/urls/:id", delete(urls::delete_url));
-    
-    // Dashboard routes
-    let dashboard_routes = Router::new()
-        .route("/api/dashboard/stats", get(dashboard::get_stats));
-    
-    // Repository routes
-    let repository_routes = Router::new()
-        .route("/api/repositories", get(repositories::list_repositories))
-        .route("/api/repositories", post(repositories::create_repository))
-        .route("/api/repositories/:id", get(repositories::get_repository))
-        .route("/api/repositories/:id", delete(repositories::delete_repository));
-    
-    // Document routes
-    let document_routes = Router::new()
-        .route("/api/documents", get(documents::list_documents))
-        .route("/api/documents", post(documents::create_document))
-        .route("/api/documents/:id", delete(documents::delete_document))
-        .route("/api/documents/analytics", get(documents::get_analytics));
+        .route("/process", post(processing::process_files).layer(cached(Permission::ProcessingWrite)))
+        .route("/chunk", post(processing::chunk_content).layer(cached(Permission::ProcessingWrite)))
+        .route("/embed", post(processing::embed_text).layer(cached(Permission::ProcessingWrite)))
+        .route("/embed/batch", post(processing::embed_batch).layer(cached(Permission::ProcessingWrite)))
+        .route("/search/semantic", post(processing::semantic_search).layer(cached(Permission::SearchRead)))
+        .route("/processor/status", get(processing::get_processor_status).layer(cached(Permission::ProcessingRead)));
     
-    // Agent routes
-    let agent_routes = Router::new()
-        .route("/api/agents", get(agents::list_agents))
-        .route("/api/agents", post(agents::create_agent))
-        .route("/api/agents/:id", get(agents::get_agent))
-        .route("/api/agents/:id", put(agents::update_agent))
-        .route("/api/agents/:id", delete(agents::delete_agent))
-        .route("/api/agents/:id/test", post(agents::test_agent))
-        .route("/api/agents/:id/invoke", post(agents::invoke_agent))
-        .route("/api/agents/:id/context", get(agents::get_agent_context));
+    // Management routes for the web app (auth -> rate limit -> handler)
+    let api_routes = api_routes()
+        .layer(rate_limit())
+        .layer(axum::middleware::from_fn_with_state(
+            state.auth_layer.clone(),
+            auth_middleware,
+        ));
     
     // Compliance / Governance routes
     let compliance_routes = Router::new()
-        .route("/compliance/dashboard", get(compliance::compliance_dashboard))
-        .route("/compliance/audit-logs", get(compliance::audit_logs))
-        .route("/compliance/gdpr/export", post(compliance::gdpr_data_export))
-        .route("/compliance/gdpr/delete", post(compliance::gdpr_data_deletion));
+        .route("/compliance/dashboard", get(compliance::compliance_dashboard).layer(cached(Permission::ComplianceRead)))
+        .route("/compliance/audit-logs", get(compliance::audit_logs).layer(cached(Permission::ComplianceRead)))
+        .route("/compliance/gdpr/export", post(compliance::gdpr_data_export).layer(cached(Permission::AccountData)))
+        .route("/compliance/gdpr/delete", post(compliance::gdpr_data_deletion).layer(cached(Permission::AccountData)));
     
-    // Apply auth middleware to protected routes
+    // Apply auth middleware to protected routes (auth -> rate limit -> permission -> cache -> handler)
     let protected_routes = protected_routes
         .merge(compliance_routes)
+        .layer(rate_limit())
         .layer(axum::middleware::from_fn_with_state(
             state.auth_layer.clone(),
             auth_middleware,
         ));
     
+    // Admin routes (auth -> rate limit -> admin permission -> handler; never cached)
+    let admin_routes = Router::new()
+        .route("/admin/circuit-breakers", get(admin::list_circuit_breakers))
+        .route("/admin/circuit-breakers/:service", get(admin::get_circuit_breaker))
+        .route("/admin/circuit-breakers/:service/open", post(admin::force_open))
+        .route("/admin/circuit-breakers/:service/close", post(admin::force_close))
+        .route("/admin/circuit-breakers/:service/reset", post(admin::reset))
+        .route("/admin/auth-cache/invalidate", post(admin::invalidate_auth_cache))
+        .route("/admin/webhooks/deliveries", get(admin::list_webhook_deliveries))
+        .route("/admin/webhooks/deliveries/:id", get(admin::get_webhook_delivery))
+        .route("/admin/webhooks/deliveries/:id/redeliver", post(admin::redeliver_webhook))
+        .layer(require(Permission::Admin))
+        .layer(rate_limit())
+        .layer(axum::middleware::from_fn_with_state(
+            state.auth_layer.clone(),
+            auth_middleware,
+        ));
+    
+    // Session routes: refresh is authenticated by its refresh token, the
+    // rest by auth_middleware (never cached)
+    let session_routes = Router::new()
+        .route("/auth/me", get(auth::me))
+        .route("/auth/logout", post(auth::logout))
+        .layer(rate_limit())
+        .layer(axum::middleware::from_fn_with_state(
+            state.auth_layer.clone(),
+            auth_middleware,
+        ))
+        .merge(Router::new()
+            .route("/auth/refresh", post(auth::refresh))
+            .layer(rate_limit()));
+    
+    // Outbound webhook subscriptions (auth -> rate limit -> permission -> handler; never cached)
+    let subscription_routes = Router::new()
+        .route("/webhook-subscriptions", get(webhook_subscriptions::list_subscriptions))
+        .route("/webhook-subscriptions", post(webhook_subscriptions::create_subscription))
+        .route("/webhook-subscriptions/:id", get(webhook_subscriptions::get_subscription))
+        .route("/webhook-subscriptions/:id", put(webhook_subscriptions::update_subscription))
+        .route("/webhook-subscriptions/:id", delete(webhook_subscriptions::delete_subscription))
+        .route("/webhook-subscriptions/:id/deliveries", get(webhook_subscriptions::list_deliveries))
+        .layer(require(Permission::WebhooksManage))
+        .layer(rate_limit())
+        .layer(axum::middleware::from_fn_with_state(
+            state.auth_layer.clone(),
+            auth_middleware,
+        ));
+    
+    // Internal routes for other services (signed service identity instead of auth)
+    let internal_routes = Router::new()
+        .route("/internal/auth-cache/invalidate", post(admin::service_invalidate_auth_cache))
+        .layer(axum::middleware::from_fn(require_service_identity))
+        .layer(rate_limit());
+    
     // Webhook routes (signature verification instead of auth)
     let webhook_routes = Router::new()
         .route("/webhooks/github", post(webhooks::github_webhook))
-        .route("/webhooks/gitlab", post(webhooks::gitlab_webhook));
+        .route("/webhooks/gitlab", post(webhooks::gitlab_webhook))
+        .route("/webhooks/bitbucket", post(webhooks::bitbucket_webhook))
+        .layer(rate_limit());
     
     // Combine all routes
     Router::new()
         .merge(public_routes)
-        .nest("/v1", protected_routes)
-        .merge(url_routes)
-        .merge(dashboard_routes)
-        .merge(repository_routes)
-        .merge(document_routes)
-        .merge(agent_routes)
+        .nest("/v1", protected_routes.merge(admin_routes).merge(session_routes).merge(subscription_routes).merge(internal_routes))
+        .merge(api_routes)
         .merge(webhook_routes)
         .with_state(state)
 }
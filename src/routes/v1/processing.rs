@@ -7,6 +7,7 @@
 
 use axum::{extract::State, Json};
 use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
 
 use crate::error::Result;
 use crate::clients::unified_processor_client as upc;
@@ -16,7 +17,7 @@ use super::AppState;
 // Request/Response Types
 // ==============================================================================
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ProcessRequest {
     pub source_id: String,
     #[serde(default)]
@@ -30,7 +31,7 @@ pub struct ProcessRequest {
 
 fn default_source_type() -> String { "local".to_string() }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ChunkRequest {
     pub content: String,
     #[serde(default = "default_language")]
@@ -45,7 +46,7 @@ fn default_language() -> String { "python".to_string() }
 fn default_chunk_size() -> u32 { 1000 }
 fn default_chunk_overlap() -> u32 { 300 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct EmbedRequest {
     pub text: String,
     #[serde(default = "default_cache")]
@@ -54,14 +55,15 @@ pub struct EmbedRequest {
 
 fn default_cache() -> bool { true }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct BatchEmbedRequest {
     pub texts: Vec<String>,
     #[serde(default = "default_cache")]
     pub cache: bool,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
+#[schemars(rename = "SemanticSearchRequest")]
 pub struct SearchRequest {
     pub query: String,
     #[serde(default = "default_top_k")]
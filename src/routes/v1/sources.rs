@@ -5,13 +5,15 @@ use axum::{
     Json,
 };
 use serde::Deserialize;
+use schemars::JsonSchema;
 
 use crate::error::{AppError, Result};
 use crate::middleware::auth::AuthenticatedUser;
-use crate::models::{Source, SourceCreateRequest, SourcesListResponse};
+use crate::models::{Source, SourceCreateRequest, SourcesListResponse, EVENT_SOURCE_DELETED};
+use crate::store::Owner;
 use super::AppState;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ListSourcesQuery {
     pub limit: Option<u32>,
     pub offset: Option<u32>,
@@ -53,6 +55,8 @@ pub async fn create_source(
         .create_source(&user.0.id, &request)
         .await?;
     
+    state.response_cache.invalidate_sources(&user.0.id, user.0.workspace_id.as_deref());
+    
     Ok(Json(source))
 }
 
@@ -66,6 +70,11 @@ pub async fn delete_source(
         .delete_source(&user.0.id, &source_id)
         .await?;
     
+    state.response_cache.invalidate_sources(&user.0.id, user.0.workspace_id.as_deref());
+    state.webhook_dispatcher
+        .emit_or_log(&Owner::of(&user.0), EVENT_SOURCE_DELETED, serde_json::json!({ "source_id": source_id }))
+        .await;
+    
     Ok(Json(serde_json::json!({
         "success": true,
         "message": "Source deleted"
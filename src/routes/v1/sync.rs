@@ -9,6 +9,8 @@ use axum::{
 
 use crate::error::{AppError, Result};
 use crate::middleware::auth::AuthenticatedUser;
+use crate::metrics::METRICS;
+use crate::store::Owner;
 use confuse_common::events::{
     SourceSyncRequestedEvent, 
     SourceType as EventSourceType, 
@@ -29,7 +31,7 @@ use confuse_common::events::topics;
 // `SyncRequestResponse` was likely a derived struct.
 // I'll define `SyncRequestResponse` in this file to unblock migration.
 
-#[derive(serde::Serialize)]
+#[derive(serde::Serialize, schemars::JsonSchema)]
 pub struct SyncRequestResponse {
     pub correlation_id: Option<String>,
     pub event_id: String,
@@ -52,7 +54,7 @@ use crate::models::{JobStatusResponse, SourceType, Source};
 use super::AppState;
 
 /// Map model SourceType to event SourceType
-fn map_source_type(source_type: &SourceType) -> EventSourceType {
+pub(crate) fn map_source_type(source_type: &SourceType) -> EventSourceType {
     match source_type {
         SourceType::Github => EventSourceType::Github,
         SourceType::Gitlab => EventSourceType::Gitlab,
@@ -64,7 +66,7 @@ fn map_source_type(source_type: &SourceType) -> EventSourceType {
 }
 
 /// Extract URL from source configuration
-fn extract_source_url(source: &Source) -> String {
+pub(crate) fn extract_source_url(source: &Source) -> String {
     if let Some(ref metadata) = source.metadata {
         if let Some(url) = metadata.get("url") {
             if let Some(s) = url.as_str() {
@@ -94,15 +96,17 @@ pub async fn trigger_sync(
         let event_source_type = map_source_type(&source.source_type);
         let source_url = extract_source_url(&source);
         
-        let event = SourceSyncRequestedEvent::new(
+        let mut event = SourceSyncRequestedEvent::new(
             source_id.clone(),
             event_source_type,
             source_url,
         ).with_user(user.0.id.clone());
+        crate::kafka::attach_request_context(&mut event.headers);
         
         // Changed: parameter order (event first) and removed key (None)
-        producer.publish_to_topic(&event, topics::Topics::SOURCE_SYNC_REQUESTED).await
-            .map_err(|e| AppError::Internal(format!("Event publish failed: {}", e)))?;
+        let published = producer.publish_to_topic(&event, topics::Topics::SOURCE_SYNC_REQUESTED).await;
+        METRICS.record_kafka_publish(topics::Topics::SOURCE_SYNC_REQUESTED, published.is_ok());
+        published.map_err(|e| AppError::Internal(format!("Event publish failed: {}", e)))?;
         
         tracing::info!(
             "Published sync event: source_id={}, event_id={}",
@@ -110,6 +114,15 @@ pub async fn trigger_sync(
             event.headers.event_id
         );
         
+        state.response_cache.invalidate_sources(&user.0.id, user.0.workspace_id.as_deref());
+        
+        // No job ID comes back over Kafka; watch the source's status instead
+        state.webhook_dispatcher.watch_source_sync(
+            state.data_connector_client.clone(),
+            Owner::of(&user.0),
+            source,
+        ).await;
+        
         return Ok(Json(SyncRequestResponse::from(&event)));
     }
     
@@ -119,6 +132,16 @@ pub async fn trigger_sync(
         .sync_source(&source_id)
         .await?;
     
+    state.response_cache.invalidate_sources(&user.0.id, user.0.workspace_id.as_deref());
+    
+    // Notify webhook subscriptions when the job finishes
+    state.webhook_dispatcher.watch_sync_job(
+        state.data_connector_client.clone(),
+        Owner::of(&user.0),
+        source_id,
+        job.job_id.clone(),
+    ).await;
+    
     Ok(Json(SyncRequestResponse {
         correlation_id: Some(job.job_id.clone()),
         event_id: job.job_id,
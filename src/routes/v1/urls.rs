@@ -1,35 +1,19 @@
 //! URL management routes
 
 use axum::{
-    extract::{Path, State},
+    extract::{Path, State, Extension},
     http::StatusCode,
     Json,
 };
-use serde::{Deserialize, Serialize};
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use once_cell::sync::Lazy;
+use serde::Deserialize;
+use schemars::JsonSchema;
 
-use super::AppState;
+use crate::error::AppError;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::{ApiResponse, UrlRecord};
+use crate::store::{Owner, Stores};
 
-/// In-memory storage for URLs (for development)
-static URL_STORE: Lazy<Arc<RwLock<Vec<UrlRecord>>>> = Lazy::new(|| {
-    Arc::new(RwLock::new(Vec::new()))
-});
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UrlRecord {
-    pub id: String,
-    pub url: String,
-    pub title: String,
-    pub description: Option<String>,
-    pub tags: Vec<String>,
-    pub status: String,
-    pub created_at: String,
-    pub updated_at: String,
-}
-
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CreateUrlRequest {
     pub url: String,
     pub title: Option<String>,
@@ -37,34 +21,49 @@ pub struct CreateUrlRequest {
     pub tags: Option<Vec<String>>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ApiResponse<T> {
-    pub success: bool,
-    pub message: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub data: Option<T>,
+type ApiError = (StatusCode, Json<ApiResponse<()>>);
+
+fn not_found() -> ApiError {
+    (StatusCode::NOT_FOUND, Json(ApiResponse {
+        success: false,
+        message: "URL not found".to_string(),
+        data: None,
+    }))
+}
+
+fn store_error(err: AppError) -> ApiError {
+    tracing::error!("URL store error: {}", err);
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(ApiResponse {
+        success: false,
+        message: "Failed to access URLs".to_string(),
+        data: None,
+    }))
 }
 
 /// List all URLs
 pub async fn list_urls(
-    State(_state): State<AppState>,
-) -> Json<ApiResponse<Vec<UrlRecord>>> {
-    let store = URL_STORE.read().await;
-    Json(ApiResponse {
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<ApiResponse<Vec<UrlRecord>>>, ApiError> {
+    let urls = stores.urls.list(&Owner::of(&user.0)).await.map_err(store_error)?;
+    Ok(Json(ApiResponse {
         success: true,
         message: "URLs retrieved successfully".to_string(),
-        data: Some(store.clone()),
-    })
+        data: Some(urls),
+    }))
 }
 
 /// Create a new URL
 pub async fn create_url(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Json(payload): Json<CreateUrlRequest>,
-) -> (StatusCode, Json<ApiResponse<UrlRecord>>) {
+) -> Result<(StatusCode, Json<ApiResponse<UrlRecord>>), ApiError> {
     let now = chrono::Utc::now().to_rfc3339();
     let url_record = UrlRecord {
         id: uuid::Uuid::new_v4().to_string(),
+        user_id: user.0.id.clone(),
+        workspace_id: user.0.workspace_id.clone(),
         url: payload.url.clone(),
         title: payload.title.unwrap_or_else(|| payload.url.clone()),
         description: payload.description,
@@ -74,57 +73,45 @@ pub async fn create_url(
         updated_at: now,
     };
 
-    let mut store = URL_STORE.write().await;
-    store.push(url_record.clone());
+    stores.urls.insert(&url_record).await.map_err(store_error)?;
 
-    (StatusCode::CREATED, Json(ApiResponse {
+    Ok((StatusCode::CREATED, Json(ApiResponse {
         success: true,
         message: "URL created successfully".to_string(),
         data: Some(url_record),
-    }))
+    })))
 }
 
 /// Get a specific URL by ID
 pub async fn get_url(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<UrlRecord>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let store = URL_STORE.read().await;
-    
-    if let Some(url) = store.iter().find(|u| u.id == id) {
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "URL retrieved successfully".to_string(),
-            data: Some(url.clone()),
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "URL not found".to_string(),
-            data: None,
-        })))
-    }
+) -> Result<Json<ApiResponse<UrlRecord>>, ApiError> {
+    let url = stores.urls
+        .get(&Owner::of(&user.0), &id)
+        .await
+        .map_err(store_error)?
+        .ok_or_else(not_found)?;
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "URL retrieved successfully".to_string(),
+        data: Some(url),
+    }))
 }
 
 /// Delete a URL by ID
 pub async fn delete_url(
-    State(_state): State<AppState>,
+    State(stores): State<Stores>,
+    Extension(user): Extension<AuthenticatedUser>,
     Path(id): Path<String>,
-) -> Result<Json<ApiResponse<()>>, (StatusCode, Json<ApiResponse<()>>)> {
-    let mut store = URL_STORE.write().await;
-    
-    if let Some(pos) = store.iter().position(|u| u.id == id) {
-        store.remove(pos);
-        Ok(Json(ApiResponse {
-            success: true,
-            message: "URL deleted successfully".to_string(),
-            data: None,
-        }))
-    } else {
-        Err((StatusCode::NOT_FOUND, Json(ApiResponse {
-            success: false,
-            message: "URL not found".to_string(),
-            data: None,
-        })))
+) -> Result<Json<ApiResponse<()>>, ApiError> {
+    if !stores.urls.delete(&Owner::of(&user.0), &id).await.map_err(store_error)? {
+        return Err(not_found());
     }
+    Ok(Json(ApiResponse {
+        success: true,
+        message: "URL deleted successfully".to_string(),
+        data: None,
+    }))
 }
@@ -0,0 +1,243 @@
+//! Outbound webhook subscriptions
+//!
+//! Users register endpoints to be notified of `sync.completed`,
+//! `sync.failed` and `source.deleted` events in their workspace. Requests
+//! are signed with the subscription's secret, which is returned only when
+//! the subscription is created (see `webhook_dispatch`).
+
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+
+use crate::error::{AppError, Result};
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::{SubscriptionDelivery, WebhookSubscription, SUBSCRIPTION_EVENT_TYPES};
+use crate::store::Owner;
+use super::AppState;
+
+/// Shortest secret a caller may choose
+const MIN_SECRET_LEN: usize = 16;
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateSubscriptionRequest {
+    pub url: String,
+    pub event_types: Vec<String>,
+    /// Generated when absent
+    #[serde(default)]
+    pub secret: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UpdateSubscriptionRequest {
+    pub url: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    /// Rotate the signing secret
+    pub secret: Option<String>,
+    /// Re-enabling clears the failure count and resumes queued deliveries
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct CreatedSubscriptionResponse {
+    #[serde(flatten)]
+    pub subscription: WebhookSubscription,
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SubscriptionsResponse {
+    pub subscriptions: Vec<WebhookSubscription>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct DeliveriesQuery {
+    #[serde(default = "default_deliveries_limit")]
+    pub limit: usize,
+}
+
+fn default_deliveries_limit() -> usize {
+    50
+}
+
+/// Most deliveries returned by one listing
+const MAX_DELIVERIES_LIMIT: usize = 500;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DeliveriesResponse {
+    pub deliveries: Vec<SubscriptionDelivery>,
+}
+
+/// GET /v1/webhook-subscriptions - List the caller's subscriptions
+pub async fn list_subscriptions(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+) -> Result<Json<SubscriptionsResponse>> {
+    let subscriptions = state.stores.subscriptions.list(&Owner::of(&user.0)).await?;
+    Ok(Json(SubscriptionsResponse { subscriptions }))
+}
+
+/// POST /v1/webhook-subscriptions - Subscribe an endpoint to events
+pub async fn create_subscription(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(req): Json<CreateSubscriptionRequest>,
+) -> Result<(StatusCode, Json<CreatedSubscriptionResponse>)> {
+    state.webhook_dispatcher.validate_target(&req.url)?;
+    let event_types = validate_event_types(req.event_types)?;
+    let secret = match req.secret {
+        Some(secret) => validate_secret(secret)?,
+        None => generate_secret(),
+    };
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let subscription = WebhookSubscription {
+        id: uuid::Uuid::new_v4().to_string(),
+        user_id: user.0.id.clone(),
+        workspace_id: user.0.workspace_id.clone(),
+        url: req.url,
+        event_types,
+        secret: secret.clone(),
+        enabled: true,
+        consecutive_failures: 0,
+        disabled_reason: None,
+        created_at: now.clone(),
+        updated_at: now,
+    };
+    state.stores.subscriptions.insert(&subscription).await?;
+    tracing::info!(
+        id = %subscription.id,
+        user_id = %user.0.id,
+        url = %subscription.url,
+        event_types = ?subscription.event_types,
+        "Webhook subscription created"
+    );
+
+    Ok((StatusCode::CREATED, Json(CreatedSubscriptionResponse { subscription, secret })))
+}
+
+/// GET /v1/webhook-subscriptions/:id - Get one subscription
+pub async fn get_subscription(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<String>,
+) -> Result<Json<WebhookSubscription>> {
+    find(&state, &Owner::of(&user.0), &id).await.map(Json)
+}
+
+/// PUT /v1/webhook-subscriptions/:id - Change, rotate the secret of,
+/// enable or disable a subscription
+pub async fn update_subscription(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<String>,
+    Json(req): Json<UpdateSubscriptionRequest>,
+) -> Result<Json<WebhookSubscription>> {
+    let owner = Owner::of(&user.0);
+    let mut subscription = find(&state, &owner, &id).await?;
+
+    if let Some(url) = req.url {
+        state.webhook_dispatcher.validate_target(&url)?;
+        subscription.url = url;
+    }
+    if let Some(event_types) = req.event_types {
+        subscription.event_types = validate_event_types(event_types)?;
+    }
+    if let Some(secret) = req.secret {
+        subscription.secret = validate_secret(secret)?;
+    }
+    subscription.updated_at = chrono::Utc::now().to_rfc3339();
+
+    // The failure count and enabled flag belong to the dispatcher unless the
+    // request sets `enabled`, so they are not written back from this read
+    if !state.stores.subscriptions.update(&owner, &subscription).await? {
+        return Err(not_found(&id));
+    }
+    if let Some(enabled) = req.enabled {
+        if !state.stores.subscriptions.set_enabled(&owner, &id, enabled).await? {
+            return Err(not_found(&id));
+        }
+    }
+    Ok(Json(find(&state, &owner, &id).await?))
+}
+
+/// DELETE /v1/webhook-subscriptions/:id - Remove a subscription and its delivery log
+pub async fn delete_subscription(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<String>,
+) -> Result<StatusCode> {
+    if !state.stores.subscriptions.delete(&Owner::of(&user.0), &id).await? {
+        return Err(not_found(&id));
+    }
+    tracing::info!(id = %id, user_id = %user.0.id, "Webhook subscription deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// GET /v1/webhook-subscriptions/:id/deliveries - The subscription's delivery log, newest first
+pub async fn list_deliveries(
+    State(state): State<AppState>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<String>,
+    Query(query): Query<DeliveriesQuery>,
+) -> Result<Json<DeliveriesResponse>> {
+    let subscription = find(&state, &Owner::of(&user.0), &id).await?;
+    let deliveries = state
+        .stores
+        .subscriptions
+        .deliveries(&subscription.id, query.limit.clamp(1, MAX_DELIVERIES_LIMIT))
+        .await?;
+    Ok(Json(DeliveriesResponse { deliveries }))
+}
+
+async fn find(state: &AppState, owner: &Owner, id: &str) -> Result<WebhookSubscription> {
+    state
+        .stores
+        .subscriptions
+        .get(owner, id)
+        .await?
+        .ok_or_else(|| not_found(id))
+}
+
+fn not_found(id: &str) -> AppError {
+    AppError::NotFound(format!("No webhook subscription '{}'", id))
+}
+
+fn validate_event_types(mut event_types: Vec<String>) -> Result<Vec<String>> {
+    if event_types.is_empty() {
+        return Err(AppError::ValidationError("event_types must not be empty".to_string()));
+    }
+    if let Some(unknown) = event_types
+        .iter()
+        .find(|t| !SUBSCRIPTION_EVENT_TYPES.contains(&t.as_str()))
+    {
+        return Err(AppError::ValidationError(format!(
+            "Unknown event type '{}'; expected one of {}",
+            unknown,
+            SUBSCRIPTION_EVENT_TYPES.join(", ")
+        )));
+    }
+    event_types.sort();
+    event_types.dedup();
+    Ok(event_types)
+}
+
+fn validate_secret(secret: String) -> Result<String> {
+    if secret.len() < MIN_SECRET_LEN {
+        return Err(AppError::ValidationError(format!(
+            "secret must be at least {} characters",
+            MIN_SECRET_LEN
+        )));
+    }
+    Ok(secret)
+}
+
+/// A random signing secret (244 random bits from two v4 UUIDs)
+fn generate_secret() -> String {
+    let a = uuid::Uuid::new_v4();
+    let b = uuid::Uuid::new_v4();
+    format!("whsec_{}{}", hex::encode(a.as_bytes()), hex::encode(b.as_bytes()))
+}
@@ -1,76 +1,522 @@
 //! Webhook endpoints for receiving events from external services
+//!
+//! Deliveries are authenticated against the raw request body before they are
+//! accepted:
+//! - GitHub: `X-Hub-Signature-256: sha256=<hex HMAC-SHA256>`
+//! - Bitbucket: `X-Hub-Signature: sha256=<hex HMAC-SHA256>`
+//! - GitLab: `X-Gitlab-Token` equal to the secret (GitLab sends a shared
+//!   token rather than a signature)
+//!
+//! The secret belongs to the source: the repository named in the payload is
+//! looked up in data-connector and the `webhook_secret` metadata of each
+//! source indexing it is tried. Sources without one are checked against the
+//! provider-wide secret (`GITHUB_WEBHOOK_SECRET`, `GITLAB_WEBHOOK_TOKEN`,
+//! `BITBUCKET_WEBHOOK_SECRET`). A delivery is only verified for the sources
+//! whose secret signed it, and those are recorded with it, so registering a
+//! source with a secret of one's own does not vouch for anyone else's. Forged
+//! or unsigned deliveries, and deliveries with no secret to check against,
+//! are rejected with 401.
+//!
+//! Verified deliveries are recorded in the durable webhook inbox and
+//! acknowledged with 202 straight away; `webhook_inbox::WebhookWorker`
+//! forwards them to data-connector. Providers retry with the same delivery
+//! ID, so a delivery already in the inbox is acknowledged without being
+//! recorded again.
 
 use axum::{
-    extract::{State, Path},
-    http::HeaderMap,
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
     Json,
 };
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use schemars::JsonSchema;
+use sha2::{Digest, Sha256};
 
-use crate::error::Result;
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::metrics::METRICS;
+use crate::models::{Source, WebhookDelivery, DELIVERY_PENDING};
+use crate::webhook_sync::normalize_repository_url;
 use super::v1::AppState;
 
+/// Source metadata key holding the secret set on the source's hook
+pub const SOURCE_WEBHOOK_SECRET: &str = "webhook_secret";
+
+/// A source that delivers webhooks
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebhookProvider {
+    GitHub,
+    GitLab,
+    Bitbucket,
+}
+
+impl WebhookProvider {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookProvider::GitHub => "github",
+            WebhookProvider::GitLab => "gitlab",
+            WebhookProvider::Bitbucket => "bitbucket",
+        }
+    }
+
+    /// Headers passed on to data-connector with the payload
+    fn forwarded_headers(&self) -> &'static [&'static str] {
+        match self {
+            WebhookProvider::GitHub => &["X-GitHub-Event", "X-Hub-Signature-256", "X-GitHub-Delivery"],
+            WebhookProvider::GitLab => &["X-Gitlab-Event", "X-Gitlab-Token", "X-Gitlab-Event-UUID"],
+            WebhookProvider::Bitbucket => &["X-Event-Key", "X-Hub-Signature", "X-Request-UUID", "X-Hook-UUID"],
+        }
+    }
+
+    /// Header naming the event type
+    fn event_header(&self) -> &'static str {
+        match self {
+            WebhookProvider::GitHub => "X-GitHub-Event",
+            WebhookProvider::GitLab => "X-Gitlab-Event",
+            WebhookProvider::Bitbucket => "X-Event-Key",
+        }
+    }
+
+    /// Header identifying a delivery; retries of a delivery carry the same value
+    fn delivery_header(&self) -> &'static str {
+        match self {
+            WebhookProvider::GitHub => "X-GitHub-Delivery",
+            WebhookProvider::GitLab => "X-Gitlab-Event-UUID",
+            WebhookProvider::Bitbucket => "X-Request-UUID",
+        }
+    }
+
+    /// Provider-wide secret, used for sources that have none
+    fn fallback_secret<'a>(&self, config: &'a Config) -> Option<&'a str> {
+        match self {
+            WebhookProvider::GitHub => config.github_webhook_secret.as_deref(),
+            WebhookProvider::GitLab => config.gitlab_webhook_token.as_deref(),
+            WebhookProvider::Bitbucket => config.bitbucket_webhook_secret.as_deref(),
+        }
+    }
+
+    /// Web URL of the repository a payload is about
+    fn repository_url<'a>(&self, payload: &'a Value) -> Option<&'a str> {
+        let pointers: &[&str] = match self {
+            WebhookProvider::GitHub => &["/repository/html_url"],
+            WebhookProvider::GitLab => &["/project/web_url", "/repository/homepage"],
+            WebhookProvider::Bitbucket => &["/repository/links/html/href"],
+        };
+        pointers
+            .iter()
+            .find_map(|pointer| payload.pointer(pointer).and_then(Value::as_str))
+    }
+
+    /// Secrets a delivery may be signed with, each with the sources it speaks for
+    async fn candidates(&self, state: &AppState, payload: Option<&Value>) -> Result<Vec<SecretCandidate>> {
+        let fallback = self.fallback_secret(&state.config);
+        let Some(url) = payload.and_then(|p| self.repository_url(p)) else {
+            return Ok(candidate_secrets(&[], fallback));
+        };
+
+        match state
+            .data_connector_client
+            .find_sources_by_repository(self.as_str(), &normalize_repository_url(url))
+            .await
+        {
+            Ok(found) => Ok(candidate_secrets(&found.sources, fallback)),
+            Err(e) if fallback.is_some() => {
+                tracing::warn!(
+                    provider = self.as_str(),
+                    error = %e,
+                    "Source lookup failed; verifying webhook with the provider-wide secret"
+                );
+                Ok(candidate_secrets(&[], fallback))
+            }
+            Err(e) => {
+                tracing::error!(provider = self.as_str(), error = %e, "Source lookup failed; cannot verify webhook");
+                Err(AppError::ServiceUnavailable("Webhook secret lookup failed".to_string()))
+            }
+        }
+    }
+
+    /// Check that a delivery was signed with one of the candidate secrets,
+    /// returning the sources of every secret that matched
+    fn verify(&self, candidates: &[SecretCandidate], headers: &HeaderMap, body: &[u8]) -> Result<Vec<Source>> {
+        if candidates.is_empty() {
+            tracing::error!(provider = self.as_str(), "No webhook secret configured; rejecting delivery");
+            return Err(AppError::Unauthorized("Webhook signature cannot be verified".to_string()));
+        }
+        let header = |name: &str| headers.get(name).and_then(|v| v.to_str().ok());
+
+        let signed_with = |secret: &str| match self {
+            WebhookProvider::GitHub => header("X-Hub-Signature-256")
+                .map(|sig| hmac_matches(secret, sig, body))
+                .unwrap_or(false),
+            WebhookProvider::Bitbucket => header("X-Hub-Signature")
+                .map(|sig| hmac_matches(secret, sig, body))
+                .unwrap_or(false),
+            WebhookProvider::GitLab => header("X-Gitlab-Token")
+                .map(|token| constant_time_eq(token.as_bytes(), secret.as_bytes()))
+                .unwrap_or(false),
+        };
+
+        let matched: Vec<&SecretCandidate> = candidates
+            .iter()
+            .filter(|candidate| signed_with(&candidate.secret))
+            .collect();
+        if matched.is_empty() {
+            tracing::warn!(
+                provider = self.as_str(),
+                delivery = header(self.delivery_header()).unwrap_or("unknown"),
+                "Rejected webhook delivery with a missing or invalid signature"
+            );
+            return Err(AppError::Unauthorized("Invalid webhook signature".to_string()));
+        }
+
+        let mut sources: Vec<Source> = Vec::new();
+        for source in matched.into_iter().flat_map(|candidate| &candidate.sources) {
+            if !sources.iter().any(|s| s.id == source.id) {
+                sources.push(source.clone());
+            }
+        }
+        Ok(sources)
+    }
+}
+
+/// A secret a delivery may be signed with, and the sources it speaks for
+#[derive(Debug, Clone)]
+struct SecretCandidate {
+    secret: String,
+    /// Sources verified by this secret, with their own secret removed
+    sources: Vec<Source>,
+}
+
+/// One candidate per distinct `webhook_secret` of the sources, plus the
+/// fallback for the sources without one
+///
+/// With no sources known the fallback speaks for none: the delivery can
+/// still be forwarded, but triggers no source's sync.
+fn candidate_secrets(sources: &[Source], fallback: Option<&str>) -> Vec<SecretCandidate> {
+    let mut candidates: Vec<SecretCandidate> = Vec::new();
+    let mut unsecured = Vec::new();
+    for source in sources {
+        let secret = source
+            .metadata
+            .as_ref()
+            .and_then(|m| m.get(SOURCE_WEBHOOK_SECRET))
+            .and_then(Value::as_str)
+            .filter(|secret| !secret.is_empty());
+        let mut public = source.clone();
+        if let Some(metadata) = public.metadata.as_mut() {
+            metadata.remove(SOURCE_WEBHOOK_SECRET);
+        }
+        match secret {
+            Some(secret) => match candidates.iter_mut().find(|c| c.secret == secret) {
+                Some(candidate) => candidate.sources.push(public),
+                None => candidates.push(SecretCandidate {
+                    secret: secret.to_string(),
+                    sources: vec![public],
+                }),
+            },
+            None => unsecured.push(public),
+        }
+    }
+    if let Some(fallback) = fallback {
+        if sources.is_empty() || !unsecured.is_empty() {
+            candidates.push(SecretCandidate {
+                secret: fallback.to_string(),
+                sources: unsecured,
+            });
+        }
+    }
+    candidates
+}
+
+/// Whether `signature` (`sha256=<hex>`) is the HMAC-SHA256 of `body`
+fn hmac_matches(secret: &str, signature: &str, body: &[u8]) -> bool {
+    let Some(expected) = signature
+        .strip_prefix("sha256=")
+        .and_then(|hex_sig| hex::decode(hex_sig).ok())
+    else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Compare secrets without leaking the position of the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct WebhookAck {
+    pub delivery_id: String,
+    /// `queued`, or `duplicate` when the delivery was already received
+    pub status: &'static str,
+}
+
 /// POST /webhooks/github - Handle GitHub webhook events
 pub async fn github_webhook(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>> {
-    // Extract relevant headers for forwarding
-    let mut forward_headers = vec![];
-    
-    if let Some(event) = headers.get("X-GitHub-Event") {
-        forward_headers.push((
-            "X-GitHub-Event".to_string(),
-            event.to_str().unwrap_or("").to_string(),
-        ));
-    }
-    if let Some(sig) = headers.get("X-Hub-Signature-256") {
-        forward_headers.push((
-            "X-Hub-Signature-256".to_string(),
-            sig.to_str().unwrap_or("").to_string(),
-        ));
-    }
-    if let Some(delivery) = headers.get("X-GitHub-Delivery") {
-        forward_headers.push((
-            "X-GitHub-Delivery".to_string(),
-            delivery.to_str().unwrap_or("").to_string(),
-        ));
-    }
-    
-    // Forward to data-connector
-    let result = state.data_connector_client
-        .forward_webhook("github", payload, forward_headers)
-        .await?;
-    
-    Ok(Json(result))
+    body: Bytes,
+) -> Result<(StatusCode, Json<WebhookAck>)> {
+    receive(WebhookProvider::GitHub, &state, &headers, &body).await
 }
 
 /// POST /webhooks/gitlab - Handle GitLab webhook events
 pub async fn gitlab_webhook(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(payload): Json<serde_json::Value>,
-) -> Result<Json<serde_json::Value>> {
-    // Extract relevant headers for forwarding
-    let mut forward_headers = vec![];
-    
-    if let Some(event) = headers.get("X-Gitlab-Event") {
-        forward_headers.push((
-            "X-Gitlab-Event".to_string(),
-            event.to_str().unwrap_or("").to_string(),
-        ));
-    }
-    if let Some(token) = headers.get("X-Gitlab-Token") {
-        forward_headers.push((
-            "X-Gitlab-Token".to_string(),
-            token.to_str().unwrap_or("").to_string(),
-        ));
-    }
-    
-    // Forward to data-connector
-    let result = state.data_connector_client
-        .forward_webhook("gitlab", payload, forward_headers)
-        .await?;
-    
-    Ok(Json(result))
+    body: Bytes,
+) -> Result<(StatusCode, Json<WebhookAck>)> {
+    receive(WebhookProvider::GitLab, &state, &headers, &body).await
+}
+
+/// POST /webhooks/bitbucket - Handle Bitbucket webhook events
+pub async fn bitbucket_webhook(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<(StatusCode, Json<WebhookAck>)> {
+    receive(WebhookProvider::Bitbucket, &state, &headers, &body).await
+}
+
+/// Verify a delivery, then record it in the inbox for forwarding
+async fn receive(
+    provider: WebhookProvider,
+    state: &AppState,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(StatusCode, Json<WebhookAck>)> {
+    // Parsed before verifying only to find the repository, and so its secrets
+    let payload = serde_json::from_slice::<Value>(body);
+    let candidates = provider.candidates(state, payload.as_ref().ok()).await?;
+    let verified_sources = provider.verify(&candidates, headers, body)?;
+
+    let payload = payload
+        .map_err(|e| AppError::ValidationError(format!("Invalid webhook payload: {}", e)))?;
+
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+    };
+
+    // Headers forwarded with the payload
+    let forward_headers = provider
+        .forwarded_headers()
+        .iter()
+        .filter_map(|name| header(name).map(|value| (name.to_string(), value)))
+        .collect();
+
+    // Without a delivery ID, identical bodies are treated as the same delivery
+    let delivery_id = header(provider.delivery_header())
+        .unwrap_or_else(|| format!("sha256:{}", hex::encode(Sha256::digest(body))));
+
+    let now = chrono::Utc::now().to_rfc3339();
+    let delivery = WebhookDelivery {
+        id: uuid::Uuid::new_v4().to_string(),
+        provider: provider.as_str().to_string(),
+        delivery_id: delivery_id.clone(),
+        event: header(provider.event_header()),
+        headers: forward_headers,
+        payload,
+        verified_sources,
+        status: DELIVERY_PENDING.to_string(),
+        attempts: 0,
+        last_error: None,
+        next_attempt_at: now.clone(),
+        received_at: now,
+        delivered_at: None,
+    };
+
+    if !state.stores.webhooks.insert(&delivery).await? {
+        METRICS.record_webhook_delivery(provider.as_str(), "duplicate");
+        tracing::info!(
+            provider = provider.as_str(),
+            delivery_id = %delivery_id,
+            "Duplicate webhook delivery acknowledged"
+        );
+        return Ok((StatusCode::OK, Json(WebhookAck { delivery_id, status: "duplicate" })));
+    }
+
+    METRICS.record_webhook_delivery(provider.as_str(), "queued");
+    tracing::info!(
+        id = %delivery.id,
+        provider = provider.as_str(),
+        delivery_id = %delivery_id,
+        event = ?delivery.event,
+        "Webhook delivery queued"
+    );
+    Ok((StatusCode::ACCEPTED, Json(WebhookAck { delivery_id, status: "queued" })))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{SourceStatus, SourceType};
+    use axum::http::HeaderValue;
+    use std::collections::HashMap;
+
+    // Example from GitHub's "Validating webhook deliveries" documentation
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+    const SIGNATURE: &str = "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    /// Candidates for `values`, each speaking for a source named after its secret
+    fn secrets(values: &[&str]) -> Vec<SecretCandidate> {
+        values
+            .iter()
+            .map(|secret| SecretCandidate {
+                secret: secret.to_string(),
+                sources: vec![source(secret, None)],
+            })
+            .collect()
+    }
+
+    fn source(id: &str, secret: Option<&str>) -> Source {
+        Source {
+            id: id.to_string(),
+            source_type: SourceType::Github,
+            name: "repo".to_string(),
+            status: SourceStatus::Synced,
+            last_sync: None,
+            stats: None,
+            metadata: secret.map(|s| {
+                HashMap::from([(SOURCE_WEBHOOK_SECRET.to_string(), Value::String(s.to_string()))])
+            }),
+        }
+    }
+
+    fn ids(sources: &[Source]) -> Vec<&str> {
+        sources.iter().map(|s| s.id.as_str()).collect()
+    }
+
+    #[test]
+    fn github_signatures_are_checked() {
+        let github = WebhookProvider::GitHub;
+        let signed = headers(&[("X-Hub-Signature-256", SIGNATURE)]);
+        assert_eq!(ids(&github.verify(&secrets(&[SECRET]), &signed, BODY).unwrap()), vec![SECRET]);
+        // Only the sources of the secret that signed it are verified
+        assert_eq!(ids(&github.verify(&secrets(&["other", SECRET]), &signed, BODY).unwrap()), vec![SECRET]);
+
+        assert!(github.verify(&secrets(&["other"]), &signed, BODY).is_err());
+        assert!(github.verify(&secrets(&[SECRET]), &signed, b"Hello, World?").is_err());
+        let forged = headers(&[("X-Hub-Signature-256", &SIGNATURE.replace("757", "000"))]);
+        assert!(github.verify(&secrets(&[SECRET]), &forged, BODY).is_err());
+        let unprefixed = headers(&[("X-Hub-Signature-256", SIGNATURE.trim_start_matches("sha256="))]);
+        assert!(github.verify(&secrets(&[SECRET]), &unprefixed, BODY).is_err());
+        // The SHA-1 header GitHub also sends is not accepted in its place
+        let sha1_header = headers(&[("X-Hub-Signature", SIGNATURE)]);
+        assert!(github.verify(&secrets(&[SECRET]), &sha1_header, BODY).is_err());
+    }
+
+    #[test]
+    fn bitbucket_signatures_are_checked() {
+        let bitbucket = WebhookProvider::Bitbucket;
+        let signed = headers(&[("X-Hub-Signature", SIGNATURE)]);
+        assert!(bitbucket.verify(&secrets(&[SECRET]), &signed, BODY).is_ok());
+        assert!(bitbucket.verify(&secrets(&["other"]), &signed, BODY).is_err());
+        let forged = headers(&[("X-Hub-Signature", "sha256=not-hex")]);
+        assert!(bitbucket.verify(&secrets(&[SECRET]), &forged, BODY).is_err());
+    }
+
+    #[test]
+    fn gitlab_tokens_are_compared() {
+        let gitlab = WebhookProvider::GitLab;
+        let token = headers(&[("X-Gitlab-Token", "glt-secret")]);
+        assert!(gitlab.verify(&secrets(&["glt-secret"]), &token, BODY).is_ok());
+        assert!(gitlab.verify(&secrets(&["glt-secret2"]), &token, BODY).is_err());
+        assert!(gitlab.verify(&secrets(&["glt-secreT"]), &token, BODY).is_err());
+    }
+
+    #[test]
+    fn missing_headers_and_secrets_are_rejected() {
+        for provider in [WebhookProvider::GitHub, WebhookProvider::GitLab, WebhookProvider::Bitbucket] {
+            assert!(provider.verify(&secrets(&[SECRET]), &HeaderMap::new(), BODY).is_err());
+        }
+        let signed = headers(&[("X-Hub-Signature-256", SIGNATURE)]);
+        assert!(WebhookProvider::GitHub.verify(&[], &signed, BODY).is_err());
+    }
+
+    #[test]
+    fn each_secret_speaks_only_for_its_sources() {
+        let sources = [
+            source("victim", Some("victim-secret")),
+            source("attacker", Some(SECRET)),
+            source("legacy", None),
+            source("blank", Some("")),
+        ];
+        let candidates = candidate_secrets(&sources, Some("env"));
+        let by_secret: Vec<(&str, Vec<&str>)> = candidates
+            .iter()
+            .map(|c| (c.secret.as_str(), ids(&c.sources)))
+            .collect();
+        assert_eq!(
+            by_secret,
+            vec![
+                ("victim-secret", vec!["victim"]),
+                (SECRET, vec!["attacker"]),
+                ("env", vec!["legacy", "blank"]),
+            ]
+        );
+        // Secrets are not carried along with the verified sources
+        assert!(candidates
+            .iter()
+            .flat_map(|c| &c.sources)
+            .all(|s| s.metadata.as_ref().is_none_or(|m| !m.contains_key(SOURCE_WEBHOOK_SECRET))));
+
+        // A delivery signed with the attacker's own secret verifies only the attacker's source
+        let signed = headers(&[("X-Hub-Signature-256", SIGNATURE)]);
+        let verified = WebhookProvider::GitHub.verify(&candidates, &signed, BODY).unwrap();
+        assert_eq!(ids(&verified), vec!["attacker"]);
+    }
+
+    #[test]
+    fn the_fallback_covers_sources_without_a_secret() {
+        // Every source has its own secret: the fallback verifies nothing
+        let secured = [source("a", Some("a-secret"))];
+        assert_eq!(candidate_secrets(&secured, Some("env")).len(), 1);
+
+        let candidates = candidate_secrets(&[source("a", None)], Some("env"));
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].secret, "env");
+        assert_eq!(ids(&candidates[0].sources), vec!["a"]);
+
+        // No sources known: the fallback verifies the delivery for no source
+        let candidates = candidate_secrets(&[], Some("env"));
+        assert_eq!(candidates.len(), 1);
+        assert!(candidates[0].sources.is_empty());
+
+        assert!(candidate_secrets(&[source("a", None)], None).is_empty());
+    }
+
+    #[test]
+    fn repository_urls_are_read_per_provider() {
+        let github = serde_json::json!({ "repository": { "html_url": "https://github.com/o/r" } });
+        let gitlab = serde_json::json!({ "project": { "web_url": "https://gitlab.com/o/r" } });
+        let bitbucket = serde_json::json!({
+            "repository": { "links": { "html": { "href": "https://bitbucket.org/o/r" } } }
+        });
+        assert_eq!(WebhookProvider::GitHub.repository_url(&github), Some("https://github.com/o/r"));
+        assert_eq!(WebhookProvider::GitLab.repository_url(&gitlab), Some("https://gitlab.com/o/r"));
+        assert_eq!(
+            WebhookProvider::Bitbucket.repository_url(&bitbucket),
+            Some("https://bitbucket.org/o/r")
+        );
+        assert_eq!(WebhookProvider::GitHub.repository_url(&gitlab), None);
+    }
 }
@@ -0,0 +1,436 @@
+//! In-process store, used as a test double
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use tokio::sync::RwLock;
+
+use super::{
+    AgentStore, DocumentStore, Owner, RepositoryStore, UrlStore, WebhookStore,
+    WebhookSubscriptionStore,
+};
+use crate::error::Result;
+use crate::models::{
+    AgentRecord, DocumentRecord, RepositoryRecord, SubscriptionDelivery, UrlRecord,
+    WebhookDelivery, WebhookSubscription, DELIVERY_DELIVERED, DELIVERY_FAILED, DELIVERY_PENDING,
+};
+
+/// Records held in memory, in insertion order
+#[derive(Default)]
+pub struct InMemoryStore {
+    agents: RwLock<Vec<AgentRecord>>,
+    documents: RwLock<Vec<DocumentRecord>>,
+    repositories: RwLock<Vec<RepositoryRecord>>,
+    urls: RwLock<Vec<UrlRecord>>,
+    webhooks: RwLock<Vec<WebhookDelivery>>,
+    subscriptions: RwLock<Vec<WebhookSubscription>>,
+    subscription_deliveries: RwLock<Vec<SubscriptionDelivery>>,
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// Remove the first matching record; false if there was none
+fn remove<T>(records: &mut Vec<T>, matches: impl Fn(&T) -> bool) -> bool {
+    match records.iter().position(matches) {
+        Some(pos) => {
+            records.remove(pos);
+            true
+        }
+        None => false,
+    }
+}
+
+#[async_trait::async_trait]
+impl AgentStore for InMemoryStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<AgentRecord>> {
+        Ok(self.agents.read().await
+            .iter()
+            .filter(|a| owner.owns(&a.user_id, a.workspace_id.as_deref()))
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<AgentRecord>> {
+        Ok(self.agents.read().await
+            .iter()
+            .find(|a| a.id == id && owner.owns(&a.user_id, a.workspace_id.as_deref()))
+            .cloned())
+    }
+
+    async fn insert(&self, agent: &AgentRecord) -> Result<()> {
+        self.agents.write().await.push(agent.clone());
+        Ok(())
+    }
+
+    async fn update(&self, owner: &Owner, agent: &AgentRecord) -> Result<bool> {
+        let mut agents = self.agents.write().await;
+        match agents
+            .iter_mut()
+            .find(|a| a.id == agent.id && owner.owns(&a.user_id, a.workspace_id.as_deref()))
+        {
+            Some(existing) => {
+                *existing = agent.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        Ok(remove(&mut *self.agents.write().await, |a| {
+            a.id == id && owner.owns(&a.user_id, a.workspace_id.as_deref())
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl DocumentStore for InMemoryStore {
+    async fn list(&self, owner: &Owner, search: Option<&str>) -> Result<Vec<DocumentRecord>> {
+        let search = search.map(str::to_lowercase);
+        Ok(self.documents.read().await
+            .iter()
+            .filter(|d| owner.owns(&d.user_id, d.workspace_id.as_deref()))
+            .filter(|d| match &search {
+                Some(search) => d.name.to_lowercase().contains(search),
+                None => true,
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn insert(&self, document: &DocumentRecord) -> Result<()> {
+        self.documents.write().await.push(document.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        Ok(remove(&mut *self.documents.write().await, |d| {
+            d.id == id && owner.owns(&d.user_id, d.workspace_id.as_deref())
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl RepositoryStore for InMemoryStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<RepositoryRecord>> {
+        Ok(self.repositories.read().await
+            .iter()
+            .filter(|r| owner.owns(&r.user_id, r.workspace_id.as_deref()))
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<RepositoryRecord>> {
+        Ok(self.repositories.read().await
+            .iter()
+            .find(|r| r.id == id && owner.owns(&r.user_id, r.workspace_id.as_deref()))
+            .cloned())
+    }
+
+    async fn insert(&self, repository: &RepositoryRecord) -> Result<()> {
+        self.repositories.write().await.push(repository.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        Ok(remove(&mut *self.repositories.write().await, |r| {
+            r.id == id && owner.owns(&r.user_id, r.workspace_id.as_deref())
+        }))
+    }
+}
+
+#[async_trait::async_trait]
+impl UrlStore for InMemoryStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<UrlRecord>> {
+        Ok(self.urls.read().await
+            .iter()
+            .filter(|u| owner.owns(&u.user_id, u.workspace_id.as_deref()))
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<UrlRecord>> {
+        Ok(self.urls.read().await
+            .iter()
+            .find(|u| u.id == id && owner.owns(&u.user_id, u.workspace_id.as_deref()))
+            .cloned())
+    }
+
+    async fn insert(&self, url: &UrlRecord) -> Result<()> {
+        self.urls.write().await.push(url.clone());
+        Ok(())
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        Ok(remove(&mut *self.urls.write().await, |u| {
+            u.id == id && owner.owns(&u.user_id, u.workspace_id.as_deref())
+        }))
+    }
+}
+
+/// Whether an RFC 3339 timestamp is at or before `now`
+fn is_due(timestamp: &str, now: DateTime<Utc>) -> bool {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|t| t <= now)
+        .unwrap_or(true)
+}
+
+#[async_trait::async_trait]
+impl WebhookStore for InMemoryStore {
+    async fn insert(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        let mut webhooks = self.webhooks.write().await;
+        if webhooks
+            .iter()
+            .any(|d| d.provider == delivery.provider && d.delivery_id == delivery.delivery_id)
+        {
+            return Ok(false);
+        }
+        webhooks.push(delivery.clone());
+        Ok(true)
+    }
+
+    async fn list(&self, status: Option<&str>, limit: usize) -> Result<Vec<WebhookDelivery>> {
+        Ok(self.webhooks.read().await
+            .iter()
+            .rev()
+            .filter(|d| status.is_none() || status == Some(d.status.as_str()))
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<WebhookDelivery>> {
+        Ok(self.webhooks.read().await.iter().find(|d| d.id == id).cloned())
+    }
+
+    async fn claim_due(&self, limit: usize, lease: Duration) -> Result<Vec<WebhookDelivery>> {
+        let now = Utc::now();
+        let lease_until = (now + chrono::Duration::from_std(lease).unwrap_or_default()).to_rfc3339();
+        Ok(self.webhooks.write().await
+            .iter_mut()
+            .filter(|d| d.status == DELIVERY_PENDING && is_due(&d.next_attempt_at, now))
+            .take(limit)
+            .map(|d| {
+                d.attempts += 1;
+                d.next_attempt_at = lease_until.clone();
+                d.clone()
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(&self, id: &str) -> Result<()> {
+        if let Some(d) = self.webhooks.write().await.iter_mut().find(|d| d.id == id) {
+            d.status = DELIVERY_DELIVERED.to_string();
+            d.last_error = None;
+            d.delivered_at = Some(Utc::now().to_rfc3339());
+        }
+        Ok(())
+    }
+
+    async fn mark_failed(&self, id: &str, error: &str, retry_at: Option<DateTime<Utc>>) -> Result<()> {
+        if let Some(d) = self.webhooks.write().await.iter_mut().find(|d| d.id == id) {
+            d.last_error = Some(error.to_string());
+            match retry_at {
+                Some(at) => d.next_attempt_at = at.to_rfc3339(),
+                None => d.status = DELIVERY_FAILED.to_string(),
+            }
+        }
+        Ok(())
+    }
+
+    async fn redeliver(&self, id: &str) -> Result<bool> {
+        match self.webhooks.write().await.iter_mut().find(|d| d.id == id) {
+            Some(d) => {
+                d.status = DELIVERY_PENDING.to_string();
+                d.attempts = 0;
+                d.last_error = None;
+                d.next_attempt_at = Utc::now().to_rfc3339();
+                d.delivered_at = None;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookSubscriptionStore for InMemoryStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<WebhookSubscription>> {
+        Ok(self.subscriptions.read().await
+            .iter()
+            .filter(|s| owner.owns(&s.user_id, s.workspace_id.as_deref()))
+            .cloned()
+            .collect())
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<WebhookSubscription>> {
+        Ok(self.subscriptions.read().await
+            .iter()
+            .find(|s| s.id == id && owner.owns(&s.user_id, s.workspace_id.as_deref()))
+            .cloned())
+    }
+
+    async fn insert(&self, subscription: &WebhookSubscription) -> Result<()> {
+        self.subscriptions.write().await.push(subscription.clone());
+        Ok(())
+    }
+
+    async fn update(&self, owner: &Owner, subscription: &WebhookSubscription) -> Result<bool> {
+        let mut subscriptions = self.subscriptions.write().await;
+        match subscriptions
+            .iter_mut()
+            .find(|s| s.id == subscription.id && owner.owns(&s.user_id, s.workspace_id.as_deref()))
+        {
+            Some(existing) => {
+                existing.url = subscription.url.clone();
+                existing.event_types = subscription.event_types.clone();
+                existing.secret = subscription.secret.clone();
+                existing.updated_at = subscription.updated_at.clone();
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    async fn set_enabled(&self, owner: &Owner, id: &str, enabled: bool) -> Result<bool> {
+        let mut subscriptions = self.subscriptions.write().await;
+        let Some(s) = subscriptions
+            .iter_mut()
+            .find(|s| s.id == id && owner.owns(&s.user_id, s.workspace_id.as_deref()))
+        else {
+            return Ok(false);
+        };
+        if enabled && !s.enabled {
+            s.consecutive_failures = 0;
+            s.disabled_reason = None;
+        }
+        s.enabled = enabled;
+        s.updated_at = Utc::now().to_rfc3339();
+        Ok(true)
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        let removed = remove(&mut *self.subscriptions.write().await, |s| {
+            s.id == id && owner.owns(&s.user_id, s.workspace_id.as_deref())
+        });
+        if removed {
+            self.subscription_deliveries.write().await.retain(|d| d.subscription_id != id);
+        }
+        Ok(removed)
+    }
+
+    async fn subscribers(&self, owner: &Owner, event_type: &str) -> Result<Vec<WebhookSubscription>> {
+        Ok(self.subscriptions.read().await
+            .iter()
+            .filter(|s| {
+                s.enabled
+                    && owner.owns(&s.user_id, s.workspace_id.as_deref())
+                    && s.event_types.iter().any(|t| t == event_type)
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn insert_delivery(&self, delivery: &SubscriptionDelivery) -> Result<()> {
+        self.subscription_deliveries.write().await.push(delivery.clone());
+        Ok(())
+    }
+
+    async fn deliveries(&self, subscription_id: &str, limit: usize) -> Result<Vec<SubscriptionDelivery>> {
+        Ok(self.subscription_deliveries.read().await
+            .iter()
+            .rev()
+            .filter(|d| d.subscription_id == subscription_id)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<(SubscriptionDelivery, WebhookSubscription)>> {
+        let now = Utc::now();
+        let lease_until = (now + chrono::Duration::from_std(lease).unwrap_or_default()).to_rfc3339();
+        let subscriptions = self.subscriptions.read().await;
+        let mut deliveries = self.subscription_deliveries.write().await;
+
+        let mut claimed = Vec::new();
+        for delivery in deliveries
+            .iter_mut()
+            .filter(|d| d.status == DELIVERY_PENDING && is_due(&d.next_attempt_at, now))
+        {
+            if claimed.len() == limit {
+                break;
+            }
+            let Some(subscription) = subscriptions
+                .iter()
+                .find(|s| s.id == delivery.subscription_id && s.enabled)
+            else {
+                continue;
+            };
+            delivery.attempts += 1;
+            delivery.next_attempt_at = lease_until.clone();
+            claimed.push((delivery.clone(), subscription.clone()));
+        }
+        Ok(claimed)
+    }
+
+    async fn mark_delivered(&self, delivery_id: &str, response_status: u16) -> Result<()> {
+        if let Some(d) = self.subscription_deliveries.write().await.iter_mut().find(|d| d.id == delivery_id) {
+            d.status = DELIVERY_DELIVERED.to_string();
+            d.response_status = Some(response_status);
+            d.last_error = None;
+            d.delivered_at = Some(Utc::now().to_rfc3339());
+        }
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        delivery_id: &str,
+        response_status: Option<u16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        if let Some(d) = self.subscription_deliveries.write().await.iter_mut().find(|d| d.id == delivery_id) {
+            d.response_status = response_status;
+            d.last_error = Some(error.to_string());
+            match retry_at {
+                Some(at) => d.next_attempt_at = at.to_rfc3339(),
+                None => d.status = DELIVERY_FAILED.to_string(),
+            }
+        }
+        Ok(())
+    }
+
+    async fn record_attempt(
+        &self,
+        subscription_id: &str,
+        succeeded: bool,
+        disable_after: u32,
+        disable_reason: &str,
+    ) -> Result<bool> {
+        let mut subscriptions = self.subscriptions.write().await;
+        let Some(s) = subscriptions.iter_mut().find(|s| s.id == subscription_id) else {
+            return Ok(false);
+        };
+        if succeeded {
+            s.consecutive_failures = 0;
+            return Ok(false);
+        }
+        s.consecutive_failures += 1;
+        if s.enabled && s.consecutive_failures >= disable_after {
+            s.enabled = false;
+            s.disabled_reason = Some(disable_reason.to_string());
+            s.updated_at = Utc::now().to_rfc3339();
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
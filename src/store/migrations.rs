@@ -0,0 +1,135 @@
+//! Embedded schema migrations
+//!
+//! Migrations live in `migrations/` as reversible `.up.sql` / `.down.sql`
+//! pairs and are compiled into the binary. Applying, reverting or listing
+//! them holds a Postgres advisory lock on a dedicated connection, so replicas
+//! starting at the same time apply each migration exactly once and a status
+//! check never sees a migration half-applied.
+
+use serde::Serialize;
+use sqlx::migrate::{Migrate, Migrator};
+use sqlx::{Connection, PgConnection, PgPool};
+use std::collections::HashMap;
+
+use crate::error::Result;
+
+/// Schema migrations embedded from `migrations/`
+pub static MIGRATOR: Migrator = sqlx::migrate!();
+
+/// Advisory lock key held while migrations run ("api_migr")
+const LOCK_KEY: i64 = 0x6170_695f_6d69_6772;
+
+/// Where a migration stands against the database
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationState {
+    Applied,
+    Pending,
+    /// Applied, but the embedded SQL no longer matches what was run
+    Modified,
+}
+
+impl MigrationState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationState::Applied => "applied",
+            MigrationState::Pending => "pending",
+            MigrationState::Modified => "modified",
+        }
+    }
+}
+
+/// One embedded migration and its state
+#[derive(Debug, Clone, Serialize)]
+pub struct MigrationStatus {
+    pub version: i64,
+    pub description: String,
+    pub state: MigrationState,
+}
+
+/// Take the migration lock on a connection detached from the pool
+///
+/// The lock is session-scoped: it is released when the connection closes,
+/// including when it is dropped after an error.
+async fn lock(pool: &PgPool) -> Result<PgConnection> {
+    let mut conn = pool.acquire().await?.detach();
+    sqlx::query("SELECT pg_advisory_lock($1)")
+        .bind(LOCK_KEY)
+        .execute(&mut conn)
+        .await?;
+    Ok(conn)
+}
+
+async fn statuses(conn: &mut PgConnection) -> Result<Vec<MigrationStatus>> {
+    conn.ensure_migrations_table().await?;
+    let applied: HashMap<i64, Vec<u8>> = conn
+        .list_applied_migrations()
+        .await?
+        .into_iter()
+        .map(|m| (m.version, m.checksum.into_owned()))
+        .collect();
+
+    Ok(MIGRATOR
+        .iter()
+        .filter(|m| m.migration_type.is_up_migration())
+        .map(|m| MigrationStatus {
+            version: m.version,
+            description: m.description.to_string(),
+            state: match applied.get(&m.version) {
+                Some(checksum) if *checksum == *m.checksum => MigrationState::Applied,
+                Some(_) => MigrationState::Modified,
+                None => MigrationState::Pending,
+            },
+        })
+        .collect())
+}
+
+/// State of every embedded migration
+///
+/// Waits for any migration in progress elsewhere to finish first.
+pub async fn status(pool: &PgPool) -> Result<Vec<MigrationStatus>> {
+    let mut conn = lock(pool).await?;
+    let statuses = statuses(&mut conn).await?;
+    conn.close().await?;
+    Ok(statuses)
+}
+
+/// Apply all pending migrations, returning the ones this call applied
+pub async fn up(pool: &PgPool) -> Result<Vec<MigrationStatus>> {
+    let mut conn = lock(pool).await?;
+    let pending: Vec<MigrationStatus> = statuses(&mut conn)
+        .await?
+        .into_iter()
+        .filter(|m| m.state == MigrationState::Pending)
+        .collect();
+
+    if !pending.is_empty() {
+        MIGRATOR.run(&mut conn).await?;
+    }
+    conn.close().await?;
+
+    Ok(pending
+        .into_iter()
+        .map(|m| MigrationStatus { state: MigrationState::Applied, ..m })
+        .collect())
+}
+
+/// Revert the most recently applied migration, if any
+pub async fn down(pool: &PgPool) -> Result<Option<MigrationStatus>> {
+    let mut conn = lock(pool).await?;
+    let applied: Vec<MigrationStatus> = statuses(&mut conn)
+        .await?
+        .into_iter()
+        .filter(|m| m.state != MigrationState::Pending)
+        .collect();
+
+    let Some(last) = applied.last().cloned() else {
+        conn.close().await?;
+        return Ok(None);
+    };
+    let target = applied.iter().rev().nth(1).map(|m| m.version).unwrap_or(0);
+    MIGRATOR.undo(&mut conn, target).await?;
+    conn.close().await?;
+
+    Ok(Some(MigrationStatus { state: MigrationState::Pending, ..last }))
+}
@@ -0,0 +1,201 @@
+//! Persistence for the `/api/*` management resources and webhooks
+//!
+//! Handlers talk to one store trait per table. `PostgresStore` backs them in
+//! production; `InMemoryStore` keeps the same behaviour in process memory and
+//! is used as a test double.
+//!
+//! Every lookup is scoped to an `Owner`: records belonging to another user or
+//! workspace are indistinguishable from records that do not exist. Inbox
+//! webhook deliveries belong to no user and are only exposed to admins.
+
+mod memory;
+pub mod migrations;
+mod postgres;
+mod secrets;
+
+pub use memory::InMemoryStore;
+pub use postgres::PostgresStore;
+pub use secrets::SecretCipher;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+
+use crate::error::Result;
+use crate::models::{
+    AgentRecord, DocumentRecord, RepositoryRecord, SubscriptionDelivery, UrlRecord, User,
+    WebhookDelivery, WebhookSubscription,
+};
+
+/// The user and workspace a record belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Owner {
+    pub user_id: String,
+    pub workspace_id: Option<String>,
+}
+
+impl Owner {
+    pub fn of(user: &User) -> Self {
+        Self {
+            user_id: user.id.clone(),
+            workspace_id: user.workspace_id.clone(),
+        }
+    }
+
+    /// Whether a record with these owner fields belongs to this owner
+    pub fn owns(&self, user_id: &str, workspace_id: Option<&str>) -> bool {
+        self.user_id == user_id && self.workspace_id.as_deref() == workspace_id
+    }
+}
+
+/// Storage for connected AI agents
+#[async_trait::async_trait]
+pub trait AgentStore: Send + Sync {
+    async fn list(&self, owner: &Owner) -> Result<Vec<AgentRecord>>;
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<AgentRecord>>;
+    async fn insert(&self, agent: &AgentRecord) -> Result<()>;
+    /// Replace an existing agent; false if the owner has no such agent
+    async fn update(&self, owner: &Owner, agent: &AgentRecord) -> Result<bool>;
+    /// Remove an agent; false if the owner has no such agent
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool>;
+}
+
+/// Storage for documents
+#[async_trait::async_trait]
+pub trait DocumentStore: Send + Sync {
+    /// List documents, optionally filtered by a case-insensitive name match
+    async fn list(&self, owner: &Owner, search: Option<&str>) -> Result<Vec<DocumentRecord>>;
+    async fn insert(&self, document: &DocumentRecord) -> Result<()>;
+    /// Remove a document; false if the owner has no such document
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool>;
+}
+
+/// Storage for connected code repositories
+#[async_trait::async_trait]
+pub trait RepositoryStore: Send + Sync {
+    async fn list(&self, owner: &Owner) -> Result<Vec<RepositoryRecord>>;
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<RepositoryRecord>>;
+    async fn insert(&self, repository: &RepositoryRecord) -> Result<()>;
+    /// Remove a repository; false if the owner has no such repository
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool>;
+}
+
+/// Storage for saved URLs
+#[async_trait::async_trait]
+pub trait UrlStore: Send + Sync {
+    async fn list(&self, owner: &Owner) -> Result<Vec<UrlRecord>>;
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<UrlRecord>>;
+    async fn insert(&self, url: &UrlRecord) -> Result<()>;
+    /// Remove a URL; false if the owner has no such URL
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool>;
+}
+
+/// Durable inbox of incoming webhook deliveries
+#[async_trait::async_trait]
+pub trait WebhookStore: Send + Sync {
+    /// Record a delivery; false if the provider's delivery ID is already recorded
+    async fn insert(&self, delivery: &WebhookDelivery) -> Result<bool>;
+    /// Most recently received first, optionally only those with `status`
+    async fn list(&self, status: Option<&str>, limit: usize) -> Result<Vec<WebhookDelivery>>;
+    async fn get(&self, id: &str) -> Result<Option<WebhookDelivery>>;
+    /// Take up to `limit` pending deliveries that are due, counting an attempt
+    /// and holding each back for `lease` so no other worker takes it meanwhile
+    async fn claim_due(&self, limit: usize, lease: Duration) -> Result<Vec<WebhookDelivery>>;
+    async fn mark_delivered(&self, id: &str) -> Result<()>;
+    /// Record a failed attempt; retried at `retry_at`, or given up on when `None`
+    async fn mark_failed(&self, id: &str, error: &str, retry_at: Option<DateTime<Utc>>) -> Result<()>;
+    /// Queue a delivery to be forwarded again now; false if there is no such delivery
+    async fn redeliver(&self, id: &str) -> Result<bool>;
+}
+
+/// Storage for outbound webhook subscriptions and their delivery logs
+#[async_trait::async_trait]
+pub trait WebhookSubscriptionStore: Send + Sync {
+    async fn list(&self, owner: &Owner) -> Result<Vec<WebhookSubscription>>;
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<WebhookSubscription>>;
+    async fn insert(&self, subscription: &WebhookSubscription) -> Result<()>;
+    /// Save a subscription's URL, event types and secret; false if the owner
+    /// has no such subscription
+    async fn update(&self, owner: &Owner, subscription: &WebhookSubscription) -> Result<bool>;
+    /// Enable or disable a subscription; enabling a disabled one clears its
+    /// failure count and disable reason. False if the owner has no such subscription
+    async fn set_enabled(&self, owner: &Owner, id: &str, enabled: bool) -> Result<bool>;
+    /// Remove a subscription and its delivery log; false if the owner has no such subscription
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool>;
+    /// The owner's enabled subscriptions that want `event_type`
+    async fn subscribers(&self, owner: &Owner, event_type: &str) -> Result<Vec<WebhookSubscription>>;
+    async fn insert_delivery(&self, delivery: &SubscriptionDelivery) -> Result<()>;
+    /// A subscription's deliveries, newest first
+    async fn deliveries(&self, subscription_id: &str, limit: usize) -> Result<Vec<SubscriptionDelivery>>;
+    /// Take up to `limit` due pending deliveries of enabled subscriptions,
+    /// with their subscription, counting an attempt and holding each back
+    /// for `lease` so no other worker takes it meanwhile
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<(SubscriptionDelivery, WebhookSubscription)>>;
+    async fn mark_delivered(&self, delivery_id: &str, response_status: u16) -> Result<()>;
+    /// Record a failed attempt; retried at `retry_at`, or given up on when `None`
+    async fn mark_failed(
+        &self,
+        delivery_id: &str,
+        response_status: Option<u16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()>;
+    /// Count a subscription's attempt, disabling it with `disable_reason`
+    /// once `disable_after` attempts in a row have failed; true if this
+    /// attempt disabled it
+    async fn record_attempt(
+        &self,
+        subscription_id: &str,
+        succeeded: bool,
+        disable_after: u32,
+        disable_reason: &str,
+    ) -> Result<bool>;
+}
+
+/// The stores used by the route handlers
+#[derive(Clone)]
+pub struct Stores {
+    pub agents: Arc<dyn AgentStore>,
+    pub documents: Arc<dyn DocumentStore>,
+    pub repositories: Arc<dyn RepositoryStore>,
+    pub urls: Arc<dyn UrlStore>,
+    pub webhooks: Arc<dyn WebhookStore>,
+    pub subscriptions: Arc<dyn WebhookSubscriptionStore>,
+}
+
+impl Stores {
+    /// Stores backed by Postgres tables
+    pub fn postgres(store: PostgresStore) -> Self {
+        Self::from_store(Arc::new(store))
+    }
+
+    /// Empty stores held in process memory
+    pub fn in_memory() -> Self {
+        Self::from_store(Arc::new(InMemoryStore::new()))
+    }
+
+    fn from_store<S>(store: Arc<S>) -> Self
+    where
+        S: AgentStore
+            + DocumentStore
+            + RepositoryStore
+            + UrlStore
+            + WebhookStore
+            + WebhookSubscriptionStore
+            + 'static,
+    {
+        Self {
+            agents: store.clone(),
+            documents: store.clone(),
+            repositories: store.clone(),
+            urls: store.clone(),
+            webhooks: store.clone(),
+            subscriptions: store,
+        }
+    }
+}
@@ -0,0 +1,969 @@
+//! Postgres-backed store
+//!
+//! Records carry RFC 3339 timestamp strings; columns are `TIMESTAMPTZ`.
+//! Owner-scoped queries match `workspace_id` with `IS NOT DISTINCT FROM` so
+//! records created without a workspace only match callers without one.
+
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+use sqlx::PgPool;
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::secrets::SecretCipher;
+use super::{
+    AgentStore, DocumentStore, Owner, RepositoryStore, UrlStore, WebhookStore,
+    WebhookSubscriptionStore,
+};
+use crate::error::{AppError, Result};
+use crate::models::{
+    mask_api_key, AgentConfig, AgentRecord, AgentUsageStats, DocumentRecord, RepositoryRecord,
+    Source, SubscriptionDelivery, UrlRecord, WebhookDelivery, WebhookSubscription,
+    DELIVERY_DELIVERED, DELIVERY_FAILED, DELIVERY_PENDING,
+};
+
+/// Store over the tables created by the embedded migrations
+pub struct PostgresStore {
+    pool: PgPool,
+    /// Seals `agents.api_key` and `webhook_subscriptions.secret`
+    cipher: SecretCipher,
+}
+
+impl PostgresStore {
+    pub fn new(pool: PgPool, cipher: SecretCipher) -> Self {
+        Self { pool, cipher }
+    }
+
+    /// Encrypt agent API keys written before they were sealed.
+    /// Returns the number of keys encrypted.
+    pub async fn seal_plaintext_agent_keys(&self) -> Result<u64> {
+        let rows: Vec<(String, String)> =
+            sqlx::query_as("SELECT id, api_key FROM agents WHERE api_key NOT LIKE 'enc:%'")
+                .fetch_all(&self.pool)
+                .await?;
+
+        let mut sealed = 0;
+        for (id, api_key) in rows {
+            // Conditional on the old value so a concurrent update is not overwritten
+            let result = sqlx::query("UPDATE agents SET api_key = $2 WHERE id = $1 AND api_key = $3")
+                .bind(&id)
+                .bind(self.cipher.seal(&api_key)?)
+                .bind(&api_key)
+                .execute(&self.pool)
+                .await?;
+            sealed += result.rows_affected();
+        }
+        Ok(sealed)
+    }
+
+    /// Encrypt webhook subscription secrets written before they were sealed.
+    /// Returns the number of secrets encrypted.
+    pub async fn seal_plaintext_subscription_secrets(&self) -> Result<u64> {
+        let rows: Vec<(String, String)> = sqlx::query_as(
+            "SELECT id, secret FROM webhook_subscriptions WHERE secret NOT LIKE 'enc:%'",
+        )
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut sealed = 0;
+        for (id, secret) in rows {
+            // Conditional on the old value so a concurrent update is not overwritten
+            let result =
+                sqlx::query("UPDATE webhook_subscriptions SET secret = $2 WHERE id = $1 AND secret = $3")
+                    .bind(&id)
+                    .bind(self.cipher.seal(&secret)?)
+                    .bind(&secret)
+                    .execute(&self.pool)
+                    .await?;
+            sealed += result.rows_affected();
+        }
+        Ok(sealed)
+    }
+
+    fn agent_from_row(&self, row: AgentRow) -> Result<AgentRecord> {
+        let api_key = self.cipher.open(&row.api_key)?;
+        Ok(AgentRecord {
+            id: row.id,
+            user_id: row.user_id,
+            workspace_id: row.workspace_id,
+            name: row.name,
+            agent_type: row.agent_type,
+            endpoint: row.endpoint,
+            api_key_hint: mask_api_key(&api_key),
+            api_key,
+            permissions: row.permissions,
+            status: row.status,
+            config: row.config.0,
+            usage_stats: row.usage_stats.0,
+            created_at: row.created_at.to_rfc3339(),
+            updated_at: row.updated_at.to_rfc3339(),
+            last_used: row.last_used.map(|t| t.to_rfc3339()),
+        })
+    }
+
+    fn subscription_from_row(&self, row: SubscriptionRow) -> Result<WebhookSubscription> {
+        Ok(WebhookSubscription {
+            secret: self.cipher.open(&row.secret)?,
+            id: row.id,
+            user_id: row.user_id,
+            workspace_id: row.workspace_id,
+            url: row.url,
+            event_types: row.event_types,
+            enabled: row.enabled,
+            consecutive_failures: row.consecutive_failures.max(0) as u32,
+            disabled_reason: row.disabled_reason,
+            created_at: row.created_at.to_rfc3339(),
+            updated_at: row.updated_at.to_rfc3339(),
+        })
+    }
+
+    /// Delete one of the owner's records from `table`
+    async fn delete_owned(&self, table: &str, owner: &Owner, id: &str) -> Result<bool> {
+        let result = sqlx::query(&format!("DELETE FROM {} WHERE {}", table, BY_ID_AND_OWNER))
+            .bind(id)
+            .bind(&owner.user_id)
+            .bind(&owner.workspace_id)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Owner filter for a query whose `$1` is the record ID
+const BY_ID_AND_OWNER: &str = "id = $1 AND user_id = $2 AND workspace_id IS NOT DISTINCT FROM $3";
+
+/// Owner filter for a query whose first parameters are the owner
+const BY_OWNER: &str = "user_id = $1 AND workspace_id IS NOT DISTINCT FROM $2";
+
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|_| AppError::ValidationError(format!("Invalid timestamp: {}", value)))
+}
+
+fn parse_optional_timestamp(value: Option<&str>) -> Result<Option<DateTime<Utc>>> {
+    value.map(parse_timestamp).transpose()
+}
+
+#[derive(sqlx::FromRow)]
+struct AgentRow {
+    id: String,
+    user_id: String,
+    workspace_id: Option<String>,
+    name: String,
+    agent_type: String,
+    endpoint: Option<String>,
+    api_key: String,
+    permissions: Vec<String>,
+    status: String,
+    config: Json<AgentConfig>,
+    usage_stats: Json<AgentUsageStats>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+    last_used: Option<DateTime<Utc>>,
+}
+
+const AGENT_COLUMNS: &str = "id, user_id, workspace_id, name, agent_type, endpoint, api_key, \
+     permissions, status, config, usage_stats, created_at, updated_at, last_used";
+
+#[async_trait::async_trait]
+impl AgentStore for PostgresStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<AgentRecord>> {
+        let rows: Vec<AgentRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM agents WHERE {} ORDER BY created_at",
+            AGENT_COLUMNS, BY_OWNER
+        ))
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter().map(|row| self.agent_from_row(row)).collect()
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<AgentRecord>> {
+        let row: Option<AgentRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM agents WHERE {}",
+            AGENT_COLUMNS, BY_ID_AND_OWNER
+        ))
+        .bind(id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| self.agent_from_row(row)).transpose()
+    }
+
+    async fn insert(&self, agent: &AgentRecord) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO agents ({}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)",
+            AGENT_COLUMNS
+        ))
+        .bind(&agent.id)
+        .bind(&agent.user_id)
+        .bind(&agent.workspace_id)
+        .bind(&agent.name)
+        .bind(&agent.agent_type)
+        .bind(&agent.endpoint)
+        .bind(self.cipher.seal(&agent.api_key)?)
+        .bind(&agent.permissions)
+        .bind(&agent.status)
+        .bind(Json(&agent.config))
+        .bind(Json(&agent.usage_stats))
+        .bind(parse_timestamp(&agent.created_at)?)
+        .bind(parse_timestamp(&agent.updated_at)?)
+        .bind(parse_optional_timestamp(agent.last_used.as_deref())?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update(&self, owner: &Owner, agent: &AgentRecord) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            "UPDATE agents SET name = $4, endpoint = $5, api_key = $6, permissions = $7, status = $8, \
+             config = $9, usage_stats = $10, updated_at = $11, last_used = $12 WHERE {}",
+            BY_ID_AND_OWNER
+        ))
+        .bind(&agent.id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .bind(&agent.name)
+        .bind(&agent.endpoint)
+        .bind(self.cipher.seal(&agent.api_key)?)
+        .bind(&agent.permissions)
+        .bind(&agent.status)
+        .bind(Json(&agent.config))
+        .bind(Json(&agent.usage_stats))
+        .bind(parse_timestamp(&agent.updated_at)?)
+        .bind(parse_optional_timestamp(agent.last_used.as_deref())?)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        self.delete_owned("agents", owner, id).await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct DocumentRow {
+    id: String,
+    user_id: String,
+    workspace_id: Option<String>,
+    name: String,
+    doc_type: String,
+    source: String,
+    size: String,
+    tags: Vec<String>,
+    status: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<DocumentRow> for DocumentRecord {
+    fn from(row: DocumentRow) -> Self {
+        Self {
+            id: row.id,
+            user_id: row.user_id,
+            workspace_id: row.workspace_id,
+            name: row.name,
+            doc_type: row.doc_type,
+            source: row.source,
+            size: row.size,
+            tags: row.tags,
+            status: row.status,
+            created_at: row.created_at.to_rfc3339(),
+            updated_at: row.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+const DOCUMENT_COLUMNS: &str = "id, user_id, workspace_id, name, doc_type, source, size, tags, \
+     status, created_at, updated_at";
+
+#[async_trait::async_trait]
+impl DocumentStore for PostgresStore {
+    async fn list(&self, owner: &Owner, search: Option<&str>) -> Result<Vec<DocumentRecord>> {
+        // strpos rather than ILIKE so `%` and `_` in the search are literal
+        let rows: Vec<DocumentRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM documents \
+             WHERE {} AND ($3::text IS NULL OR strpos(lower(name), lower($3)) > 0) \
+             ORDER BY created_at",
+            DOCUMENT_COLUMNS, BY_OWNER
+        ))
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .bind(search)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn insert(&self, document: &DocumentRecord) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO documents ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            DOCUMENT_COLUMNS
+        ))
+        .bind(&document.id)
+        .bind(&document.user_id)
+        .bind(&document.workspace_id)
+        .bind(&document.name)
+        .bind(&document.doc_type)
+        .bind(&document.source)
+        .bind(&document.size)
+        .bind(&document.tags)
+        .bind(&document.status)
+        .bind(parse_timestamp(&document.created_at)?)
+        .bind(parse_timestamp(&document.updated_at)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        self.delete_owned("documents", owner, id).await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct RepositoryRow {
+    id: String,
+    user_id: String,
+    workspace_id: Option<String>,
+    name: String,
+    provider: String,
+    url: String,
+    branch: String,
+    status: String,
+    last_sync: Option<DateTime<Utc>>,
+    files_indexed: i64,
+    created_at: DateTime<Utc>,
+}
+
+impl From<RepositoryRow> for RepositoryRecord {
+    fn from(row: RepositoryRow) -> Self {
+        Self {
+            id: row.id,
+            user_id: row.user_id,
+            workspace_id: row.workspace_id,
+            name: row.name,
+            provider: row.provider,
+            url: row.url,
+            branch: row.branch,
+            status: row.status,
+            last_sync: row.last_sync.map(|t| t.to_rfc3339()),
+            files_indexed: row.files_indexed.clamp(0, u32::MAX as i64) as u32,
+            created_at: row.created_at.to_rfc3339(),
+        }
+    }
+}
+
+const REPOSITORY_COLUMNS: &str = "id, user_id, workspace_id, name, provider, url, branch, status, \
+     last_sync, files_indexed, created_at";
+
+#[async_trait::async_trait]
+impl RepositoryStore for PostgresStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<RepositoryRecord>> {
+        let rows: Vec<RepositoryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM repositories WHERE {} ORDER BY created_at",
+            REPOSITORY_COLUMNS, BY_OWNER
+        ))
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<RepositoryRecord>> {
+        let row: Option<RepositoryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM repositories WHERE {}",
+            REPOSITORY_COLUMNS, BY_ID_AND_OWNER
+        ))
+        .bind(id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(Into::into))
+    }
+
+    async fn insert(&self, repository: &RepositoryRecord) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO repositories ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            REPOSITORY_COLUMNS
+        ))
+        .bind(&repository.id)
+        .bind(&repository.user_id)
+        .bind(&repository.workspace_id)
+        .bind(&repository.name)
+        .bind(&repository.provider)
+        .bind(&repository.url)
+        .bind(&repository.branch)
+        .bind(&repository.status)
+        .bind(parse_optional_timestamp(repository.last_sync.as_deref())?)
+        .bind(repository.files_indexed as i64)
+        .bind(parse_timestamp(&repository.created_at)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        self.delete_owned("repositories", owner, id).await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct UrlRow {
+    id: String,
+    user_id: String,
+    workspace_id: Option<String>,
+    url: String,
+    title: String,
+    description: Option<String>,
+    tags: Vec<String>,
+    status: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<UrlRow> for UrlRecord {
+    fn from(row: UrlRow) -> Self {
+        Self {
+            id: row.id,
+            user_id: row.user_id,
+            workspace_id: row.workspace_id,
+            url: row.url,
+            title: row.title,
+            description: row.description,
+            tags: row.tags,
+            status: row.status,
+            created_at: row.created_at.to_rfc3339(),
+            updated_at: row.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+const URL_COLUMNS: &str =
+    "id, user_id, workspace_id, url, title, description, tags, status, created_at, updated_at";
+
+#[async_trait::async_trait]
+impl UrlStore for PostgresStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<UrlRecord>> {
+        let rows: Vec<UrlRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM urls WHERE {} ORDER BY created_at",
+            URL_COLUMNS, BY_OWNER
+        ))
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<UrlRecord>> {
+        let row: Option<UrlRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM urls WHERE {}",
+            URL_COLUMNS, BY_ID_AND_OWNER
+        ))
+        .bind(id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(Into::into))
+    }
+
+    async fn insert(&self, url: &UrlRecord) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO urls ({}) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            URL_COLUMNS
+        ))
+        .bind(&url.id)
+        .bind(&url.user_id)
+        .bind(&url.workspace_id)
+        .bind(&url.url)
+        .bind(&url.title)
+        .bind(&url.description)
+        .bind(&url.tags)
+        .bind(&url.status)
+        .bind(parse_timestamp(&url.created_at)?)
+        .bind(parse_timestamp(&url.updated_at)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        self.delete_owned("urls", owner, id).await
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct WebhookDeliveryRow {
+    id: String,
+    provider: String,
+    delivery_id: String,
+    event: Option<String>,
+    headers: Json<HashMap<String, String>>,
+    payload: Json<serde_json::Value>,
+    verified_sources: Json<Vec<Source>>,
+    status: String,
+    attempts: i32,
+    last_error: Option<String>,
+    next_attempt_at: DateTime<Utc>,
+    received_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<WebhookDeliveryRow> for WebhookDelivery {
+    fn from(row: WebhookDeliveryRow) -> Self {
+        Self {
+            id: row.id,
+            provider: row.provider,
+            delivery_id: row.delivery_id,
+            event: row.event,
+            headers: row.headers.0,
+            payload: row.payload.0,
+            verified_sources: row.verified_sources.0,
+            status: row.status,
+            attempts: row.attempts.max(0) as u32,
+            last_error: row.last_error,
+            next_attempt_at: row.next_attempt_at.to_rfc3339(),
+            received_at: row.received_at.to_rfc3339(),
+            delivered_at: row.delivered_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+const WEBHOOK_COLUMNS: &str = "id, provider, delivery_id, event, headers, payload, \
+     verified_sources, status, attempts, last_error, next_attempt_at, received_at, delivered_at";
+
+#[async_trait::async_trait]
+impl WebhookStore for PostgresStore {
+    async fn insert(&self, delivery: &WebhookDelivery) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            "INSERT INTO webhook_deliveries ({}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) \
+             ON CONFLICT (provider, delivery_id) DO NOTHING",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(&delivery.id)
+        .bind(&delivery.provider)
+        .bind(&delivery.delivery_id)
+        .bind(&delivery.event)
+        .bind(Json(&delivery.headers))
+        .bind(Json(&delivery.payload))
+        .bind(Json(&delivery.verified_sources))
+        .bind(&delivery.status)
+        .bind(delivery.attempts as i32)
+        .bind(&delivery.last_error)
+        .bind(parse_timestamp(&delivery.next_attempt_at)?)
+        .bind(parse_timestamp(&delivery.received_at)?)
+        .bind(parse_optional_timestamp(delivery.delivered_at.as_deref())?)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn list(&self, status: Option<&str>, limit: usize) -> Result<Vec<WebhookDelivery>> {
+        let rows: Vec<WebhookDeliveryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhook_deliveries WHERE $1::TEXT IS NULL OR status = $1 \
+             ORDER BY received_at DESC LIMIT $2",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(status)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn get(&self, id: &str) -> Result<Option<WebhookDelivery>> {
+        let row: Option<WebhookDeliveryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhook_deliveries WHERE id = $1",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(row.map(Into::into))
+    }
+
+    async fn claim_due(&self, limit: usize, lease: Duration) -> Result<Vec<WebhookDelivery>> {
+        // SKIP LOCKED lets several replicas run workers over the same inbox
+        let rows: Vec<WebhookDeliveryRow> = sqlx::query_as(&format!(
+            "UPDATE webhook_deliveries \
+             SET attempts = attempts + 1, next_attempt_at = now() + make_interval(secs => $3) \
+             WHERE id IN (\
+                 SELECT id FROM webhook_deliveries \
+                 WHERE status = $1 AND next_attempt_at <= now() \
+                 ORDER BY next_attempt_at LIMIT $2 FOR UPDATE SKIP LOCKED\
+             ) RETURNING {}",
+            WEBHOOK_COLUMNS
+        ))
+        .bind(DELIVERY_PENDING)
+        .bind(limit as i64)
+        .bind(lease.as_secs_f64())
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn mark_delivered(&self, id: &str) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries SET status = $2, last_error = NULL, delivered_at = now() \
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(DELIVERY_DELIVERED)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_failed(&self, id: &str, error: &str, retry_at: Option<DateTime<Utc>>) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_deliveries \
+             SET status = CASE WHEN $3::TIMESTAMPTZ IS NULL THEN $4 ELSE status END, \
+                 next_attempt_at = COALESCE($3, next_attempt_at), last_error = $2 \
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(error)
+        .bind(retry_at)
+        .bind(DELIVERY_FAILED)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn redeliver(&self, id: &str) -> Result<bool> {
+        let result = sqlx::query(
+            "UPDATE webhook_deliveries \
+             SET status = $2, attempts = 0, last_error = NULL, next_attempt_at = now(), delivered_at = NULL \
+             WHERE id = $1",
+        )
+        .bind(id)
+        .bind(DELIVERY_PENDING)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+#[derive(sqlx::FromRow)]
+struct SubscriptionRow {
+    id: String,
+    user_id: String,
+    workspace_id: Option<String>,
+    url: String,
+    event_types: Vec<String>,
+    secret: String,
+    enabled: bool,
+    consecutive_failures: i32,
+    disabled_reason: Option<String>,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+const SUBSCRIPTION_COLUMNS: &str = "id, user_id, workspace_id, url, event_types, secret, enabled, \
+     consecutive_failures, disabled_reason, created_at, updated_at";
+
+#[derive(sqlx::FromRow)]
+struct SubscriptionDeliveryRow {
+    id: String,
+    subscription_id: String,
+    event_type: String,
+    payload: Json<serde_json::Value>,
+    status: String,
+    attempts: i32,
+    response_status: Option<i32>,
+    last_error: Option<String>,
+    next_attempt_at: DateTime<Utc>,
+    created_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+impl From<SubscriptionDeliveryRow> for SubscriptionDelivery {
+    fn from(row: SubscriptionDeliveryRow) -> Self {
+        Self {
+            id: row.id,
+            subscription_id: row.subscription_id,
+            event_type: row.event_type,
+            payload: row.payload.0,
+            status: row.status,
+            attempts: row.attempts.max(0) as u32,
+            response_status: row.response_status.and_then(|s| u16::try_from(s).ok()),
+            last_error: row.last_error,
+            next_attempt_at: row.next_attempt_at.to_rfc3339(),
+            created_at: row.created_at.to_rfc3339(),
+            delivered_at: row.delivered_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+const SUBSCRIPTION_DELIVERY_COLUMNS: &str = "id, subscription_id, event_type, payload, status, \
+     attempts, response_status, last_error, next_attempt_at, created_at, delivered_at";
+
+#[async_trait::async_trait]
+impl WebhookSubscriptionStore for PostgresStore {
+    async fn list(&self, owner: &Owner) -> Result<Vec<WebhookSubscription>> {
+        let rows: Vec<SubscriptionRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhook_subscriptions WHERE {} ORDER BY created_at",
+            SUBSCRIPTION_COLUMNS, BY_OWNER
+        ))
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter().map(|row| self.subscription_from_row(row)).collect()
+    }
+
+    async fn get(&self, owner: &Owner, id: &str) -> Result<Option<WebhookSubscription>> {
+        let row: Option<SubscriptionRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhook_subscriptions WHERE {}",
+            SUBSCRIPTION_COLUMNS, BY_ID_AND_OWNER
+        ))
+        .bind(id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .fetch_optional(&self.pool)
+        .await?;
+        row.map(|row| self.subscription_from_row(row)).transpose()
+    }
+
+    async fn insert(&self, subscription: &WebhookSubscription) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO webhook_subscriptions ({}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            SUBSCRIPTION_COLUMNS
+        ))
+        .bind(&subscription.id)
+        .bind(&subscription.user_id)
+        .bind(&subscription.workspace_id)
+        .bind(&subscription.url)
+        .bind(&subscription.event_types)
+        .bind(self.cipher.seal(&subscription.secret)?)
+        .bind(subscription.enabled)
+        .bind(subscription.consecutive_failures as i32)
+        .bind(&subscription.disabled_reason)
+        .bind(parse_timestamp(&subscription.created_at)?)
+        .bind(parse_timestamp(&subscription.updated_at)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn update(&self, owner: &Owner, subscription: &WebhookSubscription) -> Result<bool> {
+        let result = sqlx::query(&format!(
+            "UPDATE webhook_subscriptions SET url = $4, event_types = $5, secret = $6, updated_at = $7 \
+             WHERE {}",
+            BY_ID_AND_OWNER
+        ))
+        .bind(&subscription.id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .bind(&subscription.url)
+        .bind(&subscription.event_types)
+        .bind(self.cipher.seal(&subscription.secret)?)
+        .bind(parse_timestamp(&subscription.updated_at)?)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn set_enabled(&self, owner: &Owner, id: &str, enabled: bool) -> Result<bool> {
+        // Right-hand sides see the row as it was before the update
+        let result = sqlx::query(&format!(
+            "UPDATE webhook_subscriptions SET enabled = $4, \
+             consecutive_failures = CASE WHEN $4 AND NOT enabled THEN 0 ELSE consecutive_failures END, \
+             disabled_reason = CASE WHEN $4 AND NOT enabled THEN NULL ELSE disabled_reason END, \
+             updated_at = now() WHERE {}",
+            BY_ID_AND_OWNER
+        ))
+        .bind(id)
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .bind(enabled)
+        .execute(&self.pool)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn delete(&self, owner: &Owner, id: &str) -> Result<bool> {
+        // Deliveries go with the subscription (ON DELETE CASCADE)
+        self.delete_owned("webhook_subscriptions", owner, id).await
+    }
+
+    async fn subscribers(&self, owner: &Owner, event_type: &str) -> Result<Vec<WebhookSubscription>> {
+        let rows: Vec<SubscriptionRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhook_subscriptions WHERE {} AND enabled AND $3 = ANY(event_types)",
+            SUBSCRIPTION_COLUMNS, BY_OWNER
+        ))
+        .bind(&owner.user_id)
+        .bind(&owner.workspace_id)
+        .bind(event_type)
+        .fetch_all(&self.pool)
+        .await?;
+        rows.into_iter().map(|row| self.subscription_from_row(row)).collect()
+    }
+
+    async fn insert_delivery(&self, delivery: &SubscriptionDelivery) -> Result<()> {
+        sqlx::query(&format!(
+            "INSERT INTO webhook_subscription_deliveries ({}) \
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+            SUBSCRIPTION_DELIVERY_COLUMNS
+        ))
+        .bind(&delivery.id)
+        .bind(&delivery.subscription_id)
+        .bind(&delivery.event_type)
+        .bind(Json(&delivery.payload))
+        .bind(&delivery.status)
+        .bind(delivery.attempts as i32)
+        .bind(delivery.response_status.map(i32::from))
+        .bind(&delivery.last_error)
+        .bind(parse_timestamp(&delivery.next_attempt_at)?)
+        .bind(parse_timestamp(&delivery.created_at)?)
+        .bind(parse_optional_timestamp(delivery.delivered_at.as_deref())?)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn deliveries(&self, subscription_id: &str, limit: usize) -> Result<Vec<SubscriptionDelivery>> {
+        let rows: Vec<SubscriptionDeliveryRow> = sqlx::query_as(&format!(
+            "SELECT {} FROM webhook_subscription_deliveries WHERE subscription_id = $1 \
+             ORDER BY created_at DESC LIMIT $2",
+            SUBSCRIPTION_DELIVERY_COLUMNS
+        ))
+        .bind(subscription_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await?;
+        Ok(rows.into_iter().map(Into::into).collect())
+    }
+
+    async fn claim_due(
+        &self,
+        limit: usize,
+        lease: Duration,
+    ) -> Result<Vec<(SubscriptionDelivery, WebhookSubscription)>> {
+        let deliveries: Vec<SubscriptionDeliveryRow> = sqlx::query_as(&format!(
+            "UPDATE webhook_subscription_deliveries \
+             SET attempts = attempts + 1, next_attempt_at = now() + make_interval(secs => $3) \
+             WHERE id IN (\
+                 SELECT d.id FROM webhook_subscription_deliveries d \
+                 JOIN webhook_subscriptions s ON s.id = d.subscription_id \
+                 WHERE d.status = $1 AND d.next_attempt_at <= now() AND s.enabled \
+                 ORDER BY d.next_attempt_at LIMIT $2 FOR UPDATE OF d SKIP LOCKED\
+             ) RETURNING {}",
+            SUBSCRIPTION_DELIVERY_COLUMNS
+        ))
+        .bind(DELIVERY_PENDING)
+        .bind(limit as i64)
+        .bind(lease.as_secs_f64())
+        .fetch_all(&self.pool)
+        .await?;
+        if deliveries.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let ids: Vec<&str> = deliveries.iter().map(|d| d.subscription_id.as_str()).collect();
+        let subscriptions: HashMap<String, WebhookSubscription> = sqlx::query_as::<_, SubscriptionRow>(
+            &format!("SELECT {} FROM webhook_subscriptions WHERE id = ANY($1)", SUBSCRIPTION_COLUMNS),
+        )
+        .bind(&ids)
+        .fetch_all(&self.pool)
+        .await?
+        .into_iter()
+        .map(|row| Ok((row.id.clone(), self.subscription_from_row(row)?)))
+        .collect::<Result<_>>()?;
+
+        Ok(deliveries
+            .into_iter()
+            .filter_map(|row| {
+                let subscription = subscriptions.get(&row.subscription_id)?.clone();
+                Some((row.into(), subscription))
+            })
+            .collect())
+    }
+
+    async fn mark_delivered(&self, delivery_id: &str, response_status: u16) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_subscription_deliveries \
+             SET status = $2, response_status = $3, last_error = NULL, delivered_at = now() \
+             WHERE id = $1",
+        )
+        .bind(delivery_id)
+        .bind(DELIVERY_DELIVERED)
+        .bind(i32::from(response_status))
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn mark_failed(
+        &self,
+        delivery_id: &str,
+        response_status: Option<u16>,
+        error: &str,
+        retry_at: Option<DateTime<Utc>>,
+    ) -> Result<()> {
+        sqlx::query(
+            "UPDATE webhook_subscription_deliveries \
+             SET status = CASE WHEN $4::TIMESTAMPTZ IS NULL THEN $5 ELSE status END, \
+                 next_attempt_at = COALESCE($4, next_attempt_at), \
+                 response_status = $2, last_error = $3 \
+             WHERE id = $1",
+        )
+        .bind(delivery_id)
+        .bind(response_status.map(i32::from))
+        .bind(error)
+        .bind(retry_at)
+        .bind(DELIVERY_FAILED)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    async fn record_attempt(
+        &self,
+        subscription_id: &str,
+        succeeded: bool,
+        disable_after: u32,
+        disable_reason: &str,
+    ) -> Result<bool> {
+        if succeeded {
+            sqlx::query("UPDATE webhook_subscriptions SET consecutive_failures = 0 WHERE id = $1")
+                .bind(subscription_id)
+                .execute(&self.pool)
+                .await?;
+            return Ok(false);
+        }
+
+        // Disables in the same statement that counts the failure, so
+        // concurrent workers cannot both miss the threshold
+        let disabled: Option<bool> = sqlx::query_scalar(
+            "UPDATE webhook_subscriptions AS s \
+             SET consecutive_failures = s.consecutive_failures + 1, \
+                 enabled = s.enabled AND s.consecutive_failures + 1 < $2, \
+                 disabled_reason = CASE WHEN s.enabled AND s.consecutive_failures + 1 >= $2 \
+                     THEN $3 ELSE s.disabled_reason END, \
+                 updated_at = CASE WHEN s.enabled AND s.consecutive_failures + 1 >= $2 \
+                     THEN now() ELSE s.updated_at END \
+             FROM (SELECT id, enabled AS was_enabled FROM webhook_subscriptions WHERE id = $1 FOR UPDATE) AS prev \
+             WHERE s.id = prev.id \
+             RETURNING prev.was_enabled AND NOT s.enabled",
+        )
+        .bind(subscription_id)
+        .bind(disable_after as i32)
+        .bind(disable_reason)
+        .fetch_optional(&self.pool)
+        .await?;
+        Ok(disabled.unwrap_or(false))
+    }
+}
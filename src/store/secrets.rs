@@ -0,0 +1,129 @@
+//! Encryption of secrets stored in the database
+//!
+//! Values are sealed with AES-256-GCM under `AGENT_KEY_ENCRYPTION_KEY` and
+//! stored as `enc:v1:` followed by the hex of nonce and ciphertext. Columns
+//! written before encryption was introduced still hold plaintext; `open`
+//! passes those through until they are re-encrypted.
+
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Key, Nonce};
+
+use crate::config::ConfigError;
+use crate::error::{AppError, Result};
+
+/// Prefix of sealed values
+const SEALED_PREFIX: &str = "enc:v1:";
+
+/// Length of an AES-GCM nonce in bytes
+const NONCE_BYTES: usize = 12;
+
+/// Seals and opens secrets with a single AES-256-GCM key
+#[derive(Clone)]
+pub struct SecretCipher {
+    cipher: Aes256Gcm,
+}
+
+impl SecretCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self {
+            cipher: Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)),
+        }
+    }
+
+    /// Key given as 64 hex characters
+    pub fn from_hex(key: &str) -> std::result::Result<Self, ConfigError> {
+        let bytes: [u8; 32] = hex::decode(key.trim())
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(|| {
+                ConfigError::InvalidValue("AGENT_KEY_ENCRYPTION_KEY must be 64 hex characters".to_string())
+            })?;
+        Ok(Self::new(&bytes))
+    }
+
+    /// Whether a stored value was written by `seal`
+    pub fn is_sealed(stored: &str) -> bool {
+        stored.starts_with(SEALED_PREFIX)
+    }
+
+    /// Encrypt a secret for storage
+    pub fn seal(&self, plaintext: &str) -> Result<String> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext.as_bytes())
+            .map_err(|_| AppError::Internal("Failed to encrypt secret".to_string()))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&ciphertext);
+        Ok(format!("{}{}", SEALED_PREFIX, hex::encode(sealed)))
+    }
+
+    /// Decrypt a stored secret; unsealed (legacy) values are returned as is
+    pub fn open(&self, stored: &str) -> Result<String> {
+        let Some(encoded) = stored.strip_prefix(SEALED_PREFIX) else {
+            return Ok(stored.to_string());
+        };
+
+        let undecryptable = || AppError::Internal("Failed to decrypt secret".to_string());
+        let sealed = hex::decode(encoded).map_err(|_| undecryptable())?;
+        if sealed.len() < NONCE_BYTES {
+            return Err(undecryptable());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_BYTES);
+        let plaintext = self
+            .cipher
+            .decrypt(Nonce::from_slice(nonce), ciphertext)
+            .map_err(|_| undecryptable())?;
+        String::from_utf8(plaintext).map_err(|_| undecryptable())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cipher(byte: u8) -> SecretCipher {
+        SecretCipher::new(&[byte; 32])
+    }
+
+    #[test]
+    fn sealed_values_round_trip() {
+        let sealed = cipher(1).seal("sk-live-1234").unwrap();
+        assert!(SecretCipher::is_sealed(&sealed));
+        assert!(!sealed.contains("sk-live"));
+        assert_eq!(cipher(1).open(&sealed).unwrap(), "sk-live-1234");
+    }
+
+    #[test]
+    fn each_seal_uses_a_fresh_nonce() {
+        assert_ne!(cipher(1).seal("same").unwrap(), cipher(1).seal("same").unwrap());
+    }
+
+    #[test]
+    fn wrong_key_or_tampering_fails() {
+        let sealed = cipher(1).seal("sk-live-1234").unwrap();
+        assert!(cipher(2).open(&sealed).is_err());
+
+        let mut tampered = sealed.clone();
+        let last = if tampered.ends_with('0') { "1" } else { "0" };
+        tampered.replace_range(tampered.len() - 1.., last);
+        assert!(cipher(1).open(&tampered).is_err());
+
+        assert!(cipher(1).open("enc:v1:00").is_err());
+        assert!(cipher(1).open("enc:v1:not-hex").is_err());
+    }
+
+    #[test]
+    fn legacy_plaintext_passes_through() {
+        assert!(!SecretCipher::is_sealed("sk-live-1234"));
+        assert_eq!(cipher(1).open("sk-live-1234").unwrap(), "sk-live-1234");
+    }
+
+    #[test]
+    fn hex_keys_must_be_32_bytes() {
+        assert!(SecretCipher::from_hex(&"ab".repeat(32)).is_ok());
+        assert!(SecretCipher::from_hex(&"ab".repeat(16)).is_err());
+        assert!(SecretCipher::from_hex("not hex").is_err());
+    }
+}
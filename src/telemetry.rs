@@ -0,0 +1,99 @@
+//! OpenTelemetry tracing for API Backend
+//!
+//! Exports spans over OTLP when `OTEL_EXPORTER_OTLP_ENDPOINT` is set and
+//! propagates W3C trace context (`traceparent` / `tracestate`) across HTTP,
+//! gRPC and Kafka hops.
+
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::TraceError;
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self as sdktrace, Sampler, Tracer};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+/// Build the OTLP export layer from the environment
+///
+/// - `OTEL_EXPORTER_OTLP_ENDPOINT`: collector gRPC endpoint; export is disabled when unset
+/// - `OTEL_SERVICE_NAME`: service name resource (default `api-backend`)
+/// - `OTEL_SAMPLING_RATE`: fraction of new traces to sample (default `1.0`);
+///   requests with a sampled parent are always recorded
+///
+/// The W3C trace context propagator is installed either way.
+pub fn otlp_layer<S>() -> Result<Option<OpenTelemetryLayer<S, Tracer>>, TraceError>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    global::set_text_map_propagator(TraceContextPropagator::new());
+
+    let Ok(endpoint) = std::env::var("OTEL_EXPORTER_OTLP_ENDPOINT") else {
+        return Ok(None);
+    };
+    let service_name = std::env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| "api-backend".to_string());
+    let sampling_rate = std::env::var("OTEL_SAMPLING_RATE")
+        .ok()
+        .and_then(|v| v.parse::<f64>().ok())
+        .unwrap_or(1.0);
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            sdktrace::config()
+                .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(sampling_rate))))
+                .with_resource(Resource::new(vec![KeyValue::new("service.name", service_name)])),
+        )
+        .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer)))
+}
+
+/// Flush pending spans; call before exit
+pub fn shutdown() {
+    global::shutdown_tracer_provider();
+}
+
+/// Trace context for the current span as propagation headers
+pub fn current_trace_headers() -> HashMap<String, String> {
+    let context = tracing::Span::current().context();
+    let mut headers = HashMap::new();
+    global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut headers));
+    headers
+}
+
+/// Add the current trace context to an outgoing HTTP request's headers
+pub fn inject_headers(headers: &mut reqwest::header::HeaderMap) {
+    for (key, value) in current_trace_headers() {
+        if let (Ok(key), Ok(value)) = (
+            reqwest::header::HeaderName::from_bytes(key.as_bytes()),
+            reqwest::header::HeaderValue::from_str(&value),
+        ) {
+            headers.insert(key, value);
+        }
+    }
+}
+
+/// Trace context carried by an incoming request, if any
+pub fn extract_context(headers: &axum::http::HeaderMap) -> Context {
+    global::get_text_map_propagator(|propagator| propagator.extract(&HeaderExtractor(headers)))
+}
+
+struct HeaderExtractor<'a>(&'a axum::http::HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|v| v.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|k| k.as_str()).collect()
+    }
+}
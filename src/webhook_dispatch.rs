@@ -0,0 +1,836 @@
+//! Outbound webhooks to subscribed endpoints
+//!
+//! `WebhookDispatcher::emit` records one delivery per subscription that
+//! wants an event, in the subscription's delivery log. The dispatcher's
+//! worker then POSTs each delivery to its endpoint, signed with the
+//! subscription's secret:
+//!
+//! ```text
+//! X-Confuse-Signature: sha256=hex(HMAC-SHA256(secret, "{X-Confuse-Timestamp}.{body}"))
+//! ```
+//!
+//! Non-2xx answers and transport errors are retried with exponential
+//! backoff. A subscription whose attempts keep failing is disabled; its
+//! pending deliveries wait until it is re-enabled.
+//!
+//! Endpoints are resolved when a delivery is sent, and the request is pinned
+//! to the addresses checked, so a host name that resolves into a private
+//! network is refused even if it did not when the subscription was created.
+//!
+//! Sync events for `POST /v1/sync/:source_id` come from polling the
+//! data-connector job started over HTTP (`watch_sync_job`), or, when the
+//! sync was requested over Kafka, the source's status (`watch_source_sync`).
+//! A sync is only watched when the owner subscribes to a `sync.*` event, at
+//! most once per owner and source, and at most `MAX_SYNC_WATCHES` at a time.
+//! Watches live in memory, so a sync still running when the gateway
+//! restarts produces no event. Syncs started by repository webhooks belong
+//! to no request user and produce no event.
+
+use chrono::Utc;
+use dashmap::DashSet;
+use hmac::{Hmac, Mac};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::clients::DataConnectorClient;
+use crate::config::Config;
+use crate::error::{AppError, Result};
+use crate::metrics::METRICS;
+use crate::models::{
+    JobStatus, Source, SourceStatus, SubscriptionDelivery, WebhookSubscription, DELIVERY_PENDING,
+    EVENT_SYNC_COMPLETED, EVENT_SYNC_FAILED,
+};
+use crate::store::{Owner, WebhookSubscriptionStore};
+
+/// Header naming the event type
+pub const HEADER_EVENT: &str = "X-Confuse-Event";
+
+/// Header carrying the delivery ID (stable across retries)
+pub const HEADER_DELIVERY: &str = "X-Confuse-Delivery";
+
+/// Header carrying the Unix time the request was signed at
+pub const HEADER_TIMESTAMP: &str = "X-Confuse-Timestamp";
+
+/// Header carrying the request signature
+pub const HEADER_SIGNATURE: &str = "X-Confuse-Signature";
+
+/// How often the delivery log is checked for due deliveries
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Deliveries claimed per round trip to the store
+const BATCH_SIZE: usize = 20;
+
+/// How long a claimed delivery is held back from other workers
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(6 * 3600);
+
+/// Longest error message kept in the delivery log
+const MAX_ERROR_LEN: usize = 500;
+
+/// How often a watched sync job's status is fetched
+const JOB_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+/// How long a sync job or source is watched before giving up
+const JOB_WATCH_LIMIT: Duration = Duration::from_secs(6 * 3600);
+
+/// Most syncs watched at once, across all owners
+const MAX_SYNC_WATCHES: usize = 1000;
+
+/// Queues and sends notifications to webhook subscriptions
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    store: Arc<dyn WebhookSubscriptionStore>,
+    http: reqwest::Client,
+    timeout: Duration,
+    max_attempts: u32,
+    retry_base: Duration,
+    disable_after: u32,
+    allow_private_targets: bool,
+    /// Owner and source of every sync being watched
+    watches: Arc<DashSet<(Owner, String)>>,
+}
+
+/// A sync watch in progress; removes itself from the dispatcher's set when
+/// the watching task ends
+struct SyncWatch {
+    watches: Arc<DashSet<(Owner, String)>>,
+    key: (Owner, String),
+}
+
+impl Drop for SyncWatch {
+    fn drop(&mut self) {
+        self.watches.remove(&self.key);
+    }
+}
+
+impl WebhookDispatcher {
+    pub fn new(config: &Config, store: Arc<dyn WebhookSubscriptionStore>) -> Result<Self> {
+        let timeout = Duration::from_secs(config.outbound_webhook_timeout_secs);
+        let http = client_builder(timeout)
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build webhook client: {}", e)))?;
+
+        Ok(Self {
+            store,
+            http,
+            timeout,
+            max_attempts: config.outbound_webhook_max_attempts.max(1),
+            retry_base: Duration::from_secs(config.outbound_webhook_retry_base_secs),
+            disable_after: config.outbound_webhook_disable_after.max(1),
+            allow_private_targets: config.outbound_webhook_allow_private_targets,
+            watches: Arc::new(DashSet::new()),
+        })
+    }
+
+    /// Check that a subscription URL is an absolute HTTPS URL that does not
+    /// point into a private network, unless private targets are allowed
+    ///
+    /// Only literal addresses and `localhost` are recognised here; host
+    /// names are resolved and checked when a delivery is sent.
+    pub fn validate_target(&self, url: &str) -> Result<()> {
+        self.parse_target(url).map(|_| ())
+    }
+
+    fn parse_target(&self, url: &str) -> Result<reqwest::Url> {
+        let parsed = reqwest::Url::parse(url).map_err(|e| invalid_target(&e.to_string()))?;
+
+        match parsed.scheme() {
+            "https" => {}
+            "http" if self.allow_private_targets => {}
+            _ => return Err(invalid_target("must use https")),
+        }
+        let host = parsed.host_str().ok_or_else(|| invalid_target("missing host"))?;
+        if self.allow_private_targets {
+            return Ok(parsed);
+        }
+
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let private = match host.parse::<IpAddr>() {
+            Ok(ip) => is_private_address(ip),
+            Err(_) => {
+                let host = host.to_ascii_lowercase();
+                host == "localhost" || host.ends_with(".localhost")
+            }
+        };
+        if private {
+            return Err(invalid_target("private and loopback addresses are not allowed"));
+        }
+        Ok(parsed)
+    }
+
+    /// A client that can only connect to public addresses of the URL's host
+    ///
+    /// The host is resolved here and the client pinned to the result, so the
+    /// name cannot resolve differently when the request connects.
+    async fn client_for(&self, url: &str) -> Result<reqwest::Client> {
+        let parsed = self.parse_target(url)?;
+        let host = match parsed.domain() {
+            Some(host) if !self.allow_private_targets => host,
+            // Literal addresses were checked by parse_target
+            _ => return Ok(self.http.clone()),
+        };
+        let port = parsed.port_or_known_default().unwrap_or(443);
+
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|e| invalid_target(&format!("cannot resolve {}: {}", host, e)))?
+            .collect();
+        check_resolved(host, &addrs)?;
+
+        client_builder(self.timeout)
+            .resolve_to_addrs(host, &addrs)
+            .build()
+            .map_err(|e| AppError::Internal(format!("Failed to build webhook client: {}", e)))
+    }
+
+    /// Queue an event for each of the owner's subscriptions that wants it;
+    /// returns how many deliveries were queued
+    pub async fn emit(&self, owner: &Owner, event_type: &str, data: Value) -> Result<usize> {
+        let subscriptions = self.store.subscribers(owner, event_type).await?;
+        if subscriptions.is_empty() {
+            return Ok(0);
+        }
+
+        let now = Utc::now().to_rfc3339();
+        let payload = json!({
+            "id": uuid::Uuid::new_v4().to_string(),
+            "type": event_type,
+            "created_at": now,
+            "workspace_id": owner.workspace_id,
+            "data": data,
+        });
+
+        for subscription in &subscriptions {
+            self.store
+                .insert_delivery(&SubscriptionDelivery {
+                    id: uuid::Uuid::new_v4().to_string(),
+                    subscription_id: subscription.id.clone(),
+                    event_type: event_type.to_string(),
+                    payload: payload.clone(),
+                    status: DELIVERY_PENDING.to_string(),
+                    attempts: 0,
+                    response_status: None,
+                    last_error: None,
+                    next_attempt_at: now.clone(),
+                    created_at: now.clone(),
+                    delivered_at: None,
+                })
+                .await?;
+        }
+        tracing::debug!(event_type, subscriptions = subscriptions.len(), "Webhook event queued");
+        Ok(subscriptions.len())
+    }
+
+    /// Queue an event, logging instead of failing; for handlers whose own
+    /// operation has already succeeded
+    pub async fn emit_or_log(&self, owner: &Owner, event_type: &str, data: Value) {
+        if let Err(e) = self.emit(owner, event_type, data).await {
+            tracing::warn!(event_type, user_id = %owner.user_id, error = %e, "Failed to queue webhook event");
+        }
+    }
+
+    /// Start watching a sync of one of the owner's sources; `None` when the
+    /// owner has no subscription to sync events, the source is already
+    /// watched for the owner, or `MAX_SYNC_WATCHES` syncs are watched
+    async fn start_watch(&self, owner: &Owner, source_id: &str) -> Option<SyncWatch> {
+        let mut subscribed = false;
+        for event_type in [EVENT_SYNC_COMPLETED, EVENT_SYNC_FAILED] {
+            match self.store.subscribers(owner, event_type).await {
+                Ok(subscriptions) if !subscriptions.is_empty() => {
+                    subscribed = true;
+                    break;
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    tracing::warn!(user_id = %owner.user_id, error = %e, "Cannot check sync event subscriptions; sync not watched");
+                    return None;
+                }
+            }
+        }
+        if !subscribed {
+            return None;
+        }
+
+        let key = (owner.clone(), source_id.to_string());
+        if !self.watches.insert(key.clone()) {
+            tracing::debug!(source_id, "Sync already watched");
+            return None;
+        }
+        let watch = SyncWatch { watches: self.watches.clone(), key };
+        if self.watches.len() > MAX_SYNC_WATCHES {
+            tracing::warn!(source_id, "Too many syncs watched; no sync event will be sent");
+            return None;
+        }
+        Some(watch)
+    }
+
+    /// Poll a sync job until it finishes, then emit `sync.completed` or
+    /// `sync.failed` to the owner's subscriptions
+    pub async fn watch_sync_job(
+        &self,
+        data_connector: Arc<DataConnectorClient>,
+        owner: Owner,
+        source_id: String,
+        job_id: String,
+    ) {
+        let Some(watch) = self.start_watch(&owner, &source_id).await else {
+            return;
+        };
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            let _watch = watch;
+            let started = tokio::time::Instant::now();
+            while started.elapsed() < JOB_WATCH_LIMIT {
+                tokio::time::sleep(JOB_POLL_INTERVAL).await;
+                let job = match data_connector.get_job_status(&job_id).await {
+                    Ok(job) => job,
+                    Err(e) => {
+                        tracing::debug!(job_id = %job_id, error = %e, "Sync job status unavailable");
+                        continue;
+                    }
+                };
+                let event_type = match job.status {
+                    JobStatus::Completed => EVENT_SYNC_COMPLETED,
+                    JobStatus::Failed | JobStatus::Cancelled => EVENT_SYNC_FAILED,
+                    JobStatus::Queued | JobStatus::Running => continue,
+                };
+                let data = json!({ "source_id": source_id, "job": job });
+                dispatcher.emit_or_log(&owner, event_type, data).await;
+                return;
+            }
+            tracing::warn!(job_id = %job_id, source_id = %source_id, "Stopped watching sync job that did not finish");
+        });
+    }
+
+    /// Poll a source whose sync was requested over Kafka until the sync
+    /// finishes, then emit `sync.completed` or `sync.failed`
+    ///
+    /// `before` is the source as it was when the sync was requested.
+    pub async fn watch_source_sync(&self, data_connector: Arc<DataConnectorClient>, owner: Owner, before: Source) {
+        let Some(watch) = self.start_watch(&owner, &before.id).await else {
+            return;
+        };
+        let dispatcher = self.clone();
+        tokio::spawn(async move {
+            let _watch = watch;
+            let started = tokio::time::Instant::now();
+            let mut syncing = false;
+            while started.elapsed() < JOB_WATCH_LIMIT {
+                tokio::time::sleep(JOB_POLL_INTERVAL).await;
+                let source = match data_connector.get_source(&owner.user_id, &before.id).await {
+                    Ok(source) => source,
+                    Err(e) => {
+                        tracing::debug!(source_id = %before.id, error = %e, "Source status unavailable");
+                        continue;
+                    }
+                };
+                let Some(event_type) = source_sync_outcome(&before, &source, &mut syncing) else {
+                    continue;
+                };
+                let data = json!({ "source_id": before.id, "source": source });
+                dispatcher.emit_or_log(&owner, event_type, data).await;
+                return;
+            }
+            tracing::warn!(source_id = %before.id, "Stopped watching source sync that did not finish");
+        });
+    }
+
+    /// Send due deliveries until the process exits
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process_due().await {
+                    tracing::warn!(error = %e, "Outbound webhook poll failed");
+                }
+            }
+        })
+    }
+
+    /// Send every delivery that is due; returns how many were attempted
+    pub async fn process_due(&self) -> Result<usize> {
+        let mut attempted = 0;
+        loop {
+            let claimed = self.store.claim_due(BATCH_SIZE, CLAIM_LEASE).await?;
+            for (delivery, subscription) in &claimed {
+                // The claim lease brings the delivery back if its outcome was not recorded
+                if let Err(e) = self.attempt(delivery, subscription).await {
+                    tracing::warn!(
+                        id = %delivery.id,
+                        subscription_id = %subscription.id,
+                        error = %e,
+                        "Failed to record webhook delivery outcome"
+                    );
+                }
+            }
+            attempted += claimed.len();
+            if claimed.len() < BATCH_SIZE {
+                return Ok(attempted);
+            }
+        }
+    }
+
+    async fn attempt(&self, delivery: &SubscriptionDelivery, subscription: &WebhookSubscription) -> Result<()> {
+        let (response_status, error) = match self.send(delivery, subscription).await {
+            Ok(status) if (200..300).contains(&status) => {
+                self.store.mark_delivered(&delivery.id, status).await?;
+                self.store.record_attempt(&subscription.id, true, self.disable_after, "").await?;
+                METRICS.record_outbound_webhook("delivered");
+                tracing::debug!(id = %delivery.id, subscription_id = %subscription.id, status, "Webhook sent");
+                return Ok(());
+            }
+            Ok(status) => (Some(status), format!("Endpoint answered {}", status)),
+            Err(e) => (None, truncate(&e, MAX_ERROR_LEN)),
+        };
+
+        let retry_at = (delivery.attempts < self.max_attempts).then(|| {
+            Utc::now() + chrono::Duration::from_std(self.backoff(delivery.attempts)).unwrap_or_default()
+        });
+        self.store.mark_failed(&delivery.id, response_status, &error, retry_at).await?;
+        METRICS.record_outbound_webhook(if retry_at.is_some() { "retry" } else { "failed" });
+
+        let reason = format!("Disabled after {} failed attempts in a row: {}", self.disable_after, error);
+        if self.store.record_attempt(&subscription.id, false, self.disable_after, &reason).await? {
+            METRICS.record_outbound_webhook("disabled");
+            tracing::warn!(
+                subscription_id = %subscription.id,
+                user_id = %subscription.user_id,
+                url = %subscription.url,
+                "Webhook subscription disabled after repeated failures"
+            );
+        }
+
+        tracing::info!(
+            id = %delivery.id,
+            subscription_id = %subscription.id,
+            attempts = delivery.attempts,
+            retry_at = ?retry_at.map(|t| t.to_rfc3339()),
+            error = %error,
+            "Webhook delivery failed"
+        );
+        Ok(())
+    }
+
+    /// POST a delivery; returns the response status, or why it was not sent
+    async fn send(
+        &self,
+        delivery: &SubscriptionDelivery,
+        subscription: &WebhookSubscription,
+    ) -> std::result::Result<u16, String> {
+        let client = self.client_for(&subscription.url).await.map_err(|e| e.to_string())?;
+        let body = serde_json::to_vec(&delivery.payload).unwrap_or_default();
+        let timestamp = Utc::now().timestamp().to_string();
+        let response = client
+            .post(&subscription.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(HEADER_EVENT, &delivery.event_type)
+            .header(HEADER_DELIVERY, &delivery.id)
+            .header(HEADER_TIMESTAMP, &timestamp)
+            .header(HEADER_SIGNATURE, sign(&subscription.secret, &timestamp, &body))
+            .body(body)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(response.status().as_u16())
+    }
+
+    /// Wait after the given number of failed attempts
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.retry_base.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+}
+
+/// Settings shared by every client that sends deliveries
+fn client_builder(timeout: Duration) -> reqwest::ClientBuilder {
+    reqwest::Client::builder()
+        .timeout(timeout)
+        // Redirects are not followed: the new target's addresses are never checked
+        .redirect(reqwest::redirect::Policy::none())
+        // A proxy would resolve the host itself, bypassing the address check
+        .no_proxy()
+}
+
+/// Refuse a host unless every address it resolved to is public
+fn check_resolved(host: &str, addrs: &[SocketAddr]) -> Result<()> {
+    if addrs.is_empty() {
+        return Err(invalid_target(&format!("{} has no addresses", host)));
+    }
+    if let Some(addr) = addrs.iter().find(|addr| is_private_address(addr.ip())) {
+        return Err(invalid_target(&format!("{} resolves to private address {}", host, addr.ip())));
+    }
+    Ok(())
+}
+
+fn invalid_target(reason: &str) -> AppError {
+    AppError::ValidationError(format!("Invalid webhook URL: {}", reason))
+}
+
+/// Event for a source sync that has finished, judged against the source as
+/// it was when the sync was requested; `syncing` remembers whether the sync
+/// has been seen running
+fn source_sync_outcome(before: &Source, now: &Source, syncing: &mut bool) -> Option<&'static str> {
+    match now.status {
+        SourceStatus::Syncing => {
+            *syncing = true;
+            None
+        }
+        SourceStatus::Synced if *syncing || now.last_sync != before.last_sync => Some(EVENT_SYNC_COMPLETED),
+        SourceStatus::Failed | SourceStatus::Disconnected if *syncing => Some(EVENT_SYNC_FAILED),
+        _ => None,
+    }
+}
+
+/// `X-Confuse-Signature` value for a request body
+pub fn sign(secret: &str, timestamp: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(timestamp.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Whether an address is loopback, private, link-local or otherwise not
+/// publicly routable, including IPv4 addresses embedded in IPv6 ones
+fn is_private_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                // 0.0.0.0/8 ("this network")
+                || a == 0
+                // 100.64.0.0/10 (carrier-grade NAT)
+                || (a == 100 && (b & 0xc0) == 64)
+                // 192.0.0.0/24 (IETF protocol assignments)
+                || (a == 192 && b == 0 && c == 0)
+                // 198.18.0.0/15 (benchmarking)
+                || (a == 198 && (b & 0xfe) == 18)
+                // 240.0.0.0/4 (reserved) and the broadcast address
+                || a >= 240
+        }
+        IpAddr::V6(v6) => match embedded_ipv4(&v6) {
+            Some(v4) => is_private_address(IpAddr::V4(v4)),
+            None => {
+                let first = v6.segments()[0];
+                v6.is_loopback()
+                    || v6.is_unspecified()
+                    // fc00::/7 (unique local) and fe80::/10 (link local)
+                    || (first & 0xfe00) == 0xfc00
+                    || (first & 0xffc0) == 0xfe80
+            }
+        },
+    }
+}
+
+/// The IPv4 address an IPv6 address reaches: IPv4-mapped (`::ffff:a.b.c.d`),
+/// IPv4-compatible (`::a.b.c.d`), NAT64 (`64:ff9b::/96`) and 6to4 (`2002::/16`)
+fn embedded_ipv4(v6: &Ipv6Addr) -> Option<Ipv4Addr> {
+    let s = v6.segments();
+    let v4 = |high: u16, low: u16| {
+        let [a, b] = high.to_be_bytes();
+        let [c, d] = low.to_be_bytes();
+        Ipv4Addr::new(a, b, c, d)
+    };
+    match s {
+        [0, 0, 0, 0, 0, 0xffff, high, low] => Some(v4(high, low)),
+        // :: and ::1 are left to the IPv6 checks
+        [0, 0, 0, 0, 0, 0, 0, 0 | 1] => None,
+        [0, 0, 0, 0, 0, 0, high, low] => Some(v4(high, low)),
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => Some(v4(high, low)),
+        [0x2002, high, low, ..] => Some(v4(high, low)),
+        _ => None,
+    }
+}
+
+fn truncate(message: &str, max: usize) -> String {
+    match message.char_indices().nth(max) {
+        Some((end, _)) => format!("{}…", &message[..end]),
+        None => message.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store::InMemoryStore;
+
+    fn dispatcher(store: Arc<InMemoryStore>, allow_private_targets: bool) -> WebhookDispatcher {
+        WebhookDispatcher {
+            store,
+            http: reqwest::Client::new(),
+            timeout: Duration::from_secs(1),
+            max_attempts: 100,
+            retry_base: Duration::ZERO,
+            disable_after: 3,
+            allow_private_targets,
+            watches: Arc::new(DashSet::new()),
+        }
+    }
+
+    fn owner() -> Owner {
+        Owner { user_id: "user-1".to_string(), workspace_id: Some("ws-1".to_string()) }
+    }
+
+    fn subscription(url: &str) -> WebhookSubscription {
+        let now = Utc::now().to_rfc3339();
+        WebhookSubscription {
+            id: "sub-1".to_string(),
+            user_id: "user-1".to_string(),
+            workspace_id: Some("ws-1".to_string()),
+            url: url.to_string(),
+            event_types: vec![EVENT_SYNC_COMPLETED.to_string()],
+            secret: "whsec-test-secret".to_string(),
+            enabled: true,
+            consecutive_failures: 0,
+            disabled_reason: None,
+            created_at: now.clone(),
+            updated_at: now,
+        }
+    }
+
+    #[test]
+    fn signatures_match_a_known_vector() {
+        assert_eq!(
+            sign("whsec-test-secret", "1700000000", br#"{"type":"source.deleted"}"#),
+            "sha256=6c4a70bead50d17be0c768294694327e20d2480736c8aebea39727487c1235b3"
+        );
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let dispatcher = WebhookDispatcher {
+            retry_base: Duration::from_secs(10),
+            ..dispatcher(Arc::new(InMemoryStore::new()), false)
+        };
+        assert_eq!(dispatcher.backoff(1), Duration::from_secs(10));
+        assert_eq!(dispatcher.backoff(2), Duration::from_secs(20));
+        assert_eq!(dispatcher.backoff(5), Duration::from_secs(160));
+        assert_eq!(dispatcher.backoff(12), Duration::from_secs(10 * 2048));
+        assert_eq!(dispatcher.backoff(13), MAX_BACKOFF);
+        assert_eq!(dispatcher.backoff(u32::MAX), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn private_and_insecure_targets_are_rejected() {
+        let dispatcher = dispatcher(Arc::new(InMemoryStore::new()), false);
+        assert!(dispatcher.validate_target("https://hooks.example.com/confuse").is_ok());
+        assert!(dispatcher.validate_target("https://93.184.216.34/hook").is_ok());
+
+        for url in [
+            "http://hooks.example.com/confuse",
+            "ftp://hooks.example.com/confuse",
+            "not a url",
+            "https://localhost/hook",
+            "https://api.localhost/hook",
+            "https://127.0.0.1/hook",
+            "https://10.1.2.3/hook",
+            "https://172.16.0.1/hook",
+            "https://192.168.1.1/hook",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/hook",
+            "https://0.0.0.0/hook",
+            "https://[::1]/hook",
+            "https://[fd00::1]/hook",
+            "https://[fe80::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook",
+        ] {
+            assert!(dispatcher.validate_target(url).is_err(), "{} was accepted", url);
+        }
+    }
+
+    #[test]
+    fn private_targets_can_be_allowed() {
+        let dispatcher = dispatcher(Arc::new(InMemoryStore::new()), true);
+        assert!(dispatcher.validate_target("http://localhost:8080/hook").is_ok());
+        assert!(dispatcher.validate_target("https://10.1.2.3/hook").is_ok());
+        assert!(dispatcher.validate_target("ftp://localhost/hook").is_err());
+    }
+
+    #[test]
+    fn host_names_resolving_to_private_addresses_are_refused() {
+        let addr = |s: &str| s.parse::<SocketAddr>().unwrap();
+        assert!(check_resolved("hooks.example.com", &[addr("93.184.216.34:443")]).is_ok());
+
+        assert!(check_resolved("hooks.example.com", &[]).is_err());
+        for private in ["127.0.0.1:443", "10.0.0.8:443", "169.254.169.254:443", "[::1]:443", "[fe80::1]:443"] {
+            // One private address is enough, whatever else the name resolves to
+            let addrs = [addr("93.184.216.34:443"), addr(private)];
+            assert!(check_resolved("rebind.example.com", &addrs).is_err(), "{} was accepted", private);
+        }
+    }
+
+    #[test]
+    fn private_address_ranges() {
+        for (address, private) in [
+            ("93.184.216.34", false),
+            ("8.8.8.8", false),
+            ("0.0.0.0", true),
+            ("0.1.2.3", true),
+            ("10.1.2.3", true),
+            ("100.64.0.1", true),
+            ("100.127.255.254", true),
+            ("100.128.0.1", false),
+            ("127.0.0.1", true),
+            ("169.254.169.254", true),
+            ("172.16.0.1", true),
+            ("192.0.0.8", true),
+            ("192.0.1.1", false),
+            ("192.168.1.1", true),
+            ("198.18.0.1", true),
+            ("198.19.255.254", true),
+            ("198.20.0.1", false),
+            ("240.0.0.1", true),
+            ("255.255.255.255", true),
+            ("2606:2800:220:1:248:1893:25c8:1946", false),
+            ("::", true),
+            ("::1", true),
+            ("fd00::1", true),
+            ("fe80::1", true),
+            // IPv4-mapped
+            ("::ffff:127.0.0.1", true),
+            ("::ffff:93.184.216.34", false),
+            // IPv4-compatible
+            ("::127.0.0.1", true),
+            ("::169.254.169.254", true),
+            ("::93.184.216.34", false),
+            // NAT64
+            ("64:ff9b::7f00:1", true),
+            ("64:ff9b::a9fe:a9fe", true),
+            ("64:ff9b::5db8:d822", false),
+            // 6to4
+            ("2002:7f00:1::", true),
+            ("2002:a00:1::1", true),
+            ("2002:5db8:d822::1", false),
+        ] {
+            let ip: IpAddr = address.parse().unwrap();
+            assert_eq!(is_private_address(ip), private, "{}", address);
+        }
+    }
+
+    #[tokio::test]
+    async fn failing_subscriptions_are_disabled_and_reset_when_re_enabled() {
+        let store = Arc::new(InMemoryStore::new());
+        let dispatcher = dispatcher(store.clone(), false);
+        // Refused before any request is made, so every attempt fails
+        store.insert(&subscription("https://localhost/hook")).await.unwrap();
+        assert_eq!(dispatcher.emit(&owner(), EVENT_SYNC_COMPLETED, json!({})).await.unwrap(), 1);
+
+        let get = || async { store.get(&owner(), "sub-1").await.unwrap().unwrap() };
+        for failures in 1..3 {
+            assert_eq!(dispatcher.process_due().await.unwrap(), 1);
+            let subscription = get().await;
+            assert!(subscription.enabled);
+            assert_eq!(subscription.consecutive_failures, failures);
+        }
+
+        assert_eq!(dispatcher.process_due().await.unwrap(), 1);
+        let disabled = get().await;
+        assert!(!disabled.enabled);
+        assert_eq!(disabled.consecutive_failures, 3);
+        assert!(disabled.disabled_reason.unwrap().starts_with("Disabled after 3 failed attempts"));
+        // Deliveries wait while the subscription is disabled
+        assert_eq!(dispatcher.process_due().await.unwrap(), 0);
+        assert!(store.subscribers(&owner(), EVENT_SYNC_COMPLETED).await.unwrap().is_empty());
+
+        assert!(store.set_enabled(&owner(), "sub-1", true).await.unwrap());
+        let enabled = get().await;
+        assert!(enabled.enabled);
+        assert_eq!(enabled.consecutive_failures, 0);
+        assert_eq!(enabled.disabled_reason, None);
+
+        // The count starts over: one more failure does not disable it again
+        assert_eq!(dispatcher.process_due().await.unwrap(), 1);
+        let subscription = get().await;
+        assert!(subscription.enabled);
+        assert_eq!(subscription.consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn a_success_resets_the_failure_count() {
+        let store = Arc::new(InMemoryStore::new());
+        store.insert(&subscription("https://hooks.example.com/confuse")).await.unwrap();
+        assert!(!store.record_attempt("sub-1", false, 3, "down").await.unwrap());
+        assert!(!store.record_attempt("sub-1", false, 3, "down").await.unwrap());
+        assert!(!store.record_attempt("sub-1", true, 3, "").await.unwrap());
+        assert!(!store.record_attempt("sub-1", false, 3, "down").await.unwrap());
+        assert_eq!(store.get(&owner(), "sub-1").await.unwrap().unwrap().consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn syncs_are_watched_once_and_only_for_subscribers() {
+        let store = Arc::new(InMemoryStore::new());
+        let dispatcher = dispatcher(store.clone(), false);
+        assert!(dispatcher.start_watch(&owner(), "src-1").await.is_none());
+
+        store.insert(&subscription("https://hooks.example.com/confuse")).await.unwrap();
+        let watch = dispatcher.start_watch(&owner(), "src-1").await.unwrap();
+        assert!(dispatcher.start_watch(&owner(), "src-1").await.is_none());
+        assert!(dispatcher.start_watch(&owner(), "src-2").await.is_some());
+        // Another workspace of the same user has no subscription
+        let elsewhere = Owner { workspace_id: Some("ws-2".to_string()), ..owner() };
+        assert!(dispatcher.start_watch(&elsewhere, "src-1").await.is_none());
+
+        // The source can be watched again once the watch ends
+        drop(watch);
+        assert!(dispatcher.start_watch(&owner(), "src-1").await.is_some());
+
+        assert!(store.set_enabled(&owner(), "sub-1", false).await.unwrap());
+        assert!(dispatcher.start_watch(&owner(), "src-3").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn sync_watches_are_capped() {
+        let store = Arc::new(InMemoryStore::new());
+        store.insert(&subscription("https://hooks.example.com/confuse")).await.unwrap();
+        let dispatcher = dispatcher(store, false);
+
+        let mut watches = Vec::new();
+        for i in 0..MAX_SYNC_WATCHES {
+            watches.push(dispatcher.start_watch(&owner(), &format!("src-{}", i)).await.unwrap());
+        }
+        assert!(dispatcher.start_watch(&owner(), "one-too-many").await.is_none());
+        assert_eq!(dispatcher.watches.len(), MAX_SYNC_WATCHES);
+    }
+
+    #[test]
+    fn source_syncs_finish_when_the_source_settles() {
+        let source = |status: SourceStatus, last_sync: Option<&str>| Source {
+            id: "src-1".to_string(),
+            source_type: crate::models::SourceType::Github,
+            name: "repo".to_string(),
+            status,
+            last_sync: last_sync.map(str::to_string),
+            stats: None,
+            metadata: None,
+        };
+        let before = source(SourceStatus::Synced, Some("2026-10-16T00:00:00Z"));
+
+        // Not picked up yet
+        let mut syncing = false;
+        assert_eq!(source_sync_outcome(&before, &before, &mut syncing), None);
+        // Finished between two polls
+        let synced = source(SourceStatus::Synced, Some("2026-10-17T00:00:00Z"));
+        assert_eq!(source_sync_outcome(&before, &synced, &mut syncing), Some(EVENT_SYNC_COMPLETED));
+
+        let running = source(SourceStatus::Syncing, Some("2026-10-16T00:00:00Z"));
+        assert_eq!(source_sync_outcome(&before, &running, &mut syncing), None);
+        assert!(syncing);
+        let failed = source(SourceStatus::Failed, Some("2026-10-16T00:00:00Z"));
+        assert_eq!(source_sync_outcome(&before, &failed, &mut syncing), Some(EVENT_SYNC_FAILED));
+
+        // A failure left over from an earlier sync is not this sync's outcome
+        let mut syncing = false;
+        assert_eq!(source_sync_outcome(&failed, &failed, &mut syncing), None);
+    }
+}
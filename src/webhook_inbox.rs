@@ -0,0 +1,184 @@
+//! Background forwarding of the webhook inbox
+//!
+//! Webhook handlers only verify and record a delivery before acknowledging
+//! the provider (see `routes::webhooks`). `WebhookWorker` claims deliveries
+//! that are due and forwards them to data-connector, or, when Kafka is
+//! available and the delivery moves a branch that its verified sources follow,
+//! publishes targeted sync events for them (see `webhook_sync`). Failures are retried with
+//! exponential backoff until `WEBHOOK_MAX_ATTEMPTS` is reached; after that
+//! a delivery stays `failed` until an operator redelivers it.
+
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+use crate::clients::DataConnectorClient;
+use crate::config::Config;
+use crate::error::Result;
+use crate::kafka::EventProducer;
+use crate::metrics::METRICS;
+use crate::models::WebhookDelivery;
+use crate::store::WebhookStore;
+use crate::webhook_sync::{publish_sync_events, RepositoryChange};
+
+/// How often the inbox is checked for due deliveries
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Deliveries claimed per round trip to the store
+const BATCH_SIZE: usize = 20;
+
+/// How long a claimed delivery is held back from other workers; if this
+/// worker dies mid-attempt the delivery becomes due again afterwards
+const CLAIM_LEASE: Duration = Duration::from_secs(300);
+
+/// Longest wait between two attempts
+const MAX_BACKOFF: Duration = Duration::from_secs(3600);
+
+/// Forwards recorded webhook deliveries to data-connector or Kafka
+pub struct WebhookWorker {
+    store: Arc<dyn WebhookStore>,
+    data_connector: Arc<DataConnectorClient>,
+    event_producer: Option<Arc<EventProducer>>,
+    max_attempts: u32,
+    retry_base: Duration,
+}
+
+impl WebhookWorker {
+    pub fn new(
+        config: &Config,
+        store: Arc<dyn WebhookStore>,
+        data_connector: Arc<DataConnectorClient>,
+        event_producer: Option<Arc<EventProducer>>,
+    ) -> Self {
+        Self {
+            store,
+            data_connector,
+            event_producer,
+            max_attempts: config.webhook_max_attempts.max(1),
+            retry_base: Duration::from_secs(config.webhook_retry_base_secs),
+        }
+    }
+
+    /// Run the worker until the process exits
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(POLL_INTERVAL);
+            interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+            loop {
+                interval.tick().await;
+                if let Err(e) = self.process_due().await {
+                    tracing::warn!(error = %e, "Webhook inbox poll failed");
+                }
+            }
+        })
+    }
+
+    /// Forward every delivery that is due; returns how many were attempted
+    pub async fn process_due(&self) -> Result<usize> {
+        let mut attempted = 0;
+        loop {
+            let deliveries = self.store.claim_due(BATCH_SIZE, CLAIM_LEASE).await?;
+            for delivery in &deliveries {
+                // The claim lease brings the delivery back if its outcome was not recorded
+                if let Err(e) = self.forward(delivery).await {
+                    tracing::warn!(
+                        id = %delivery.id,
+                        provider = %delivery.provider,
+                        error = %e,
+                        "Failed to record webhook forwarding outcome"
+                    );
+                }
+            }
+            attempted += deliveries.len();
+            if deliveries.len() < BATCH_SIZE {
+                return Ok(attempted);
+            }
+        }
+    }
+
+    async fn forward(&self, delivery: &WebhookDelivery) -> Result<()> {
+        let error = match self.send(delivery).await {
+            Ok(destination) => {
+                self.store.mark_delivered(&delivery.id).await?;
+                METRICS.record_webhook_delivery(&delivery.provider, "delivered");
+                tracing::info!(
+                    id = %delivery.id,
+                    provider = %delivery.provider,
+                    delivery_id = %delivery.delivery_id,
+                    attempts = delivery.attempts,
+                    destination = %destination,
+                    "Webhook delivery forwarded"
+                );
+                return Ok(());
+            }
+            Err(e) => e.to_string(),
+        };
+
+        let retry_at = (delivery.attempts < self.max_attempts).then(|| {
+            Utc::now() + chrono::Duration::from_std(self.backoff(delivery.attempts)).unwrap_or_default()
+        });
+        self.store.mark_failed(&delivery.id, &error, retry_at).await?;
+
+        match retry_at {
+            Some(at) => {
+                METRICS.record_webhook_delivery(&delivery.provider, "retry");
+                tracing::warn!(
+                    id = %delivery.id,
+                    provider = %delivery.provider,
+                    attempts = delivery.attempts,
+                    retry_at = %at.to_rfc3339(),
+                    error = %error,
+                    "Webhook forwarding failed; will retry"
+                );
+            }
+            None => {
+                METRICS.record_webhook_delivery(&delivery.provider, "failed");
+                tracing::error!(
+                    id = %delivery.id,
+                    provider = %delivery.provider,
+                    attempts = delivery.attempts,
+                    error = %error,
+                    "Webhook forwarding failed; giving up until redelivered"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Publish sync events for branch updates when Kafka is available and a
+    /// source follows the branch, otherwise pass the delivery to
+    /// data-connector; returns where it went
+    async fn send(&self, delivery: &WebhookDelivery) -> Result<String> {
+        if let (Some(producer), Some(change)) =
+            (&self.event_producer, RepositoryChange::from_delivery(delivery))
+        {
+            let published = publish_sync_events(&change, delivery, producer).await?;
+            if published > 0 {
+                return Ok(format!("kafka ({} sync events)", published));
+            }
+            tracing::info!(
+                id = %delivery.id,
+                branch = %change.branch,
+                repository = ?change.repository_urls.first(),
+                "No verified source follows the updated branch; forwarding to data-connector"
+            );
+        }
+
+        let headers = delivery
+            .headers
+            .iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        self.data_connector
+            .forward_webhook(&delivery.provider, delivery.payload.clone(), headers)
+            .await?;
+        Ok("data-connector".to_string())
+    }
+
+    /// Wait after the given number of failed attempts
+    fn backoff(&self, attempts: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempts.saturating_sub(1));
+        self.retry_base.saturating_mul(factor).min(MAX_BACKOFF)
+    }
+}
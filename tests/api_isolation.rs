@@ -0,0 +1,248 @@
+//! Tenant isolation for the `/api/*` management routes, served from in-memory stores
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::Response;
+use axum::Router;
+use serde_json::{json, Value};
+use tower::ServiceExt;
+
+use api_backend::middleware::auth::AuthenticatedUser;
+use api_backend::models::User;
+use api_backend::routes::v1::api_routes;
+use api_backend::store::Stores;
+
+/// Caller identity for a test request: user ID and workspace
+type Caller = (&'static str, Option<&'static str>);
+
+const ALICE: Caller = ("alice", Some("acme"));
+const BOB: Caller = ("bob", Some("globex"));
+const ALICE_ELSEWHERE: Caller = ("alice", Some("initech"));
+
+/// Stand-in for `auth_middleware`: trusts `x-test-user` / `x-test-workspace`,
+/// and `x-test-scopes` to act as an API key instead of a `user`
+async fn test_auth(mut request: Request, next: Next) -> Response {
+    let (user_id, workspace_id, scopes) = {
+        let header = |name: &str| {
+            request
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        (header("x-test-user"), header("x-test-workspace"), header("x-test-scopes"))
+    };
+    let (roles, scopes) = match scopes {
+        Some(scopes) => (vec![], scopes.split(',').map(str::to_string).collect()),
+        None => (vec!["user".to_string()], vec![]),
+    };
+    let user = User {
+        id: user_id.expect("test requests carry a user"),
+        email: "user@example.com".to_string(),
+        name: None,
+        picture: None,
+        roles,
+        scopes,
+        workspace_id,
+    };
+    request.extensions_mut().insert(AuthenticatedUser(user));
+    next.run(request).await
+}
+
+fn app() -> Router {
+    api_routes()
+        .layer(axum::middleware::from_fn(test_auth))
+        .with_state(Stores::in_memory())
+}
+
+async fn call(app: &Router, caller: Caller, method: Method, uri: &str, body: Option<Value>) -> (StatusCode, Value) {
+    let mut request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("x-test-user", caller.0);
+    if let Some(workspace) = caller.1 {
+        request = request.header("x-test-workspace", workspace);
+    }
+    let request = match body {
+        Some(body) => request
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(body.to_string())),
+        None => request.body(Body::empty()),
+    }
+    .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+    (status, body)
+}
+
+/// Create a resource as `caller` and return its ID
+async fn create(app: &Router, caller: Caller, uri: &str, body: Value) -> String {
+    let (status, body) = call(app, caller, Method::POST, uri, Some(body)).await;
+    assert_eq!(status, StatusCode::CREATED, "create {} failed: {}", uri, body);
+    body["data"]["id"].as_str().unwrap().to_string()
+}
+
+fn agent_body() -> Value {
+    json!({
+        "name": "Copilot",
+        "agent_type": "copilot",
+        "api_key": "sk-test",
+        "permissions": ["read"],
+        "config": {}
+    })
+}
+
+/// IDs of the records in a list response (documents wrap theirs in `data.data`)
+fn ids(body: &Value) -> Vec<String> {
+    let list = if body["data"].is_array() { &body["data"] } else { &body["data"]["data"] };
+    list.as_array()
+        .unwrap()
+        .iter()
+        .map(|r| r["id"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn records_are_stamped_with_the_caller() {
+    let app = app();
+    let (status, body) = call(&app, ALICE, Method::POST, "/api/urls", Some(json!({ "url": "https://example.com" }))).await;
+
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(body["data"]["user_id"], "alice");
+    assert_eq!(body["data"]["workspace_id"], "acme");
+}
+
+#[tokio::test]
+async fn other_tenants_cannot_list_read_or_delete() {
+    let app = app();
+    let resources = [
+        ("/api/urls", json!({ "url": "https://example.com" })),
+        ("/api/repositories", json!({ "name": "repo", "provider": "github", "url": "https://github.com/a/b" })),
+        ("/api/documents", json!({ "name": "Spec", "doc_type": "pdf", "source": "upload" })),
+        ("/api/agents", agent_body()),
+    ];
+
+    for (collection, body) in resources {
+        let id = create(&app, ALICE, collection, body).await;
+        let item = format!("{}/{}", collection, id);
+
+        for intruder in [BOB, ALICE_ELSEWHERE] {
+            let (status, body) = call(&app, intruder, Method::GET, collection, None).await;
+            assert_eq!(status, StatusCode::OK);
+            assert!(ids(&body).is_empty(), "{:?} sees records in {}", intruder, collection);
+
+            // Documents have no single-record GET
+            if collection != "/api/documents" {
+                let (status, _) = call(&app, intruder, Method::GET, &item, None).await;
+                assert_eq!(status, StatusCode::NOT_FOUND, "{:?} read {}", intruder, item);
+            }
+
+            let (status, _) = call(&app, intruder, Method::DELETE, &item, None).await;
+            assert_eq!(status, StatusCode::NOT_FOUND, "{:?} deleted {}", intruder, item);
+        }
+
+        let (_, body) = call(&app, ALICE, Method::GET, collection, None).await;
+        assert_eq!(ids(&body), vec![id], "owner lost access to {}", item);
+    }
+}
+
+#[tokio::test]
+async fn other_tenants_cannot_update_or_invoke_agents() {
+    let app = app();
+    let id = create(&app, ALICE, "/api/agents", agent_body()).await;
+    let item = format!("/api/agents/{}", id);
+
+    let (status, _) = call(&app, BOB, Method::PUT, &item, Some(json!({ "name": "Hijacked" }))).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    for action in ["test", "invoke", "context"] {
+        let (method, body) = match action {
+            "context" => (Method::GET, None),
+            "invoke" => (Method::POST, Some(json!({ "message": "hi" }))),
+            _ => (Method::POST, None),
+        };
+        let (status, _) = call(&app, BOB, method, &format!("{}/{}", item, action), body).await;
+        assert_eq!(status, StatusCode::NOT_FOUND, "bob could {} alice's agent", action);
+    }
+
+    let (status, body) = call(&app, ALICE, Method::GET, &item, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["data"]["name"], "Copilot");
+}
+
+#[tokio::test]
+async fn agent_api_keys_are_only_returned_masked() {
+    let app = app();
+    let mut body = agent_body();
+    body["api_key"] = json!("sk-test-secret-1234");
+    let (_, created) = call(&app, ALICE, Method::POST, "/api/agents", Some(body)).await;
+    let item = format!("/api/agents/{}", created["data"]["id"].as_str().unwrap());
+    let (_, updated) = call(&app, ALICE, Method::PUT, &item, Some(json!({ "api_key": "sk-rotated-5678" }))).await;
+    let (_, fetched) = call(&app, ALICE, Method::GET, &item, None).await;
+    let (_, listed) = call(&app, ALICE, Method::GET, "/api/agents", None).await;
+
+    for response in [&created, &fetched, &listed] {
+        assert!(!response.to_string().contains("sk-test"), "API key leaked: {}", response);
+    }
+    assert_eq!(created["data"]["api_key_hint"], "****1234");
+    assert_eq!(updated["data"]["api_key_hint"], "****5678");
+    assert_eq!(listed["data"][0]["api_key_hint"], "****5678");
+    assert!(updated["data"].get("api_key").is_none());
+}
+
+#[tokio::test]
+async fn writes_need_the_resources_write_permission() {
+    let app = app();
+    let id = create(&app, ALICE, "/api/urls", json!({ "url": "https://example.com" })).await;
+
+    let send = |scopes: &'static str, method: Method, uri: String, body: Option<Value>| {
+        let mut request = Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("x-test-user", "alice")
+            .header("x-test-workspace", "acme")
+            .header("x-test-scopes", scopes);
+        if body.is_some() {
+            request = request.header(header::CONTENT_TYPE, "application/json");
+        }
+        let body = body.map(|b| Body::from(b.to_string())).unwrap_or_else(Body::empty);
+        app.clone().oneshot(request.body(body).unwrap())
+    };
+
+    // Role names and `admin` are not scopes that grant anything
+    for scopes in ["search:read", "user", "admin"] {
+        let status = send(scopes, Method::POST, "/api/agents".to_string(), Some(agent_body())).await.unwrap().status();
+        assert_eq!(status, StatusCode::FORBIDDEN, "scopes {} could create an agent", scopes);
+        let status = send(scopes, Method::DELETE, format!("/api/urls/{}", id), None).await.unwrap().status();
+        assert_eq!(status, StatusCode::FORBIDDEN, "scopes {} could delete a URL", scopes);
+
+        // Reads are not gated
+        let status = send(scopes, Method::GET, "/api/urls".to_string(), None).await.unwrap().status();
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    let status = send("resources:write", Method::DELETE, format!("/api/urls/{}", id), None).await.unwrap().status();
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn dashboard_counts_only_the_callers_records() {
+    let app = app();
+    create(&app, ALICE, "/api/urls", json!({ "url": "https://a.example" })).await;
+    create(&app, ALICE, "/api/urls", json!({ "url": "https://b.example" })).await;
+    create(&app, BOB, "/api/urls", json!({ "url": "https://c.example" })).await;
+    create(&app, BOB, "/api/agents", agent_body()).await;
+
+    let (_, alice) = call(&app, ALICE, Method::GET, "/api/dashboard/stats", None).await;
+    assert_eq!(alice["urls"], 2);
+    assert_eq!(alice["agents"], 0);
+
+    let (_, bob) = call(&app, BOB, Method::GET, "/api/dashboard/stats", None).await;
+    assert_eq!(bob["urls"], 1);
+    assert_eq!(bob["agents"], 1);
+}
@@ -0,0 +1,179 @@
+//! Circuit breaker state machine tests, driven by a manual clock
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use api_backend::metrics::METRICS;
+use api_backend::middleware::{
+    CacheConfig, CircuitBreakerConfig, CircuitBreakerRegistry, CircuitState, ManualClock, ResponseCache,
+};
+
+const SERVICE: &str = "relation-graph";
+
+fn registry(config: CircuitBreakerConfig) -> (CircuitBreakerRegistry, ManualClock) {
+    let clock = ManualClock::new(1_000_000);
+    let registry = CircuitBreakerRegistry::new(config).with_clock(Arc::new(clock.clone()));
+    (registry, clock)
+}
+
+fn consecutive(failure_threshold: u32) -> CircuitBreakerConfig {
+    CircuitBreakerConfig {
+        failure_threshold,
+        open_duration: Duration::from_secs(30),
+        half_open_successes: 2,
+        ..Default::default()
+    }
+}
+
+fn fail(registry: &CircuitBreakerRegistry, times: u32) {
+    for _ in 0..times {
+        registry.try_acquire(SERVICE).expect("call should be allowed").failure();
+    }
+}
+
+fn succeed(registry: &CircuitBreakerRegistry, times: u32) {
+    for _ in 0..times {
+        registry.try_acquire(SERVICE).expect("call should be allowed").success();
+    }
+}
+
+#[test]
+fn trips_after_consecutive_failures() {
+    let (registry, _clock) = registry(consecutive(3));
+
+    fail(&registry, 2);
+    succeed(&registry, 1);
+    fail(&registry, 2);
+    assert_eq!(registry.state(SERVICE), CircuitState::Closed);
+
+    fail(&registry, 1);
+    assert_eq!(registry.state(SERVICE), CircuitState::Open);
+    assert!(registry.try_acquire(SERVICE).is_none());
+}
+
+#[test]
+fn half_open_after_open_duration_with_bounded_probes() {
+    let (registry, clock) = registry(consecutive(1));
+    fail(&registry, 1);
+
+    clock.advance(Duration::from_secs(29));
+    assert!(registry.try_acquire(SERVICE).is_none());
+
+    clock.advance(Duration::from_secs(1));
+    assert_eq!(registry.state(SERVICE), CircuitState::HalfOpen);
+
+    let first = registry.try_acquire(SERVICE).expect("first probe");
+    let second = registry.try_acquire(SERVICE).expect("second probe");
+    assert!(registry.try_acquire(SERVICE).is_none(), "only two probes may be in flight");
+
+    // An abandoned probe gives its slot back without counting as an outcome
+    drop(first);
+    let third = registry.try_acquire(SERVICE).expect("released slot is reusable");
+    assert!(registry.try_acquire(SERVICE).is_none());
+
+    second.success();
+    assert_eq!(registry.state(SERVICE), CircuitState::HalfOpen);
+    third.success();
+    assert_eq!(registry.state(SERVICE), CircuitState::Closed);
+}
+
+#[test]
+fn successes_before_opening_do_not_close_the_circuit() {
+    let (registry, clock) = registry(consecutive(1));
+
+    // A slow call that started while closed and finishes after the trip
+    let slow = registry.try_acquire(SERVICE).unwrap();
+    succeed(&registry, 5);
+    fail(&registry, 1);
+    assert_eq!(registry.state(SERVICE), CircuitState::Open);
+
+    clock.advance(Duration::from_secs(30));
+    let probe = registry.try_acquire(SERVICE).unwrap();
+    slow.success();
+    assert_eq!(registry.state(SERVICE), CircuitState::HalfOpen);
+
+    probe.success();
+    assert_eq!(registry.state(SERVICE), CircuitState::HalfOpen, "needs two probe successes");
+}
+
+#[test]
+fn probe_failure_reopens() {
+    let (registry, clock) = registry(consecutive(1));
+    fail(&registry, 1);
+    clock.advance(Duration::from_secs(30));
+
+    registry.try_acquire(SERVICE).unwrap().failure();
+    assert_eq!(registry.state(SERVICE), CircuitState::Open);
+
+    clock.advance(Duration::from_secs(29));
+    assert!(registry.try_acquire(SERVICE).is_none());
+    clock.advance(Duration::from_secs(1));
+    assert!(registry.try_acquire(SERVICE).is_some());
+}
+
+#[test]
+fn failure_rate_trips_over_sliding_window() {
+    let (registry, _clock) = registry(CircuitBreakerConfig {
+        failure_rate_threshold: Some(50),
+        window_size: 20,
+        min_calls: 10,
+        ..consecutive(0)
+    });
+
+    // 4 of 8 failed: 50%, but below the minimum number of calls
+    for _ in 0..4 {
+        succeed(&registry, 1);
+        fail(&registry, 1);
+    }
+    assert_eq!(registry.state(SERVICE), CircuitState::Closed);
+
+    // 9 of the last 20 failed once the oldest outcomes slide out
+    succeed(&registry, 12);
+    fail(&registry, 9);
+    assert_eq!(registry.state(SERVICE), CircuitState::Closed);
+    assert_eq!(registry.snapshot(SERVICE).window_calls, 20);
+
+    fail(&registry, 1);
+    assert_eq!(registry.state(SERVICE), CircuitState::Open);
+}
+
+#[test]
+fn forced_states_hold_until_reset() {
+    let (registry, clock) = registry(consecutive(1));
+
+    registry.force(SERVICE, CircuitState::Open);
+    clock.advance(Duration::from_secs(3600));
+    assert!(registry.try_acquire(SERVICE).is_none());
+
+    registry.force(SERVICE, CircuitState::Closed);
+    fail(&registry, 5);
+    assert_eq!(registry.state(SERVICE), CircuitState::Closed);
+
+    registry.reset(SERVICE);
+    let snapshot = registry.snapshot(SERVICE);
+    assert_eq!(snapshot.forced, None);
+    assert_eq!(snapshot.consecutive_failures, 0);
+
+    fail(&registry, 1);
+    assert_eq!(registry.state(SERVICE), CircuitState::Open);
+}
+
+#[test]
+fn transitions_are_counted_by_state() {
+    let service = "metrics-probe";
+    let (registry, clock) = registry(consecutive(1));
+
+    registry.try_acquire(service).expect("closed").failure();
+    clock.advance(Duration::from_secs(30));
+    registry.try_acquire(service).expect("probe").failure();
+
+    let cache = ResponseCache::new(CacheConfig::default());
+    let body = METRICS.render(&cache.stats(), &[]);
+    for (from, to, count) in [("closed", "open", 1), ("open", "half_open", 1), ("half_open", "open", 1)] {
+        let line = format!(
+            "circuit_breaker_transitions_total{{from=\"{}\",service=\"{}\",to=\"{}\"}} {}",
+            from, service, to, count
+        );
+        assert!(body.contains(&line), "missing {}", line);
+    }
+}
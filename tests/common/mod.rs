@@ -0,0 +1,121 @@
+//! Shared setup for tests that drive the real `v1_router`
+
+use axum::http::StatusCode;
+use axum::Router;
+use std::sync::{Arc, Once};
+use std::time::Duration;
+
+use api_backend::clients::{
+    AuthClient, DataConnectorClient, EnhancedGraphClient, GrpcClients, McpClient, RelationGraphClient,
+    UnifiedProcessorClient,
+};
+use api_backend::middleware::{
+    AuthCache, AuthLayer, CacheConfig, CircuitBreakerRegistry, InMemoryRateLimitStore, JwtVerifier,
+    PolicyRegistry, RateLimitConfig, ResponseCache,
+};
+use api_backend::models::WorkspaceMembership;
+use api_backend::routes::v1::{v1_router, AppState};
+use api_backend::store::Stores;
+use api_backend::webhook_dispatch::WebhookDispatcher;
+use api_backend::Config;
+
+const JWT_SECRET: &str = "route-test-secret";
+
+/// User, and workspace, the test token is issued for
+pub const TEST_USER: &str = "route-test";
+
+/// Nothing listens here
+const CLOSED: &str = "127.0.0.1:1";
+
+/// Answer for requests that match no route
+pub const UNROUTED: StatusCode = StatusCode::IM_A_TEAPOT;
+
+fn config() -> Config {
+    static ENV: Once = Once::new();
+    ENV.call_once(|| {
+        for name in [
+            "AUTH_MIDDLEWARE_GRPC_ADDR",
+            "DATA_CONNECTOR_GRPC_ADDR",
+            "RELATION_GRAPH_GRPC_ADDR",
+            "MCP_SERVER_GRPC_ADDR",
+            "FEATURE_TOGGLE_GRPC_ADDR",
+            "UNIFIED_PROCESSOR_GRPC_ADDR",
+            "ENHANCED_GRAPH_URL",
+        ] {
+            std::env::set_var(name, CLOSED);
+        }
+        std::env::set_var("DATABASE_URL", format!("postgres://test@{}/test", CLOSED));
+        std::env::set_var("AGENT_KEY_ENCRYPTION_KEY", "00".repeat(32));
+        std::env::set_var("JWT_SECRET", JWT_SECRET);
+    });
+    Config::from_env().expect("test configuration")
+}
+
+/// Application state with every downstream service unreachable
+pub fn state() -> AppState {
+    let config = config();
+    let breaker = Arc::new(CircuitBreakerRegistry::new(config.circuit_breaker.clone()));
+    let auth_client = AuthClient::new(&config.auth_middleware_url, breaker.clone()).unwrap();
+    let data_connector_client =
+        Arc::new(DataConnectorClient::new(&config.data_connector_url, breaker.clone()).unwrap());
+    let response_cache = Arc::new(ResponseCache::new(CacheConfig::default()));
+    // auth-middleware is unreachable, so the test user's workspaces are cached up front
+    let auth_cache = AuthCache::new(response_cache.clone(), Duration::from_secs(3600));
+    auth_cache.insert_memberships(
+        TEST_USER,
+        &[WorkspaceMembership { workspace_id: TEST_USER.to_string(), role: None, is_default: true }],
+    );
+    let auth_layer = AuthLayer::new(auth_client.clone(), JwtVerifier::new(&config).unwrap(), auth_cache, false);
+    let stores = Stores::in_memory();
+    let closed = || format!("http://{}", CLOSED);
+
+    AppState {
+        db: sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(100))
+            .connect_lazy(&config.database_url)
+            .unwrap(),
+        auth_client: Arc::new(auth_client),
+        data_connector_client,
+        relation_graph_client: Arc::new(RelationGraphClient::new(&config.relation_graph_url, breaker.clone()).unwrap()),
+        mcp_client: Arc::new(McpClient::new(&config.mcp_server_url, breaker.clone()).unwrap()),
+        unified_processor_client: Arc::new(
+            UnifiedProcessorClient::new(&config.unified_processor_url, breaker.clone()).unwrap(),
+        ),
+        enhanced_graph_client: Arc::new(EnhancedGraphClient::new(&config.enhanced_graph_url, breaker.clone()).unwrap()),
+        auth_layer,
+        rate_limit: RateLimitConfig::new(
+            Arc::new(InMemoryRateLimitStore::new()),
+            PolicyRegistry::load(&config).unwrap(),
+            true,
+        ),
+        event_producer: None,
+        circuit_breaker: breaker,
+        response_cache,
+        grpc_clients: GrpcClients::connect_lazy(closed(), closed(), closed(), closed(), closed(), closed(), closed())
+            .unwrap(),
+        webhook_dispatcher: WebhookDispatcher::new(&config, stores.subscriptions.clone()).unwrap(),
+        stores,
+        config: Arc::new(config),
+    }
+}
+
+/// `v1_router` over `state`, answering unrouted requests with `UNROUTED`
+pub fn app(state: AppState) -> Router {
+    v1_router(state).fallback(|| async { UNROUTED })
+}
+
+/// Token for `TEST_USER` with the admin and user roles
+pub fn admin_token() -> String {
+    let claims = serde_json::json!({
+        "sub": TEST_USER,
+        "roles": ["admin", "user"],
+        "workspace_id": TEST_USER,
+        "exp": chrono::Utc::now().timestamp() + 3600,
+    });
+    jsonwebtoken::encode(
+        &jsonwebtoken::Header::default(),
+        &claims,
+        &jsonwebtoken::EncodingKey::from_secret(JWT_SECRET.as_bytes()),
+    )
+    .unwrap()
+}
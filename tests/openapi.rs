@@ -0,0 +1,166 @@
+//! The OpenAPI document describes the routes `v1_router` mounts
+//!
+//! axum cannot list a router's routes, so requests are sent through the real
+//! router instead: each documented operation must reach a route (not the
+//! fallback) that accepts its method, and other methods on a documented path
+//! must be refused with 405. Requests carry an admin token so auth does not
+//! answer in the router's place; downstream services point at a closed port,
+//! so handlers that do run fail fast.
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, Method, StatusCode};
+use axum::Router;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use tower::ServiceExt;
+
+use api_backend::openapi::document;
+
+mod common;
+use common::{admin_token, UNROUTED};
+
+const METHODS: &[&str] = &["get", "post", "put", "delete", "patch"];
+
+fn app() -> Router {
+    common::app(common::state())
+}
+
+/// Documented (method, path) pairs, with axum's `:param` syntax
+fn documented_routes() -> BTreeSet<(String, String)> {
+    let paths = document()["paths"].as_object().expect("paths object");
+    let mut routes = BTreeSet::new();
+    for (path, item) in paths {
+        let path = path.replace('{', ":").replace('}', "");
+        for method in item.as_object().expect("path item object").keys() {
+            routes.insert((method.clone(), path.clone()));
+        }
+    }
+    routes
+}
+
+/// Status the router gives an admin's request, with every path parameter set to `x`
+async fn route_status(app: &Router, method: &str, path: &str) -> StatusCode {
+    let uri: String = path
+        .split('/')
+        .map(|segment| if segment.starts_with(':') { "x" } else { segment })
+        .collect::<Vec<_>>()
+        .join("/");
+    let request = Request::builder()
+        .method(Method::from_bytes(method.to_uppercase().as_bytes()).unwrap())
+        .uri(uri)
+        .header(header::AUTHORIZATION, format!("Bearer {}", admin_token()))
+        .body(Body::empty())
+        .unwrap();
+    app.clone().oneshot(request).await.unwrap().status()
+}
+
+#[tokio::test]
+async fn every_operation_has_a_route() {
+    let app = app();
+    let mut missing = Vec::new();
+    for (method, path) in documented_routes() {
+        let status = route_status(&app, &method, &path).await;
+        if status == UNROUTED || status == StatusCode::METHOD_NOT_ALLOWED {
+            missing.push(format!("{} {} ({})", method, path, status));
+        }
+    }
+    assert!(missing.is_empty(), "OpenAPI operations without a route: {:?}", missing);
+}
+
+#[tokio::test]
+async fn documented_paths_have_no_undocumented_methods() {
+    let app = app();
+    let documented = documented_routes();
+    // Service identity is checked before routing within its group, so it
+    // answers for any method there
+    let paths: BTreeSet<&String> = documented
+        .iter()
+        .map(|(_, path)| path)
+        .filter(|path| !path.starts_with("/v1/internal/"))
+        .collect();
+
+    let mut undocumented = Vec::new();
+    for path in paths {
+        for method in METHODS {
+            if documented.contains(&(method.to_string(), path.clone())) {
+                continue;
+            }
+            let status = route_status(&app, method, path).await;
+            if status != StatusCode::METHOD_NOT_ALLOWED {
+                undocumented.push(format!("{} {} ({})", method, path, status));
+            }
+        }
+    }
+    assert!(undocumented.is_empty(), "routes without an OpenAPI operation: {:?}", undocumented);
+}
+
+#[tokio::test]
+async fn unknown_paths_are_not_routed() {
+    // Guards against the checks above passing because everything matches
+    let app = app();
+    assert_eq!(route_status(&app, "get", "/v1/no-such-route").await, UNROUTED);
+    assert_eq!(route_status(&app, "delete", "/health").await, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+#[tokio::test]
+async fn docs_page_pins_swagger_ui() {
+    let request = Request::builder().uri("/docs").body(Body::empty()).unwrap();
+    let response = app().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let page = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let page = String::from_utf8(page.to_vec()).unwrap();
+
+    assert!(page.contains("swagger-ui-dist@5.17.14/"));
+    assert_eq!(page.matches("integrity=\"sha384-").count(), 2);
+    assert_eq!(page.matches("crossorigin=\"anonymous\"").count(), 2);
+}
+
+#[test]
+fn references_resolve() {
+    fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(target)) = map.get("$ref") {
+                    found.push(target);
+                }
+                map.values().for_each(|v| refs(v, found));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+            _ => {}
+        }
+    }
+
+    let document = document();
+    assert_eq!(document["openapi"], "3.1.0");
+
+    let mut found = Vec::new();
+    refs(document, &mut found);
+    assert!(!found.is_empty());
+    let unresolved: Vec<_> = found
+        .into_iter()
+        .filter(|target| {
+            target
+                .strip_prefix('#')
+                .and_then(|pointer| document.pointer(pointer))
+                .is_none()
+        })
+        .collect();
+    assert!(unresolved.is_empty(), "unresolved references: {:?}", unresolved);
+}
+
+#[test]
+fn each_route_is_spelled_once() {
+    // Two spellings of one path (`:id` vs `:source_id`) would be two paths
+    let mut seen: HashMap<String, String> = HashMap::new();
+    for path in document()["paths"].as_object().unwrap().keys() {
+        let shape = path
+            .split('/')
+            .map(|s| if s.starts_with('{') { "{}" } else { s })
+            .collect::<Vec<_>>()
+            .join("/");
+        if let Some(other) = seen.insert(shape, path.clone()) {
+            panic!("{} and {} describe the same route", other, path);
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Permission checks run before the response cache
+//!
+//! A response cached for one credential must not be served to another
+//! credential of the same user that lacks the route's permission.
+
+use axum::body::Body;
+use axum::extract::Request;
+use axum::http::{header, StatusCode};
+use axum::Router;
+use std::time::Duration;
+use tower::ServiceExt;
+
+use api_backend::middleware::auth_cache::Credential;
+use api_backend::middleware::ResponseCache;
+use api_backend::models::{ApiKeyInfo, User};
+
+mod common;
+use common::{admin_token, TEST_USER};
+
+/// API key of `TEST_USER` scoped to `sources:read` only
+const NARROW_KEY: &str = "narrow-api-key";
+
+/// Cached GET routes behind permissions an API key with only `sources:read` lacks
+const ROUTES: &[&str] = &["/v1/entities/x", "/v1/mcp/capabilities", "/v1/compliance/dashboard"];
+
+fn app() -> (Router, std::sync::Arc<ResponseCache>) {
+    let state = common::state();
+    let info = ApiKeyInfo {
+        id: "narrow".to_string(),
+        user_id: TEST_USER.to_string(),
+        name: "narrow".to_string(),
+        scopes: vec!["sources:read".to_string()],
+        created_at: "2026-01-01T00:00:00Z".to_string(),
+        expires_at: None,
+    };
+    let user = User {
+        id: TEST_USER.to_string(),
+        email: "narrow@example.com".to_string(),
+        name: None,
+        picture: None,
+        roles: vec![],
+        scopes: info.scopes.clone(),
+        workspace_id: None,
+    };
+    // auth-middleware is unreachable, so the key's verification is cached up front
+    state.auth_layer.cache.insert(Credential::ApiKey(NARROW_KEY), &user, Some(&info), None);
+    let cache = state.response_cache.clone();
+    (common::app(state), cache)
+}
+
+/// Stand in for the response a full-scope caller's request stored
+fn fill(cache: &ResponseCache, path: &str) {
+    let key = ResponseCache::build_key("GET", path, None, Some(TEST_USER), Some(TEST_USER), None);
+    cache.set(
+        ResponseCache::namespace_for_path(path),
+        &key,
+        b"{\"cached\":true}".to_vec(),
+        200,
+        "application/json",
+        Duration::from_secs(60),
+    );
+}
+
+async fn get(app: &Router, path: &str, credential: (header::HeaderName, String)) -> (StatusCode, Option<String>) {
+    let request = Request::builder()
+        .uri(path)
+        .header(credential.0, credential.1)
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let cache = response
+        .headers()
+        .get("X-Cache")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    (response.status(), cache)
+}
+
+#[tokio::test]
+async fn narrow_key_is_refused_a_response_cached_for_a_full_token() {
+    let (app, cache) = app();
+    let full = (header::AUTHORIZATION, format!("Bearer {}", admin_token()));
+    let narrow = (header::HeaderName::from_static("x-api-key"), NARROW_KEY.to_string());
+
+    for path in ROUTES {
+        fill(&cache, path);
+        assert_eq!(
+            get(&app, path, full.clone()).await,
+            (StatusCode::OK, Some("HIT".to_string())),
+            "{} should be served from the cache",
+            path
+        );
+        assert_eq!(get(&app, path, narrow.clone()).await.0, StatusCode::FORBIDDEN, "{}", path);
+    }
+}